@@ -0,0 +1,130 @@
+use crate::{lerp_f64, Easing};
+
+/// A point of an [`AutomationCurve`].
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Breakpoint {
+    /// Time of the point in seconds. See [`AutomationTime`].
+    pub time: f64,
+    /// Value of the parameter at this point.
+    pub value: f64,
+    /// Easing used between this point and the next one.
+    pub easing: Easing,
+}
+
+impl Breakpoint {
+    /// Create a new [`Breakpoint`].
+    #[inline]
+    pub const fn new(time: f64, value: f64, easing: Easing) -> Self {
+        Self {
+            time,
+            value,
+            easing,
+        }
+    }
+}
+
+/// A list of [`Breakpoint`]s that describe how a parameter changes over
+/// time. Can be applied to a sound with [`crate::Sound::set_automation`].
+///
+/// Before the first point the curve has the value of the first point, after
+/// the last point it has the value of the last point.
+///
+/// With the `serde` feature, curves are (de)serialized as a list of
+/// breakpoints, so they can be authored in external tools.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(from = "Vec<Breakpoint>", into = "Vec<Breakpoint>")
+)]
+pub struct AutomationCurve {
+    /// Breakpoints sorted by time.
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl From<Vec<Breakpoint>> for AutomationCurve {
+    fn from(breakpoints: Vec<Breakpoint>) -> Self {
+        Self::new(breakpoints)
+    }
+}
+
+impl From<AutomationCurve> for Vec<Breakpoint> {
+    fn from(curve: AutomationCurve) -> Self {
+        curve.breakpoints
+    }
+}
+
+impl AutomationCurve {
+    /// Create a new [`AutomationCurve`] from a list of breakpoints. The
+    /// breakpoints don't have to be sorted.
+    pub fn new(mut breakpoints: Vec<Breakpoint>) -> Self {
+        breakpoints.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { breakpoints }
+    }
+
+    /// Add a breakpoint to the curve.
+    pub fn push(&mut self, breakpoint: Breakpoint) {
+        let index = self
+            .breakpoints
+            .partition_point(|point| point.time <= breakpoint.time);
+        self.breakpoints.insert(index, breakpoint);
+    }
+
+    /// Return the curve with a breakpoint added to it.
+    #[inline]
+    pub fn with_point(mut self, time: f64, value: f64, easing: Easing) -> Self {
+        self.push(Breakpoint::new(time, value, easing));
+        self
+    }
+
+    /// Return the breakpoints of the curve, sorted by time.
+    #[inline]
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Return the value of the curve at the given time. Returns [`None`] if
+    /// the curve has no breakpoints.
+    pub fn value(&self, time: f64) -> Option<f64> {
+        let index = self.breakpoints.partition_point(|point| point.time <= time);
+        if index == 0 {
+            return self.breakpoints.first().map(|point| point.value);
+        }
+        let Some(next) = self.breakpoints.get(index) else {
+            return self.breakpoints.last().map(|point| point.value);
+        };
+
+        let prev = &self.breakpoints[index - 1];
+        let t = (time - prev.time) / (next.time - prev.time);
+        let t = prev.easing.apply(t as f32) as f64;
+        Some(lerp_f64(prev.value, next.value, t))
+    }
+}
+
+/// The sound parameter an [`AutomationCurve`] controls.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AutomationTarget {
+    /// The volume of the sound.
+    Volume,
+    /// The panning of the sound. See [`crate::Sound::set_panning`].
+    Panning,
+    /// The playback rate of the sound, as a factor of the original speed.
+    PlaybackRate,
+    /// The pitch of the sound in semitones. Controls the playback rate.
+    Pitch,
+}
+
+/// The clock an [`AutomationCurve`] follows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AutomationTime {
+    /// The time is the position of the sound in seconds. The curve follows
+    /// seeks and loops of the sound (default).
+    #[default]
+    SoundPosition,
+    /// The time is the amount of seconds that were rendered since the curve
+    /// was set, like the timers of commands.
+    RenderTime,
+}
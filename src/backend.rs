@@ -1,44 +1,51 @@
-use crate::{KaError, Renderer, RendererHandle};
+use crate::logging::{log_error, log_info, log_warn};
+use crate::{BufferTiming, KaError, Renderer, RendererHandle};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     FromSample, SampleFormat, SizedSample, StreamConfig,
 };
 use parking_lot::Mutex;
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// Specifies what device [`cpal`] should use.
 ///
 /// For example, if you want [`cpal`] to use the default OS audio device,
 /// use [`Device::Default`]. If you want select a device by name, use `Device::Name("device name".to_string())`.
 ///
-/// Use [`device_names`] to get all device names available on the system. The
+/// Use [`device_names`] to get all device names available on the system, or
+/// [`output_devices`] to tell apart devices that have the same name. The
 /// [`Device`] struct also has methods for finding a device by name and getting
 /// the default device as a [`Device::Custom`].
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub enum Device {
     /// Use the default OS audio device.
     #[default]
     Default,
-    /// Specify a device by name.
+    /// Specify a device by name. If several devices have the same name, the
+    /// first one is used, see [`Device::NameIndex`].
     Name(String),
+    /// Specify the n-th device (starting from 0) out of the devices that
+    /// have the given name. See [`output_devices`].
+    NameIndex(String, usize),
     /// Use a [`cpal::Device`].
     Custom(cpal::Device),
 }
 
 impl Device {
-    /// Finds a [`cpal`] audio output device ([`cpal::Device`]) by name.
+    /// Finds a [`cpal`] audio output device ([`cpal::Device`]) by name. If
+    /// several devices have the same name, the first one is returned.
     pub fn from_name(name: &str) -> Result<Self, KaError> {
-        let host = cpal::default_host();
         Ok(Self::Custom(
-            host.output_devices()?
-                .find(|d| device_name(d) == name)
-                .ok_or(KaError::NoOutputDevice)?,
+            Device::Name(name.to_string()).cpal_device(default_host()?)?,
         ))
     }
 
     /// Get the default device as [`Device::Custom`].
     pub fn default_device() -> Result<Self, KaError> {
-        let host = cpal::default_host();
+        let host = default_host()?;
         Ok(Self::Custom(
             host.default_output_device()
                 .ok_or(KaError::NoOutputDevice)?,
@@ -47,16 +54,25 @@ impl Device {
 
     /// Find the cpal device that matches [`Device`]'s criteries.
     ///
-    /// If the device could not be found, returns [`KaError::NoOutputDevice`].
+    /// If there is no default device, returns [`KaError::NoOutputDevice`].
+    /// If no device has the requested name (or not enough devices have it),
+    /// returns [`KaError::DeviceNotFound`].
     pub fn cpal_device(self, host: cpal::Host) -> Result<cpal::Device, KaError> {
         Ok(match self {
             Device::Default => host
                 .default_output_device()
                 .ok_or(KaError::NoOutputDevice)?,
-            Device::Name(name) => host
-                .output_devices()?
-                .find(|d| device_name(d) == name)
-                .ok_or(KaError::NoOutputDevice)?,
+            Device::Name(name) => Device::NameIndex(name, 0).cpal_device(host)?,
+            Device::NameIndex(name, index) => {
+                let mut matches: Vec<_> = host
+                    .output_devices()?
+                    .filter(|d| device_name(d) == name)
+                    .collect();
+                if index >= matches.len() {
+                    return Err(KaError::DeviceNotFound(name, index, matches.len()));
+                }
+                matches.swap_remove(index)
+            }
             Device::Custom(device) => device,
         })
     }
@@ -76,19 +92,89 @@ impl Device {
 
     /// Return the [cpal] device name.
     pub fn name(self) -> Result<String, KaError> {
-        Ok(self.cpal_device(cpal::default_host())?.name()?)
+        Ok(self.cpal_device(default_host()?)?.name()?)
+    }
+
+    /// Return all sample formats supported by this device for output.
+    pub fn supported_sample_formats(self, host: cpal::Host) -> Result<Vec<SampleFormat>, KaError> {
+        let mut formats = Vec::new();
+        for config in self.cpal_device(host)?.supported_output_configs()? {
+            if !formats.contains(&config.sample_format()) {
+                formats.push(config.sample_format());
+            }
+        }
+        Ok(formats)
     }
+
+    /// Return the highest precision sample format supported by this device.
+    /// See [`best_sample_format`]. The result can be used in
+    /// [`StreamSettings::sample_format`].
+    pub fn best_sample_format(self, host: cpal::Host) -> Result<SampleFormat, KaError> {
+        let formats = self.supported_sample_formats(host)?;
+        best_sample_format(formats.iter().copied()).ok_or(KaError::NoSupportedSampleFormat)
+    }
+}
+
+/// Pick the highest precision sample format out of `formats`: [`f32`] if
+/// available, then [`f64`], then the widest integer format. Formats that
+/// the [`Backend`] can't output are skipped.
+pub fn best_sample_format(formats: impl IntoIterator<Item = SampleFormat>) -> Option<SampleFormat> {
+    use SampleFormat::*;
+    // the first format in this list has the highest priority
+    const PRIORITY: [SampleFormat; 10] = [F32, F64, I64, U64, I32, U32, I16, U16, I8, U8];
+    formats
+        .into_iter()
+        .filter_map(|format| {
+            PRIORITY
+                .iter()
+                .position(|f| *f == format)
+                .map(|rank| (rank, format))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, format)| format)
 }
 
-/// Returns all device names available on the system.
+/// Returns all device names available on the system. Several devices can
+/// have the same name, see [`output_devices`].
 pub fn device_names() -> Result<Vec<String>, KaError> {
-    let host = cpal::default_host();
-    Ok(host.output_devices()?.map(|d| device_name(&d)).collect())
+    Ok(default_host()?
+        .output_devices()?
+        .map(|d| device_name(&d))
+        .collect())
+}
+
+/// Returns all output devices available on the system as
+/// [`Device::NameIndex`], in the order of [`device_names`]. Devices that
+/// have the same name are told apart by their index among them, so each
+/// returned [`Device`] selects the device it was listed for.
+pub fn output_devices() -> Result<Vec<Device>, KaError> {
+    let names = device_names()?;
+    Ok(names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            // how many devices before this one have the same name
+            let index = names[..i].iter().filter(|n| *n == name).count();
+            Device::NameIndex(name.clone(), index)
+        })
+        .collect())
+}
+
+/// Return [cpal]'s default host, or [`KaError::NoOutputDevice`] if no host
+/// is available (for example in a browser without Web Audio). cpal panics
+/// if its default host isn't available, so this is checked first. The
+/// default host is the only host that is always compiled in, so it is
+/// available whenever any host is.
+fn default_host() -> Result<cpal::Host, KaError> {
+    if cpal::available_hosts().is_empty() {
+        return Err(KaError::NoOutputDevice);
+    }
+    Ok(cpal::default_host())
 }
 
 #[inline]
 fn default_device_and_config() -> Result<(cpal::Device, StreamConfig), KaError> {
-    let host = cpal::default_host();
+    let host = default_host()?;
     let device = host
         .default_output_device()
         .ok_or(KaError::NoOutputDevice)?;
@@ -112,12 +198,28 @@ pub struct StreamSettings {
     pub sample_rate: Option<u32>,
     /// Audio buffer size (in samples). If [`None`], [`cpal`] provides the default value.
     pub buffer_size: Option<u32>,
+    /// What to do if the device doesn't support the requested buffer size.
+    pub buffer_size_policy: BufferSizePolicy,
+    /// Requested output latency. If set and [`StreamSettings::buffer_size`]
+    /// is [`None`], the buffer size is chosen to match this latency at the
+    /// stream's sample rate, clamped to the range supported by the device.
+    pub latency_target: Option<Duration>,
     /// Stream sample format. If [`None`], [`cpal`] provides the default value.
     pub sample_format: Option<SampleFormat>,
     /// Whether to check the stream for device changes/disconnections.
     pub check_stream: bool,
     /// Interval at which to check the stream for device changes/disconnections.
     pub check_stream_interval: Duration,
+    /// How to retry restarting the stream if it fails after a device
+    /// change/disconnection.
+    pub restart_policy: RestartPolicy,
+    /// Whether to request real-time priority for the audio thread. This
+    /// reduces dropouts under load. If the priority can't be elevated (for
+    /// example, if the process lacks the permissions), the stream runs with
+    /// the default priority.
+    ///
+    /// Required features: `thread-priority` (ignored otherwise)
+    pub realtime_priority: bool,
 }
 
 impl Default for StreamSettings {
@@ -126,47 +228,231 @@ impl Default for StreamSettings {
             channels: None,
             sample_rate: None,
             buffer_size: None,
+            buffer_size_policy: BufferSizePolicy::Clamp,
+            latency_target: None,
             sample_format: None,
             check_stream: true,
             check_stream_interval: Duration::from_millis(500),
+            restart_policy: RestartPolicy::default(),
+            realtime_priority: false,
         }
     }
 }
 
+impl StreamSettings {
+    /// Default settings with the given latency target. See
+    /// [`StreamSettings::latency_target`].
+    #[inline]
+    pub fn latency_target(latency: Duration) -> Self {
+        Self {
+            latency_target: Some(latency),
+            ..Default::default()
+        }
+    }
+
+    /// Settings for low latency (5 ms), at the cost of higher CPU usage and
+    /// a higher risk of dropouts.
+    #[inline]
+    pub fn low_latency() -> Self {
+        Self::latency_target(Duration::from_millis(5))
+    }
+
+    /// Default settings, the latency is chosen by the device.
+    #[inline]
+    pub fn default_latency() -> Self {
+        Self::default()
+    }
+
+    /// Return the default settings, keeping only the settings that don't
+    /// depend on the device (stream checking, the restart policy and the
+    /// thread priority). Used by the [`Backend`] when the requested settings
+    /// aren't supported by the device it falls back to.
+    #[inline]
+    pub fn fallback(&self) -> Self {
+        Self {
+            check_stream: self.check_stream,
+            check_stream_interval: self.check_stream_interval,
+            restart_policy: self.restart_policy,
+            realtime_priority: self.realtime_priority,
+            ..Default::default()
+        }
+    }
+
+    /// Settings for high latency (100 ms), which reduces CPU usage.
+    #[inline]
+    pub fn power_saving() -> Self {
+        Self::latency_target(Duration::from_millis(100))
+    }
+}
+
+/// Specifies what to do if the device doesn't support the buffer size
+/// requested in [`StreamSettings::buffer_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BufferSizePolicy {
+    /// Clamp the buffer size to the range supported by the device.
+    #[default]
+    Clamp,
+    /// Return [`KaError::UnsupportedBufferSize`].
+    Strict,
+}
+
+impl BufferSizePolicy {
+    /// Apply the policy to the requested buffer size. If the supported range
+    /// is unknown, the requested size is returned as is.
+    pub fn apply(self, size: u32, supported: &cpal::SupportedBufferSize) -> Result<u32, KaError> {
+        let cpal::SupportedBufferSize::Range { min, max } = *supported else {
+            return Ok(size);
+        };
+        match self {
+            Self::Clamp => Ok(size.clamp(min, max)),
+            Self::Strict if (min..=max).contains(&size) => Ok(size),
+            Self::Strict => Err(KaError::UnsupportedBufferSize(size, min, max)),
+        }
+    }
+}
+
+/// Controls how the [`Backend`] retries restarting the stream when no device
+/// is available, for example after the only device was unplugged.
+///
+/// After each failed attempt, the delay before the next one is doubled, up
+/// to [`RestartPolicy::max_delay`]. While the backend is retrying, sounds are
+/// not rendered; use [`crate::Mixer::set_pause_without_stream`] to choose
+/// whether the mixer time is frozen during that time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// Delay before the first retry.
+    pub initial_delay: Duration,
+    /// Maximum delay between retries.
+    pub max_delay: Duration,
+    /// Maximum amount of retries. If [`None`], the backend retries until a
+    /// device is available.
+    pub max_retries: Option<u32>,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            max_retries: None,
+        }
+    }
+}
+
+/// Why a stream stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamExit {
+    /// The stream was stopped with [`Backend::stop_stream`].
+    Stopped,
+    /// The device changed or was disconnected, the stream should be
+    /// restarted.
+    Restart,
+}
+
+/// An event reported by the [`Backend`]. See [`Backend::handle_events`].
+#[derive(Debug)]
+pub enum BackendEvent {
+    /// The device changed or was disconnected, and the stream is being
+    /// restarted.
+    StreamRestarted,
+    /// The stream could not be restarted with the requested device, so the
+    /// backend falls back to the default device. The requested settings are
+    /// kept if the default device supports them, otherwise only the
+    /// settings that don't depend on the device are kept. See
+    /// [`StreamSettings::fallback`].
+    FellBackToDefault(KaError),
+    /// An attempt to restart the stream failed. The backend will retry
+    /// according to the [`RestartPolicy`].
+    RestartFailed {
+        /// The number of the failed attempt, starting from 1.
+        attempt: u32,
+        /// Why the attempt failed.
+        error: KaError,
+    },
+}
+
 /// A wrapper around [`cpal`]'s stream. The [`Backend`] will check for device
 /// changes or disconnections, handle errors and manage the stream.
+///
+/// The streams are opened by an [`OutputStreamProvider`], [`CpalProvider`]
+/// by default. See [`Backend::with_provider`].
 #[derive(Default)]
-pub struct Backend {
+pub struct Backend<P: OutputStreamProvider = CpalProvider> {
     /// Stream error queue.
     pub error_queue: Arc<Mutex<Vec<cpal::StreamError>>>,
+    /// Backend event queue.
+    pub event_queue: Arc<Mutex<Vec<BackendEvent>>>,
+    /// Configuration of the running stream, or [`None`] if no stream is
+    /// running. Stored separately, as the backend is locked while the
+    /// stream is running.
+    pub stream_config: Arc<Mutex<Option<ActualStreamConfig>>>,
     /// The interval at which the stream should be checked.
     pub check_stream_interval: Duration,
     /// Whether the stream should be checked.
     pub check_stream: bool,
+    /// Whether to request real-time priority for the audio thread.
+    pub realtime_priority: bool,
     /// Whether to stop the stream at the next stream check.
     // TODO: how can we apply this faster?
     stop_stream: bool,
+    /// The device requested in [`Backend::start_audio_thread`]. Used when
+    /// the stream is restarted.
+    device: Device,
+    /// The settings requested in [`Backend::start_audio_thread`]. Used when
+    /// the stream is restarted.
+    settings: StreamSettings,
+    /// Opens the streams.
+    provider: P,
 }
 
 impl Backend {
     /// Creates a new [`Backend`].
     #[inline]
     pub fn new() -> Self {
+        Self::with_provider(CpalProvider)
+    }
+}
+
+impl<P: OutputStreamProvider> Backend<P> {
+    /// Creates a new [`Backend`] that opens its streams with `provider`.
+    #[inline]
+    pub fn with_provider(provider: P) -> Self {
         Self {
             error_queue: Arc::new(Mutex::new(Vec::new())),
+            event_queue: Arc::new(Mutex::new(Vec::new())),
+            stream_config: Arc::new(Mutex::new(None)),
             check_stream_interval: Duration::from_millis(500),
             check_stream: true,
+            realtime_priority: false,
             stop_stream: false,
+            device: Device::Default,
+            settings: StreamSettings::default(),
+            provider,
         }
     }
 
+    /// Return the provider that opens the streams.
+    #[inline]
+    pub fn provider(&self) -> &P {
+        &self.provider
+    }
+
     /// Handle all errors in the error queue.
     #[inline]
     pub fn handle_errors(&mut self, err_fn: impl FnMut(cpal::StreamError)) {
         self.error_queue.lock().drain(..).for_each(err_fn)
     }
 
+    /// Handle all events in the event queue.
+    #[inline]
+    pub fn handle_events(&mut self, event_fn: impl FnMut(BackendEvent)) {
+        self.event_queue.lock().drain(..).for_each(event_fn)
+    }
+
     /// Starts the audio thread.
+    ///
+    /// The device and settings are remembered, and are reused if the stream
+    /// has to be restarted after a device change or disconnection.
     pub fn start_audio_thread<R>(
         &mut self,
         device: Device,
@@ -176,68 +462,133 @@ impl Backend {
     where
         R: Renderer,
     {
-        // cpal will panic if no default host is present, we can't do anything
-        // about that
-        let host = cpal::default_host();
+        self.device = device.clone();
+        self.settings = settings;
+        let mut exit = self.open_stream(device, settings, renderer.clone())?;
+        while exit == StreamExit::Restart {
+            log_info!("restarting stream");
+            self.event_queue.lock().push(BackendEvent::StreamRestarted);
+            exit = self.restart_stream(renderer.clone())?;
+        }
+        Ok(())
+    }
 
-        // get output device
-        let device = device.cpal_device(host)?;
+    /// Restart the stream with the device and settings that were passed to
+    /// [`Backend::start_audio_thread`]. If that fails, fall back to the
+    /// default device, keeping the settings if possible. If no device is
+    /// available, retry
+    /// according to the [`RestartPolicy`].
+    fn restart_stream<R>(&mut self, renderer: RendererHandle<R>) -> Result<StreamExit, KaError>
+    where
+        R: Renderer,
+    {
+        let policy = self.settings.restart_policy;
+        let mut delay = policy.initial_delay;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let error = match self.reopen_stream(renderer.clone()) {
+                Ok(exit) => return Ok(exit),
+                Err(err) => err,
+            };
 
-        // get supported stream config
-        let default_config = device.default_output_config()?;
-        let sample_format = settings
-            .sample_format
-            .unwrap_or_else(|| default_config.sample_format());
+            if policy.max_retries.is_some_and(|max| attempt > max) {
+                log_error!("giving up restarting stream after {attempt} attempts: {error}");
+                return Err(error);
+            }
+            log_warn!("failed to restart stream (attempt {attempt}): {error}");
+            self.event_queue
+                .lock()
+                .push(BackendEvent::RestartFailed { attempt, error });
 
-        // create modified stream config (if `settings` has [`Some`] values)
-        let config = StreamConfig {
-            channels: settings
-                .channels
-                .unwrap_or_else(|| default_config.config().channels),
-            sample_rate: settings
-                .sample_rate
-                .map(cpal::SampleRate)
-                .unwrap_or_else(|| default_config.sample_rate()),
-            buffer_size: settings
-                .buffer_size
-                .map(cpal::BufferSize::Fixed)
-                .unwrap_or(cpal::BufferSize::Default),
+            std::thread::sleep(delay);
+            delay = (delay * 2).min(policy.max_delay);
+
+            // see if the stream was stopped while we were waiting
+            if self.stop_stream {
+                self.stop_stream = false;
+                return Ok(StreamExit::Stopped);
+            }
+        }
+    }
+
+    /// Make a single attempt at restarting the stream. See
+    /// [`Backend::restart_stream`].
+    fn reopen_stream<R>(&mut self, renderer: RendererHandle<R>) -> Result<StreamExit, KaError>
+    where
+        R: Renderer,
+    {
+        let err = match self.open_stream(self.device.clone(), self.settings, renderer.clone()) {
+            Err(err) => err,
+            exit => return exit,
         };
+        log_warn!("failed to open stream, falling back to the default device: {err}");
+        self.event_queue
+            .lock()
+            .push(BackendEvent::FellBackToDefault(err));
+
+        // keep the requested settings on the default device if possible
+        match self.open_stream(Device::Default, self.settings, renderer.clone()) {
+            Err(err) if self.settings != self.settings.fallback() => {
+                log_warn!("default device doesn't support the requested settings: {err}");
+                self.open_stream(Device::Default, self.settings.fallback(), renderer)
+            }
+            exit => exit,
+        }
+    }
+
+    /// Open a stream and block until it is stopped.
+    fn open_stream<R>(
+        &mut self,
+        device: Device,
+        settings: StreamSettings,
+        renderer: RendererHandle<R>,
+    ) -> Result<StreamExit, KaError>
+    where
+        R: Renderer,
+    {
+        let (stream, output, config) = self.provider.open_stream(
+            device,
+            &settings,
+            renderer.clone(),
+            self.error_queue.clone(),
+        )?;
+        *self.stream_config.lock() = Some(config);
 
         // update backend settings
         self.check_stream = settings.check_stream;
         self.check_stream_interval = settings.check_stream_interval;
+        self.realtime_priority = settings.realtime_priority;
 
         // check if this is a custom device
-        let custom_device =
-            if let Ok((default_device, default_config)) = default_device_and_config() {
-                device_name(&device) != device_name(&default_device)
-                    || config.sample_rate != default_config.sample_rate
-            } else {
-                false
-            };
+        let custom_device = self
+            .provider
+            .default_output()
+            .is_some_and(|default| default != output);
 
-        // start the stream for the requested sample format
-        use SampleFormat::*;
-        match sample_format {
-            I8 => self.start_stream::<i8, R>(&device, &config, renderer, custom_device)?,
-            I16 => self.start_stream::<i16, R>(&device, &config, renderer, custom_device)?,
-            // I24 => self.start_stream::<I24, R>(&device, &conf, I24.into(), renderer,custom_device)?,
-            I32 => self.start_stream::<i32, R>(&device, &config, renderer, custom_device)?,
-            // I48 => self.start_stream::<I48, R>(&device, &conf, I48.into(), renderer,custom_device)?,
-            I64 => self.start_stream::<i64, R>(&device, &config, renderer, custom_device)?,
-            U8 => self.start_stream::<u8, R>(&device, &config, renderer, custom_device)?,
-            U16 => self.start_stream::<u16, R>(&device, &config, renderer, custom_device)?,
-            // U24 => self.start_stream::<U24, R>(&device, &conf, U24.into(), renderer,custom_device)?,
-            U32 => self.start_stream::<u32, R>(&device, &config, renderer, custom_device)?,
-            // U48 => self.start_stream::<U48, R>(&device, &conf, U48.into(), renderer,custom_device)?,
-            U64 => self.start_stream::<u64, R>(&device, &config, renderer, custom_device)?,
-            F32 => self.start_stream::<f32, R>(&device, &config, renderer, custom_device)?,
-            F64 => self.start_stream::<f64, R>(&device, &config, renderer, custom_device)?,
-            sample_format => return Err(KaError::UnsupportedSampleFormat(sample_format)),
-        }
+        renderer.guard().on_stream_active(true);
 
-        Ok(())
+        // periodically check for device changes
+        loop {
+            std::thread::sleep(self.check_stream_interval);
+
+            // check stream
+            if self.check_stream && self.check_stream(&output, custom_device) {
+                drop(stream); // stop this stream so we can start a new one
+                *self.stream_config.lock() = None;
+                renderer.guard().on_stream_active(false);
+                return Ok(StreamExit::Restart);
+            }
+
+            // see if we should stop the stream
+            if self.stop_stream {
+                self.stop_stream = false;
+                drop(stream); // stop stream
+                *self.stream_config.lock() = None;
+                renderer.guard().on_stream_active(false);
+                return Ok(StreamExit::Stopped);
+            }
+        }
     }
 
     /// Stop the audio thread at the next stream check.
@@ -247,16 +598,12 @@ impl Backend {
     }
 
     /// Return true if the audio stream should be restarted.
-    fn check_stream(
-        &mut self,
-        device: &cpal::Device,
-        config: &cpal::StreamConfig,
-        custom_device: bool,
-    ) -> bool {
+    fn check_stream(&mut self, output: &OutputInfo, custom_device: bool) -> bool {
         // check for device disconnection
         let error_queue = self.error_queue.clone();
         for err in error_queue.lock().drain(..) {
             if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+                log_info!("device is not available anymore");
                 return true;
             }
         }
@@ -266,10 +613,13 @@ impl Backend {
         // being queried while a stream is playing
         #[cfg(not(target_os = "macos"))]
         if !custom_device {
-            if let Ok((default_device, default_config)) = default_device_and_config() {
-                if device_name(device) != device_name(&default_device)
-                    || config.sample_rate != default_config.sample_rate
-                {
+            if let Some(default) = self.provider.default_output() {
+                if default != *output {
+                    log_info!(
+                        "default device changed to \"{}\" ({} Hz)",
+                        default.device_name,
+                        default.sample_rate
+                    );
                     return true;
                 }
             }
@@ -277,82 +627,907 @@ impl Backend {
 
         false
     }
+}
 
-    /// Start the [`cpal`] stream.
-    fn start_stream<T, R>(
+/// Name and sample rate of an output device, as reported by an
+/// [`OutputStreamProvider`]. The [`Backend`] restarts the stream when the
+/// default output changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputInfo {
+    /// Name of the device.
+    pub device_name: String,
+    /// Sample rate of the stream, or the default sample rate of the device.
+    pub sample_rate: u32,
+}
+
+/// The configuration a stream was actually opened with, after the values
+/// that were left as [`None`] in the [`StreamSettings`] were filled in by
+/// the device. See [`crate::Mixer::stream_config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActualStreamConfig {
+    /// Name of the device.
+    pub device_name: String,
+    /// Number of output channels.
+    pub channels: u16,
+    /// Sample rate of the stream.
+    pub sample_rate: u32,
+    /// Sample format of the stream.
+    pub sample_format: SampleFormat,
+    /// Buffer size that was requested from the device.
+    /// [`cpal::BufferSize::Default`] means the device picks the size, see
+    /// [`crate::Mixer::output_latency`] for the latency of the buffers that
+    /// are actually rendered.
+    pub buffer_size: cpal::BufferSize,
+}
+
+/// Opens output streams for a [`Backend`]. The backend only handles
+/// restarts, fallbacks and device changes, so it can run on top of any
+/// provider. [`CpalProvider`] is used by default; other providers can
+/// output to custom APIs, or simulate devices without audio hardware.
+pub trait OutputStreamProvider: Send + 'static {
+    /// A running stream. The stream is stopped when it is dropped.
+    type Stream;
+
+    /// Open a stream on `device` with `settings` that renders audio with
+    /// `renderer`, and start playing it. Errors that happen while the stream
+    /// is running should be pushed to `error_queue`, the backend restarts
+    /// the stream on [`cpal::StreamError::DeviceNotAvailable`].
+    ///
+    /// Returns the stream, the output it plays on, and the configuration it
+    /// was opened with. See [`OutputCallback`] for rendering the buffers of
+    /// the stream.
+    fn open_stream<R: Renderer>(
         &mut self,
-        device: &cpal::Device,
-        config: &cpal::StreamConfig,
+        device: Device,
+        settings: &StreamSettings,
         renderer: RendererHandle<R>,
-        custom_device: bool,
-    ) -> Result<(), KaError>
+        error_queue: Arc<Mutex<Vec<cpal::StreamError>>>,
+    ) -> Result<(Self::Stream, OutputInfo, ActualStreamConfig), KaError>;
+
+    /// Return the current default output, or [`None`] if there is no
+    /// default device.
+    fn default_output(&self) -> Option<OutputInfo>;
+}
+
+/// Renders the buffers of an output stream with a [`Renderer`], calling
+/// the renderer's buffer hooks. Used in the stream callback of an
+/// [`OutputStreamProvider`].
+pub struct OutputCallback<R: Renderer> {
+    renderer: RendererHandle<R>,
+    channels: usize,
+    sample_rate: u32,
+    /// Samples of a single frame, allocated once for the whole stream.
+    channel_buffer: Vec<f32>,
+    /// Whether to request real-time priority for the thread that renders
+    /// the next buffer. See [`OutputCallback::with_realtime_priority`].
+    elevate_priority: bool,
+}
+
+impl<R: Renderer> OutputCallback<R> {
+    /// Create a new [`OutputCallback`] for a stream with the given amount of
+    /// channels and sample rate.
+    pub fn new(renderer: RendererHandle<R>, channels: usize, sample_rate: u32) -> Self {
+        Self {
+            renderer,
+            channels,
+            sample_rate,
+            channel_buffer: vec![0.0; channels],
+            elevate_priority: false,
+        }
+    }
+
+    /// Request real-time priority for the audio thread when the first
+    /// buffer is rendered, see [`StreamSettings::realtime_priority`]. If the
+    /// priority can't be elevated, the stream keeps the default priority.
+    ///
+    /// Required features: `thread-priority` (ignored otherwise)
+    #[inline]
+    pub fn with_realtime_priority(mut self, realtime_priority: bool) -> Self {
+        self.elevate_priority = realtime_priority;
+        self
+    }
+
+    /// Fill a buffer of interleaved samples. `output_latency` is the time
+    /// until the first frame of the buffer is heard.
+    pub fn render<T>(&mut self, data: &mut [T], output_latency: Duration)
     where
         T: SizedSample + FromSample<f32>,
-        R: Renderer,
     {
-        let channels = config.channels as usize; // number of channels
-        let sample_rate = config.sample_rate.0; // sample rate
-        let error_queue = self.error_queue.clone(); // stream error queue
-
-        // create a clone of the renderer handle so we can move it inside the
-        // stream closure
-        let renderer_moved = renderer.clone();
-
-        let stream = device.build_output_stream(
-            config,
-            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
-                for frame in data.chunks_exact_mut(channels) {
-                    // mix next frame
-                    let out = renderer_moved.guard().next_frame(sample_rate);
-
-                    // write to buffer
-                    if channels == 1 {
-                        // mix both channels
-                        frame[0] = T::from_sample((out.left + out.right) / 2.0);
-                    } else {
-                        frame[0] = T::from_sample(out.left);
-                        frame[1] = T::from_sample(out.right);
-
-                        // if there are more than 2 channels, send silence to them,
-                        // otherwise we might leave some garbage in there
-                        for channel in frame.iter_mut().skip(2) {
-                            *channel = T::from_sample(0.);
-                        }
-                    }
-                }
-                renderer_moved.guard().on_buffer(data);
-            },
-            move |err| {
-                // we got an error on stream, push it to the error queue
-                error_queue.lock().push(err)
-            },
-            None,
-        )?;
+        let channels = self.channels.max(1);
 
-        // start cpal's audio playback thread
-        stream.play()?;
+        // the callback runs on the thread of the stream, so the priority
+        // has to be requested from the callback itself
+        if self.elevate_priority {
+            self.elevate_priority = false;
+            elevate_current_thread();
+        }
 
-        // periodically check for device changes
-        loop {
-            std::thread::sleep(self.check_stream_interval);
+        // report when the first frame of this buffer will be heard
+        self.renderer.guard().on_buffer_timing(BufferTiming {
+            callback: Instant::now(),
+            frames: data.len() / channels,
+            sample_rate: self.sample_rate,
+            output_latency,
+        });
 
-            // check stream
-            if self.check_stream && self.check_stream(device, config, custom_device) {
-                drop(stream); // stop this stream so we can start a new one
-                return self.start_audio_thread(
-                    Device::Default,
-                    StreamSettings::default(),
-                    renderer,
-                );
+        for frame in data.chunks_exact_mut(channels) {
+            // mix next frame into all channels
+            self.renderer
+                .guard()
+                .next_frame_channels(self.sample_rate, &mut self.channel_buffer);
+
+            // write to buffer
+            for (out, sample) in frame.iter_mut().zip(&self.channel_buffer) {
+                *out = T::from_sample(*sample);
             }
+        }
+        self.renderer.guard().on_buffer(data);
+    }
+}
 
-            // see if we should stop the stream
-            if self.stop_stream {
-                self.stop_stream = false;
-                drop(stream); // stop stream
-                break;
+/// The default [`OutputStreamProvider`], which outputs to the devices of
+/// [`cpal`]'s default host.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpalProvider;
+
+impl OutputStreamProvider for CpalProvider {
+    type Stream = cpal::Stream;
+
+    fn open_stream<R: Renderer>(
+        &mut self,
+        device: Device,
+        settings: &StreamSettings,
+        renderer: RendererHandle<R>,
+        error_queue: Arc<Mutex<Vec<cpal::StreamError>>>,
+    ) -> Result<(Self::Stream, OutputInfo, ActualStreamConfig), KaError> {
+        let host = default_host()?;
+
+        // get output device
+        let device = device.cpal_device(host)?;
+
+        // get supported stream config
+        let default_config = device.default_output_config()?;
+        let sample_format = settings
+            .sample_format
+            .unwrap_or_else(|| default_config.sample_format());
+
+        // create modified stream config (if `settings` has [`Some`] values)
+        let mut config = StreamConfig {
+            channels: settings
+                .channels
+                .unwrap_or_else(|| default_config.config().channels),
+            sample_rate: settings
+                .sample_rate
+                .map(cpal::SampleRate)
+                .unwrap_or_else(|| default_config.sample_rate()),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        // pick the buffer size from the requested size or latency target
+        if let Some(size) = settings.buffer_size {
+            config.buffer_size = cpal::BufferSize::Fixed(
+                settings
+                    .buffer_size_policy
+                    .apply(size, default_config.buffer_size())?,
+            );
+        } else if let Some(latency) = settings.latency_target {
+            let size = (latency.as_secs_f64() * config.sample_rate.0 as f64).round() as u32;
+            config.buffer_size = cpal::BufferSize::Fixed(
+                BufferSizePolicy::Clamp.apply(size, default_config.buffer_size())?,
+            );
+        }
+
+        let callback =
+            OutputCallback::new(renderer, config.channels as usize, config.sample_rate.0)
+                .with_realtime_priority(settings.realtime_priority);
+
+        // start the stream for the requested sample format
+        use SampleFormat::*;
+        let stream = match sample_format {
+            I8 => build_stream::<i8, R>(&device, &config, callback, error_queue),
+            I16 => build_stream::<i16, R>(&device, &config, callback, error_queue),
+            // I24 => build_stream::<I24, R>(&device, &config, callback, error_queue),
+            I32 => build_stream::<i32, R>(&device, &config, callback, error_queue),
+            // I48 => build_stream::<I48, R>(&device, &config, callback, error_queue),
+            I64 => build_stream::<i64, R>(&device, &config, callback, error_queue),
+            U8 => build_stream::<u8, R>(&device, &config, callback, error_queue),
+            U16 => build_stream::<u16, R>(&device, &config, callback, error_queue),
+            // U24 => build_stream::<U24, R>(&device, &config, callback, error_queue),
+            U32 => build_stream::<u32, R>(&device, &config, callback, error_queue),
+            // U48 => build_stream::<U48, R>(&device, &config, callback, error_queue),
+            U64 => build_stream::<u64, R>(&device, &config, callback, error_queue),
+            F32 => build_stream::<f32, R>(&device, &config, callback, error_queue),
+            F64 => build_stream::<f64, R>(&device, &config, callback, error_queue),
+            sample_format => Err(KaError::UnsupportedSampleFormat(sample_format)),
+        }?;
+
+        let output = OutputInfo {
+            device_name: device_name(&device),
+            sample_rate: config.sample_rate.0,
+        };
+        let actual = ActualStreamConfig {
+            device_name: output.device_name.clone(),
+            channels: config.channels,
+            sample_rate: config.sample_rate.0,
+            sample_format,
+            buffer_size: config.buffer_size,
+        };
+        Ok((stream, output, actual))
+    }
+
+    fn default_output(&self) -> Option<OutputInfo> {
+        let (device, config) = default_device_and_config().ok()?;
+        Some(OutputInfo {
+            device_name: device_name(&device),
+            sample_rate: config.sample_rate.0,
+        })
+    }
+}
+
+/// Request real-time priority for the current thread. If we aren't allowed
+/// to elevate the priority, the thread keeps running with the default one.
+#[inline]
+fn elevate_current_thread() {
+    #[cfg(feature = "thread-priority")]
+    if let Err(err) =
+        thread_priority::set_current_thread_priority(thread_priority::ThreadPriority::Max)
+    {
+        log_warn!("failed to elevate the audio thread priority: {err:?}");
+    }
+}
+
+/// Build and start a [`cpal`] stream for the sample format `T`.
+fn build_stream<T, R>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut callback: OutputCallback<R>,
+    error_queue: Arc<Mutex<Vec<cpal::StreamError>>>,
+) -> Result<cpal::Stream, KaError>
+where
+    T: SizedSample + FromSample<f32>,
+    R: Renderer,
+{
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], info: &cpal::OutputCallbackInfo| {
+            let timestamp = info.timestamp();
+            let output_latency = timestamp
+                .playback
+                .duration_since(&timestamp.callback)
+                .unwrap_or_default();
+            callback.render(data, output_latency);
+        },
+        move |err| {
+            // we got an error on stream, push it to the error queue
+            log_error!("stream error: {err}");
+            error_queue.lock().push(err)
+        },
+        None,
+    )?;
+
+    // start cpal's audio playback thread
+    stream.play()?;
+    log_info!(
+        "started stream on \"{}\" ({} channels, {} Hz, {}, buffer size: {:?})",
+        device_name(device),
+        config.channels,
+        config.sample_rate.0,
+        T::FORMAT,
+        config.buffer_size
+    );
+    Ok(stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DefaultRenderer, Frame, Sound, SoundHandle};
+
+    /// A simulated output device.
+    struct MockDevice {
+        name: String,
+        sample_rate: u32,
+        formats: Vec<SampleFormat>,
+    }
+
+    /// Devices and streams of a [`MockProvider`], shared with the test.
+    #[derive(Default)]
+    struct MockState {
+        devices: Vec<MockDevice>,
+        /// Name of the default device.
+        default: Option<String>,
+        /// Sizes (in frames) of the buffers rendered when a stream starts.
+        buffer_sizes: Vec<usize>,
+        /// Buffers rendered by the streams.
+        buffers: Vec<Vec<f32>>,
+        /// Configurations of the streams that were opened, in order.
+        opened: Vec<ActualStreamConfig>,
+        /// Whether real-time priority was requested, for each opened stream.
+        realtime_priority: Vec<bool>,
+        /// Error queue of the last stream.
+        error_queue: Option<Arc<Mutex<Vec<cpal::StreamError>>>>,
+        /// Renders a buffer with the given amount of frames with the callback
+        /// of the last stream. See [`render_buffer`].
+        render: Option<Box<dyn FnMut(usize) -> Vec<f32> + Send>>,
+    }
+
+    impl MockState {
+        fn add_device(&mut self, name: &str, sample_rate: u32, formats: &[SampleFormat]) {
+            self.devices.push(MockDevice {
+                name: name.to_string(),
+                sample_rate,
+                formats: formats.to_vec(),
+            });
+        }
+
+        /// Remove a device and report it as unavailable to the stream.
+        fn unplug(&mut self, name: &str) {
+            self.devices.retain(|device| device.name != name);
+            if self.default.as_deref() == Some(name) {
+                self.default = None;
+            }
+            if let Some(error_queue) = &self.error_queue {
+                error_queue
+                    .lock()
+                    .push(cpal::StreamError::DeviceNotAvailable);
             }
         }
-        Ok(())
+    }
+
+    /// An [`OutputStreamProvider`] that simulates devices without audio
+    /// hardware. Streams render their buffers once when they are opened.
+    #[derive(Clone, Default)]
+    struct MockProvider(Arc<Mutex<MockState>>);
+
+    impl OutputStreamProvider for MockProvider {
+        type Stream = ();
+
+        fn open_stream<R: Renderer>(
+            &mut self,
+            device: Device,
+            settings: &StreamSettings,
+            renderer: RendererHandle<R>,
+            error_queue: Arc<Mutex<Vec<cpal::StreamError>>>,
+        ) -> Result<(Self::Stream, OutputInfo, ActualStreamConfig), KaError> {
+            let mut state = self.0.lock();
+            let name = match device {
+                Device::Default => state.default.clone(),
+                Device::Name(name) => Some(name),
+                _ => None,
+            };
+            let device = state
+                .devices
+                .iter()
+                .find(|device| Some(&device.name) == name.as_ref())
+                .ok_or(KaError::NoOutputDevice)?;
+
+            let sample_format = settings.sample_format.unwrap_or(device.formats[0]);
+            if !device.formats.contains(&sample_format) {
+                return Err(KaError::UnsupportedSampleFormat(sample_format));
+            }
+            let output = OutputInfo {
+                device_name: device.name.clone(),
+                sample_rate: settings.sample_rate.unwrap_or(device.sample_rate),
+            };
+            let actual = ActualStreamConfig {
+                device_name: output.device_name.clone(),
+                channels: settings.channels.unwrap_or(2),
+                sample_rate: output.sample_rate,
+                sample_format,
+                buffer_size: cpal::BufferSize::Default,
+            };
+
+            let mut callback =
+                OutputCallback::new(renderer, actual.channels as usize, actual.sample_rate)
+                    .with_realtime_priority(settings.realtime_priority);
+            for frames in state.buffer_sizes.clone() {
+                let mut buffer = vec![0.0; frames * actual.channels as usize];
+                callback.render(&mut buffer, Duration::ZERO);
+                state.buffers.push(buffer);
+            }
+            state.opened.push(actual.clone());
+            state.realtime_priority.push(settings.realtime_priority);
+            state.error_queue = Some(error_queue);
+            let channels = actual.channels as usize;
+            state.render = Some(Box::new(move |frames| {
+                let mut buffer = vec![0.0; frames * channels];
+                callback.render(&mut buffer, Duration::ZERO);
+                buffer
+            }));
+            Ok(((), output, actual))
+        }
+
+        fn default_output(&self) -> Option<OutputInfo> {
+            let state = self.0.lock();
+            let default = state.default.as_ref()?;
+            let device = state.devices.iter().find(|d| &d.name == default)?;
+            Some(OutputInfo {
+                device_name: device.name.clone(),
+                sample_rate: device.sample_rate,
+            })
+        }
+    }
+
+    /// A renderer that outputs the index of each frame, and records the
+    /// buffers it was asked to render.
+    #[derive(Clone, Default)]
+    struct CountingRenderer {
+        frames: u32,
+        buffers: Vec<usize>,
+        active: Vec<bool>,
+    }
+
+    impl Renderer for CountingRenderer {
+        fn next_frame(&mut self, _sample_rate: u32) -> Frame {
+            self.frames += 1;
+            Frame::from_mono((self.frames - 1) as f32)
+        }
+
+        fn on_buffer_timing(&mut self, timing: BufferTiming) {
+            self.buffers.push(timing.frames);
+        }
+
+        fn on_stream_active(&mut self, active: bool) {
+            self.active.push(active);
+        }
+    }
+
+    /// Settings that check the stream often and give up restarting it after
+    /// the first failed attempt.
+    fn test_settings() -> StreamSettings {
+        StreamSettings {
+            check_stream_interval: Duration::from_millis(1),
+            restart_policy: RestartPolicy {
+                initial_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(1),
+                max_retries: Some(0),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Run a backend on `provider` on another thread, and call `scenario`
+    /// while it runs. When `scenario` returns, all devices are unplugged, so
+    /// the backend gives up restarting the stream. Returns the events of the
+    /// backend and the renderer.
+    fn run(
+        provider: &MockProvider,
+        device: Device,
+        settings: StreamSettings,
+        scenario: impl FnOnce(),
+    ) -> (Vec<BackendEvent>, CountingRenderer) {
+        let renderer = RendererHandle::new(CountingRenderer::default());
+        let events = run_with(provider, device, settings, renderer.clone(), scenario);
+        let renderer = renderer.guard().clone();
+        (events, renderer)
+    }
+
+    /// Like [`run`], with any renderer. Returns the events of the backend.
+    fn run_with<R: Renderer>(
+        provider: &MockProvider,
+        device: Device,
+        settings: StreamSettings,
+        renderer: RendererHandle<R>,
+        scenario: impl FnOnce(),
+    ) -> Vec<BackendEvent> {
+        let mut backend = Backend::with_provider(provider.clone());
+        let events = backend.event_queue.clone();
+        let thread =
+            std::thread::spawn(move || backend.start_audio_thread(device, settings, renderer));
+
+        scenario();
+        let mut state = provider.0.lock();
+        let names: Vec<String> = state.devices.iter().map(|d| d.name.clone()).collect();
+        names.iter().for_each(|name| state.unplug(name));
+        drop(state);
+
+        let result = thread.join().unwrap();
+        assert!(matches!(result, Err(KaError::NoOutputDevice)));
+        let events = std::mem::take(&mut *events.lock());
+        events
+    }
+
+    /// Render a buffer of `frames` frames with the last stream, like the
+    /// device requesting it. Returns the rendered samples.
+    fn render_buffer(provider: &MockProvider, frames: usize) -> Vec<f32> {
+        let mut state = provider.0.lock();
+        let render = state.render.as_mut().expect("no stream was opened");
+        render(frames)
+    }
+
+    /// Wait until `condition` is true.
+    fn wait_until(mut condition: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Wait until `n` streams were opened.
+    fn wait_for_streams(provider: &MockProvider, n: usize) {
+        let start = Instant::now();
+        while provider.0.lock().opened.len() < n {
+            assert!(start.elapsed() < Duration::from_secs(5), "no stream opened");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn opened_devices(provider: &MockProvider) -> Vec<String> {
+        let state = provider.0.lock();
+        state.opened.iter().map(|c| c.device_name.clone()).collect()
+    }
+
+    #[test]
+    fn starting_without_devices_fails() {
+        let provider = MockProvider::default();
+        let mut backend = Backend::with_provider(provider.clone());
+        let renderer = RendererHandle::new(CountingRenderer::default());
+        let result = backend.start_audio_thread(Device::Default, test_settings(), renderer.clone());
+
+        assert!(matches!(result, Err(KaError::NoOutputDevice)));
+        assert!(provider.0.lock().opened.is_empty());
+        assert!(backend.stream_config.lock().is_none());
+        assert!(renderer.guard().active.is_empty());
+    }
+
+    #[test]
+    fn stream_config_follows_the_stream() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.add_device("headphones", 44100, &[SampleFormat::I16]);
+            state.default = Some("speakers".to_string());
+        }
+        let mut backend = Backend::with_provider(provider.clone());
+        let config = backend.stream_config.clone();
+        let renderer = RendererHandle::new(CountingRenderer::default());
+        let mut settings = test_settings();
+        settings.channels = Some(1);
+        let thread = std::thread::spawn(move || {
+            backend.start_audio_thread(Device::Default, settings, renderer)
+        });
+
+        // the fields that weren't requested are filled in by the device
+        let current = || config.lock().clone().map(|c| c.device_name);
+        wait_until(|| current().as_deref() == Some("speakers"));
+        let actual = config.lock().clone().unwrap();
+        assert_eq!(actual.channels, 1);
+        assert_eq!(actual.sample_rate, 48000);
+        assert_eq!(actual.sample_format, SampleFormat::F32);
+
+        // a restart reports the config of the new stream
+        provider.0.lock().default = Some("headphones".to_string());
+        wait_until(|| current().as_deref() == Some("headphones"));
+        let actual = config.lock().clone().unwrap();
+        assert_eq!(actual.sample_rate, 44100);
+        assert_eq!(actual.sample_format, SampleFormat::I16);
+
+        // and there is no config once the stream stopped
+        for name in ["speakers", "headphones"] {
+            provider.0.lock().unplug(name);
+        }
+        assert!(thread.join().unwrap().is_err());
+        assert!(config.lock().is_none());
+    }
+
+    #[test]
+    fn restarts_on_disconnect() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.add_device("headphones", 44100, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let device = Device::Name("headphones".to_string());
+        let (events, renderer) = run(&provider, device, test_settings(), || {
+            wait_for_streams(&provider, 1);
+            provider.0.lock().unplug("headphones");
+            wait_for_streams(&provider, 2);
+        });
+
+        assert_eq!(opened_devices(&provider), ["headphones", "speakers"]);
+        assert_eq!(provider.0.lock().opened[1].sample_rate, 48000);
+        assert!(matches!(events[0], BackendEvent::StreamRestarted));
+        assert!(matches!(
+            events[1],
+            BackendEvent::FellBackToDefault(KaError::NoOutputDevice)
+        ));
+        assert_eq!(renderer.active[..4], [true, false, true, false]);
+    }
+
+    #[test]
+    fn restarts_on_default_device_change() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.add_device("headphones", 44100, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let (events, _) = run(&provider, Device::Default, test_settings(), || {
+            wait_for_streams(&provider, 1);
+            provider.0.lock().default = Some("headphones".to_string());
+            wait_for_streams(&provider, 2);
+        });
+
+        assert_eq!(opened_devices(&provider), ["speakers", "headphones"]);
+        // the default device opened fine, so the next event is the restart
+        // after all devices are unplugged
+        assert!(matches!(events[0], BackendEvent::StreamRestarted));
+        assert!(matches!(events[1], BackendEvent::StreamRestarted));
+    }
+
+    /// A [`log::Log`] that keeps the messages, so tests can check them.
+    #[cfg(feature = "log")]
+    struct CaptureLogger(Mutex<Vec<String>>);
+
+    #[cfg(feature = "log")]
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let message = format!("{} {}", record.level(), record.args());
+            self.0.lock().push(message);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[cfg(feature = "log")]
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn default_device_change_is_logged() {
+        static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Info);
+
+        // other tests log too, so look for a device name only this test uses
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.add_device("logged headphones", 44100, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        run(&provider, Device::Default, test_settings(), || {
+            wait_for_streams(&provider, 1);
+            provider.0.lock().default = Some("logged headphones".to_string());
+            wait_for_streams(&provider, 2);
+        });
+
+        let messages = LOGGER.0.lock();
+        let changed = "INFO default device changed to \"logged headphones\" (44100 Hz)";
+        let position = |message: &str| messages.iter().position(|m| m == message);
+        let changed = position(changed).expect("device change wasn't logged");
+        // the restart is logged after the change that caused it
+        assert!(messages[changed + 1..]
+            .iter()
+            .any(|message| message == "INFO restarting stream"));
+    }
+
+    #[test]
+    fn custom_device_ignores_default_device_change() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.add_device("headphones", 44100, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let device = Device::Name("headphones".to_string());
+        run(&provider, device, test_settings(), || {
+            wait_for_streams(&provider, 1);
+            provider.0.lock().default = None;
+            std::thread::sleep(Duration::from_millis(50));
+        });
+        assert_eq!(opened_devices(&provider), ["headphones"]);
+    }
+
+    #[test]
+    fn falls_back_to_supported_format() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.add_device("interface", 96000, &[SampleFormat::I16, SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let settings = StreamSettings {
+            sample_format: Some(SampleFormat::I16),
+            sample_rate: Some(96000),
+            ..test_settings()
+        };
+        let device = Device::Name("interface".to_string());
+        let (events, _) = run(&provider, device, settings, || {
+            wait_for_streams(&provider, 1);
+            provider.0.lock().unplug("interface");
+            wait_for_streams(&provider, 2);
+        });
+
+        // the default device doesn't support the requested format, so only
+        // the device independent settings are kept
+        let opened = provider.0.lock().opened.clone();
+        assert_eq!(opened[0].sample_format, SampleFormat::I16);
+        assert_eq!(opened[0].sample_rate, 96000);
+        assert_eq!(opened[1].device_name, "speakers");
+        assert_eq!(opened[1].sample_format, SampleFormat::F32);
+        assert_eq!(opened[1].sample_rate, 48000);
+        assert!(matches!(events[1], BackendEvent::FellBackToDefault(_)));
+    }
+
+    #[test]
+    fn unsupported_format_fails_to_start() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let settings = StreamSettings {
+            sample_format: Some(SampleFormat::U8),
+            ..test_settings()
+        };
+        let mut backend = Backend::with_provider(provider.clone());
+        let renderer = RendererHandle::new(CountingRenderer::default());
+        let result = backend.start_audio_thread(Device::Default, settings, renderer);
+        assert!(matches!(
+            result,
+            Err(KaError::UnsupportedSampleFormat(SampleFormat::U8))
+        ));
+        assert!(backend.stream_config.lock().is_none());
+    }
+
+    #[test]
+    fn realtime_priority_is_requested_by_the_callback() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+            // rendered on the backend thread, which requests the priority
+            state.buffer_sizes = vec![64, 64];
+        }
+        let mut settings = test_settings();
+        settings.realtime_priority = true;
+        let (events, renderer) = run(&provider, Device::Default, settings, || {
+            wait_for_streams(&provider, 1);
+        });
+
+        assert_eq!(provider.0.lock().realtime_priority, [true]);
+        // whether or not the priority could be elevated, the stream started
+        // and rendered its buffers
+        assert_eq!(renderer.buffers, [64, 64]);
+        assert!(matches!(events[0], BackendEvent::StreamRestarted));
+
+        // the request is made once, when the first buffer is rendered
+        let callback =
+            OutputCallback::new(RendererHandle::new(CountingRenderer::default()), 2, 48000);
+        let mut callback = callback.with_realtime_priority(true);
+        assert!(callback.elevate_priority);
+        let handle = std::thread::spawn(move || {
+            callback.render(&mut [0.0f32; 128], Duration::ZERO);
+            callback.elevate_priority
+        });
+        assert!(!handle.join().unwrap());
+    }
+
+    #[test]
+    fn mixer_pauses_while_no_stream_is_active() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 1000, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let renderer = RendererHandle::new(DefaultRenderer::default());
+        renderer.guard().pause_without_stream = true;
+        let sound = SoundHandle::new(Sound::from_mono_samples(1000, &[0.5; 10000]));
+        renderer.guard().add_sound(sound.clone());
+
+        // keep retrying while the device is unplugged
+        let mut settings = test_settings();
+        settings.restart_policy.max_retries = Some(2000);
+
+        let start = sound.index();
+        let events = run_with(
+            &provider,
+            Device::Default,
+            settings,
+            renderer.clone(),
+            || {
+                // active: the sound plays
+                wait_for_streams(&provider, 1);
+                assert!(renderer.guard().stream_active);
+                render_buffer(&provider, 100);
+                assert_eq!(sound.index(), start + 100);
+
+                // gap: a late callback of the old stream doesn't advance the sound
+                provider.0.lock().unplug("speakers");
+                wait_until(|| !renderer.guard().stream_active);
+                assert!(render_buffer(&provider, 100).iter().all(|&s| s == 0.0));
+                assert_eq!(sound.index(), start + 100);
+
+                // active again: the sound continues where it stopped
+                {
+                    let mut state = provider.0.lock();
+                    state.add_device("speakers", 1000, &[SampleFormat::F32]);
+                    state.default = Some("speakers".to_string());
+                }
+                wait_for_streams(&provider, 2);
+                assert!(renderer.guard().stream_active);
+                render_buffer(&provider, 100);
+                assert_eq!(sound.index(), start + 200);
+            },
+        );
+
+        assert!(matches!(events[0], BackendEvent::StreamRestarted));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, BackendEvent::RestartFailed { .. })));
+        assert!(!renderer.guard().stream_active);
+    }
+
+    #[test]
+    fn late_buffers_are_counted_as_underruns() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+        }
+        let renderer = RendererHandle::new(DefaultRenderer::default());
+
+        // 100 ms buffers, so the "immediate" callbacks have plenty of slack
+        let underruns = || renderer.guard().underruns;
+        run_with(
+            &provider,
+            Device::Default,
+            test_settings(),
+            renderer.clone(),
+            || {
+                wait_for_streams(&provider, 1);
+                render_buffer(&provider, 4800);
+                render_buffer(&provider, 4800);
+                assert_eq!(underruns(), 0);
+
+                // the device waited for the buffer after the previous one ended
+                std::thread::sleep(Duration::from_millis(300));
+                render_buffer(&provider, 4800);
+                assert_eq!(underruns(), 1);
+                render_buffer(&provider, 4800);
+                assert_eq!(underruns(), 1);
+
+                std::thread::sleep(Duration::from_millis(300));
+                render_buffer(&provider, 4800);
+                assert_eq!(underruns(), 2);
+            },
+        );
+    }
+
+    #[test]
+    fn renders_buffers_of_varying_sizes() {
+        let provider = MockProvider::default();
+        {
+            let mut state = provider.0.lock();
+            state.add_device("speakers", 48000, &[SampleFormat::F32]);
+            state.default = Some("speakers".to_string());
+            state.buffer_sizes = vec![1, 64, 17, 0, 256];
+        }
+        let (_, renderer) = run(&provider, Device::Default, test_settings(), || {
+            wait_for_streams(&provider, 1);
+        });
+
+        // every frame is rendered once, in order, into both channels
+        assert_eq!(renderer.buffers, [1, 64, 17, 0, 256]);
+        assert_eq!(renderer.frames, 1 + 64 + 17 + 256);
+        let samples: Vec<f32> = provider.0.lock().buffers.concat();
+        for (i, frame) in samples.chunks_exact(2).enumerate() {
+            assert_eq!(frame, [i as f32, i as f32]);
+        }
     }
 }
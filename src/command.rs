@@ -1,5 +1,5 @@
-use crate::PlaybackRate;
-use std::{f32::consts::PI, ops::RangeInclusive};
+use crate::{KaError, PlaybackRate};
+use std::{f32::consts::PI, ops::RangeInclusive, sync::Arc};
 
 /// https://github.com/Michaelangel007/easing#the-magic-of-170158
 const C1: f32 = 1.70158;
@@ -46,11 +46,11 @@ fn bounce_out(t: f32) -> f32 {
     if t < 1.0 / D1 {
         N1 * t * t
     } else if t < 2.0 / D1 {
-        return N1.mul_add((t - 1.5 / D1).powi(2), 0.75);
+        N1.mul_add((t - 1.5 / D1).powi(2), 0.75)
     } else if t < 2.5 / D1 {
-        return N1.mul_add((t - 2.25 / D1).powi(2), 0.9375);
+        N1.mul_add((t - 2.25 / D1).powi(2), 0.9375)
     } else {
-        return N1.mul_add((t - 2.625 / D1).powi(2), 0.984_375);
+        N1.mul_add((t - 2.625 / D1).powi(2), 0.984_375)
     }
 }
 
@@ -288,6 +288,45 @@ fn sine_in_out(t: f32) -> f32 {
     -((PI * t).cos() - 1.0) / 2.0
 }
 
+/// An easing function. Implemented for [`Easing`] and for closures, so
+/// crates can ship their own curves and use them in commands with
+/// [`Command::with_ease`].
+pub trait Ease {
+    /// Map a time between 0.0 and 1.0 to the progress of the motion.
+    fn ease(&self, t: f32) -> f32;
+}
+
+impl<F: Fn(f32) -> f32> Ease for F {
+    #[inline]
+    fn ease(&self, t: f32) -> f32 {
+        self(t)
+    }
+}
+
+impl Ease for Easing {
+    #[inline]
+    fn ease(&self, t: f32) -> f32 {
+        self.apply(t)
+    }
+}
+
+/// A shared custom easing function of a [`Command`]. Clones share the same
+/// function, and two custom easings are equal if they share it.
+#[derive(Clone)]
+pub struct CustomEase(pub Arc<dyn Ease + Send + Sync>);
+
+impl std::fmt::Debug for CustomEase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CustomEase")
+    }
+}
+
+impl PartialEq for CustomEase {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 /// Specifies what easing function to use.
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -402,8 +441,12 @@ impl Easing {
 }
 
 /// Specifies what change to make to a [`crate::Sound`]. Used with [`Command`].
+///
+/// New kinds of changes may be added in minor releases, so matches on this
+/// enum need a wildcard arm.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum Change {
     /// Change volume value.
     Volume(f32),
@@ -417,8 +460,12 @@ pub enum Change {
     /// Change the position in seconds.
     Position(f64),
     /// Change the loop points in seconds.
+    ///
+    /// While the loop points are tweened, the playhead only wraps when it
+    /// crosses a loop point. If a loop point moves past the playhead, the
+    /// wrap is deferred until the tween completes.
     LoopSeconds(RangeInclusive<f64>),
-    /// Change the loop points in samples.
+    /// Change the loop points in samples. See [`Change::LoopSeconds`].
     LoopIndex(RangeInclusive<usize>),
     /// Control the audio panning.
     ///
@@ -426,6 +473,15 @@ pub enum Change {
     /// * Panning of 0.5 means center panning (default)
     /// * Panning of 1.0 means hard right panning
     Panning(f32),
+    /// Change the gain of the bass shelf in decibels. See
+    /// [`crate::Sound::set_tone`].
+    BassDb(f32),
+    /// Change the gain of the treble shelf in decibels. See
+    /// [`crate::Sound::set_tone`].
+    TrebleDb(f32),
+    /// Stop the sound after the easing function returns a value bigger than
+    /// 0.5. A stopped sound is finished and is removed from the renderer.
+    Stop,
 }
 
 /// A command that specifies an action that is applied on a [`crate::Sound`]
@@ -441,6 +497,19 @@ pub struct Command {
     pub start_after: f64,
     /// How much time the command lasts (in seconds).
     pub duration: f64,
+    /// Automation lane of the command. Commands in a lane can be cancelled
+    /// or replaced together, see [`crate::Sound::cancel_lane`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lane: Option<u32>,
+    /// Whether the easing is clamped to the 0.0..=1.0 range, so easings
+    /// like [`Easing::BackOut`] or [`Easing::ElasticOut`] don't overshoot
+    /// the target value. Index and position changes are always clamped.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub clamped: bool,
+    /// Custom easing function. If set, it is used instead of
+    /// [`Command::easing`]. Not serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub custom_ease: Option<CustomEase>,
 }
 
 impl Command {
@@ -451,14 +520,78 @@ impl Command {
             easing,
             start_after,
             duration,
+            lane: None,
+            clamped: false,
+            custom_ease: None,
         }
     }
 
+    /// Return the command with a custom easing function, which is used
+    /// instead of [`Command::easing`]. See [`Ease`].
+    #[inline]
+    pub fn with_ease(mut self, ease: impl Ease + Send + Sync + 'static) -> Self {
+        self.custom_ease = Some(CustomEase(Arc::new(ease)));
+        self
+    }
+
+    /// Return the command with easing overshoot clamping enabled or
+    /// disabled.
+    #[inline]
+    pub const fn clamped(mut self, clamped: bool) -> Self {
+        self.clamped = clamped;
+        self
+    }
+
+    /// Return the command in the given automation lane. See
+    /// [`crate::Sound::cancel_lane`].
+    #[inline]
+    pub const fn with_lane(mut self, lane: u32) -> Self {
+        self.lane = Some(lane);
+        self
+    }
+
+    /// Check that the timing and the value of the command are valid: the
+    /// duration must not be negative or NaN, `start_after` must be finite
+    /// and at most `max_start_after` seconds, and the value must not be NaN
+    /// or infinite. A duration of zero is valid, the change is applied
+    /// instantly once `start_after` passes.
+    pub fn validate(&self, max_start_after: Option<f64>) -> Result<(), KaError> {
+        if self.duration.is_nan() || self.duration < 0.0 {
+            return Err(KaError::InvalidCommand("duration is negative or NaN"));
+        }
+        if !self.start_after.is_finite() {
+            return Err(KaError::InvalidCommand("start_after is not finite"));
+        }
+        if max_start_after.is_some_and(|max| self.start_after > max) {
+            return Err(KaError::InvalidCommand(
+                "start_after is later than the maximum command delay",
+            ));
+        }
+
+        let finite = match &self.change {
+            Change::Volume(value)
+            | Change::Panning(value)
+            | Change::BassDb(value)
+            | Change::TrebleDb(value) => value.is_finite(),
+            Change::PlaybackRate(rate) => rate.as_factor().is_finite(),
+            Change::Position(position) => position.is_finite(),
+            Change::LoopSeconds(range) => range.start().is_finite() && range.end().is_finite(),
+            Change::Pause(_) | Change::Index(_) | Change::LoopIndex(_) | Change::Stop => true,
+        };
+        if !finite {
+            return Err(KaError::InvalidCommand("value is NaN or infinite"));
+        }
+        Ok(())
+    }
+
     /// Get the value of the command at a given time.
     #[must_use]
     #[inline(always)]
     pub fn value(&self, t: f32) -> f32 {
-        self.easing.apply(t)
+        match &self.custom_ease {
+            Some(ease) => ease.0.ease(t),
+            None => self.easing.apply(t),
+        }
     }
 }
 
@@ -0,0 +1,225 @@
+use crate::Frame;
+
+/// Settings for converting [`Frame`]s to other sample formats, see
+/// [`frames_to_i16`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct ConvertSettings {
+    /// Clamp samples to the -1.0..=1.0 range. Integer formats are always
+    /// clamped to their range, so this only changes float output.
+    pub clamp: bool,
+    /// Add triangular (TPDF) dither of 1 LSB before quantizing to an integer
+    /// format, which turns quantization distortion of quiet signals into
+    /// low-level noise. Ignored for float output.
+    pub dither: bool,
+}
+
+/// Small xorshift generator for dither noise.
+struct DitherNoise(u32);
+
+impl DitherNoise {
+    /// Return a random value in the 0.0..1.0 range.
+    #[inline]
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f64 / (u32::MAX as f64 + 1.0)
+    }
+
+    /// Return triangular noise in the -1.0..1.0 range.
+    #[inline]
+    fn triangular(&mut self) -> f64 {
+        self.next() + self.next() - 1.0
+    }
+}
+
+/// Write frames as interleaved stereo samples (left, right, left, ...) to
+/// `out`. Returns the amount of frames that were written, which is limited
+/// by the length of `out`.
+pub fn frames_to_f32_interleaved(
+    frames: &[Frame],
+    out: &mut [f32],
+    settings: ConvertSettings,
+) -> usize {
+    let count = frames.len().min(out.len() / 2);
+    for (frame, out) in frames.iter().zip(out.chunks_exact_mut(2)) {
+        let (left, right) = if settings.clamp {
+            (frame.left.clamp(-1.0, 1.0), frame.right.clamp(-1.0, 1.0))
+        } else {
+            (frame.left, frame.right)
+        };
+        out[0] = left;
+        out[1] = right;
+    }
+    count
+}
+
+/// Quantize frames to an integer format with `scale` steps per 1.0.
+fn frames_to_int<T: Copy>(
+    frames: &[Frame],
+    out: &mut [T],
+    settings: ConvertSettings,
+    scale: f64,
+    (min, max): (f64, f64),
+    cast: impl Fn(f64) -> T,
+) -> usize {
+    let mut noise = DitherNoise(0x9e37_79b9);
+    let mut quantize = |sample: f32| {
+        let mut value = sample as f64 * scale;
+        if settings.dither {
+            value += noise.triangular();
+        }
+        cast(value.round().clamp(min, max))
+    };
+
+    let count = frames.len().min(out.len() / 2);
+    for (frame, out) in frames.iter().zip(out.chunks_exact_mut(2)) {
+        out[0] = quantize(frame.left);
+        out[1] = quantize(frame.right);
+    }
+    count
+}
+
+/// Write frames as interleaved stereo [`i16`] samples to `out`. 1.0 maps to
+/// 32768, like the conversion the [`crate::Backend`] uses, and samples
+/// outside of the range are clamped. Returns the amount of frames that were
+/// written, which is limited by the length of `out`.
+pub fn frames_to_i16(frames: &[Frame], out: &mut [i16], settings: ConvertSettings) -> usize {
+    frames_to_int(
+        frames,
+        out,
+        settings,
+        32768.0,
+        (i16::MIN as f64, i16::MAX as f64),
+        |value| value as i16,
+    )
+}
+
+/// Write frames as interleaved stereo [`i32`] samples to `out`. See
+/// [`frames_to_i16`].
+pub fn frames_to_i32(frames: &[Frame], out: &mut [i32], settings: ConvertSettings) -> usize {
+    frames_to_int(
+        frames,
+        out,
+        settings,
+        2147483648.0,
+        (i32::MIN as f64, i32::MAX as f64),
+        |value| value as i32,
+    )
+}
+
+/// Read frames from interleaved stereo samples (left, right, left, ...). A
+/// trailing sample without a pair is ignored.
+pub fn frames_from_f32_interleaved(samples: &[f32]) -> Vec<Frame> {
+    samples
+        .chunks_exact(2)
+        .map(|pair| Frame::new(pair[0], pair[1]))
+        .collect()
+}
+
+/// Read frames from interleaved stereo [`i16`] samples. This is the inverse
+/// of [`frames_to_i16`].
+pub fn frames_from_i16(samples: &[i16]) -> Vec<Frame> {
+    samples
+        .chunks_exact(2)
+        .map(|pair| Frame::new(pair[0] as f32 / 32768.0, pair[1] as f32 / 32768.0))
+        .collect()
+}
+
+/// Read frames from interleaved stereo [`i32`] samples. This is the inverse
+/// of [`frames_to_i32`].
+pub fn frames_from_i32(samples: &[i32]) -> Vec<Frame> {
+    samples
+        .chunks_exact(2)
+        .map(|pair| {
+            Frame::new(
+                (pair[0] as f64 / 2147483648.0) as f32,
+                (pair[1] as f64 / 2147483648.0) as f32,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Return frames with values spread over the -1.0..1.0 range (1.0 is
+    /// clamped by integer formats).
+    fn sweep() -> Vec<Frame> {
+        (0..200)
+            .map(|i| {
+                let value = i as f32 / 100.0 - 1.0;
+                Frame::new(value, -value * 0.7)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips() {
+        let frames = sweep();
+        let max_error = |decoded: &[Frame]| {
+            decoded
+                .iter()
+                .zip(&frames)
+                .map(|(a, b)| (a.left - b.left).abs().max((a.right - b.right).abs()))
+                .fold(0.0, f32::max)
+        };
+
+        let mut floats = vec![0.0; frames.len() * 2];
+        frames_to_f32_interleaved(&frames, &mut floats, ConvertSettings::default());
+        assert_eq!(frames_from_f32_interleaved(&floats), frames);
+
+        let mut ints = vec![0; frames.len() * 2];
+        frames_to_i16(&frames, &mut ints, ConvertSettings::default());
+        assert!(max_error(&frames_from_i16(&ints)) <= 0.5 / 32768.0);
+
+        let dither = ConvertSettings {
+            dither: true,
+            ..Default::default()
+        };
+        frames_to_i16(&frames, &mut ints, dither);
+        assert!(max_error(&frames_from_i16(&ints)) <= 1.5 / 32768.0);
+
+        let mut ints = vec![0; frames.len() * 2];
+        frames_to_i32(&frames, &mut ints, ConvertSettings::default());
+        assert!(max_error(&frames_from_i32(&ints)) <= f32::EPSILON);
+    }
+
+    #[test]
+    fn clamps_at_the_extremes() {
+        let frames = [
+            Frame::new(1.0, -1.0),
+            Frame::new(2.0, -2.0),
+            Frame::new(f32::INFINITY, f32::NEG_INFINITY),
+        ];
+
+        let mut ints = [0; 6];
+        assert_eq!(
+            frames_to_i16(&frames, &mut ints, ConvertSettings::default()),
+            3
+        );
+        assert_eq!(ints, [32767, -32768, 32767, -32768, 32767, -32768]);
+
+        let mut ints = [0; 6];
+        frames_to_i32(&frames, &mut ints, ConvertSettings::default());
+        assert_eq!(ints[2..4], [i32::MAX, i32::MIN]);
+
+        let mut floats = [0.0; 6];
+        frames_to_f32_interleaved(&frames, &mut floats, ConvertSettings::default());
+        assert_eq!(floats[2..4], [2.0, -2.0]);
+        let clamp = ConvertSettings {
+            clamp: true,
+            ..Default::default()
+        };
+        frames_to_f32_interleaved(&frames, &mut floats, clamp);
+        assert_eq!(floats, [1.0, -1.0, 1.0, -1.0, 1.0, -1.0]);
+
+        // the output limits the amount of written frames
+        let mut short = [0; 3];
+        assert_eq!(
+            frames_to_i16(&frames, &mut short, ConvertSettings::default()),
+            1
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{BufferTiming, SoundHandle};
+use std::time::Duration;
+
+/// How much the latest buffer contributes to [`RenderLoad::average`].
+const AVERAGE_WEIGHT: f64 = 0.05;
+
+/// How much of the audio budget rendering takes. See
+/// [`crate::Mixer::render_load`].
+///
+/// Required features: `diagnostics`
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct RenderLoad {
+    /// Rolling average of the time it takes to render a buffer.
+    pub average: Duration,
+    /// Longest time it took to render a buffer.
+    pub max: Duration,
+    /// Rolling average of the render time divided by the buffer duration. A
+    /// value of 1.0 means rendering takes as long as playing the buffer, so
+    /// the device will underrun.
+    pub average_load: f64,
+    /// Highest render time divided by the buffer duration.
+    pub max_load: f64,
+    /// Amount of measured buffers.
+    pub buffers: u64,
+}
+
+/// Measures the render time of a [`crate::DefaultRenderer`]. Only a couple
+/// of timer reads per buffer are done, unless per-sound costs are measured.
+///
+/// Required features: `diagnostics`
+#[derive(Debug, Clone, Default)]
+pub struct Diagnostics {
+    load: RenderLoad,
+    /// Measure the cost of every sound every `sound_cost_interval` buffers.
+    sound_cost_interval: Option<u32>,
+    /// Buffers left until the next buffer where sound costs are measured.
+    buffers_until_sample: u32,
+    /// Whether the sound costs are measured in the current buffer.
+    sampling: bool,
+    /// Time spent rendering each sound in the current buffer, in the order
+    /// of the renderer's sounds.
+    costs: Vec<Duration>,
+    /// Sound costs measured in the last sampled buffer.
+    sound_costs: Vec<(SoundHandle, Duration)>,
+}
+
+impl Diagnostics {
+    /// Return the measured render load.
+    #[inline]
+    pub fn render_load(&self) -> RenderLoad {
+        self.load
+    }
+
+    /// Measure how long each sound takes to render every `interval`
+    /// buffers. If [`None`], sound costs are not measured (default).
+    ///
+    /// Note: in measured buffers, the timer is read for every frame of every
+    /// sound.
+    #[inline]
+    pub fn set_sound_cost_interval(&mut self, interval: Option<u32>) {
+        self.sound_cost_interval = interval;
+        self.buffers_until_sample = 0;
+        if interval.is_none() {
+            self.sound_costs.clear();
+        }
+    }
+
+    /// Return the time each sound took to render in the last measured
+    /// buffer. See [`Diagnostics::set_sound_cost_interval`].
+    #[inline]
+    pub fn sound_costs(&self) -> &[(SoundHandle, Duration)] {
+        &self.sound_costs
+    }
+
+    /// Reset the render load and the sound costs.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.load = RenderLoad::default();
+        self.sound_costs.clear();
+    }
+
+    /// Return whether the sound costs should be measured right now.
+    #[inline]
+    pub(crate) fn sampling(&self) -> bool {
+        self.sampling
+    }
+
+    /// Called before a buffer is rendered.
+    #[inline]
+    pub(crate) fn begin_buffer(&mut self) {
+        let Some(interval) = self.sound_cost_interval else {
+            return;
+        };
+        self.sampling = self.buffers_until_sample == 0;
+        if self.sampling {
+            self.buffers_until_sample = interval.max(1);
+            self.costs.clear();
+        }
+        self.buffers_until_sample -= 1;
+    }
+
+    /// Add the time it took to render a frame of the sound at `index`.
+    #[inline]
+    pub(crate) fn add_sound_cost(&mut self, index: usize, cost: Duration) {
+        if index >= self.costs.len() {
+            self.costs.resize(index + 1, Duration::ZERO);
+        }
+        self.costs[index] += cost;
+    }
+
+    /// Called when the renderer swap-removes the sound at `index`.
+    #[inline]
+    pub(crate) fn remove_sound(&mut self, index: usize) {
+        if index < self.costs.len() {
+            self.costs.swap_remove(index);
+        }
+    }
+
+    /// Called after a buffer is rendered.
+    pub(crate) fn end_buffer(&mut self, timing: &BufferTiming, sounds: &[SoundHandle]) {
+        let elapsed = timing.callback.elapsed();
+        let load = elapsed.as_secs_f64() / timing.duration().as_secs_f64().max(f64::EPSILON);
+
+        let stats = &mut self.load;
+        if stats.buffers == 0 {
+            stats.average = elapsed;
+            stats.average_load = load;
+        } else {
+            stats.average =
+                stats.average.mul_f64(1.0 - AVERAGE_WEIGHT) + elapsed.mul_f64(AVERAGE_WEIGHT);
+            stats.average_load += (load - stats.average_load) * AVERAGE_WEIGHT;
+        }
+        stats.max = stats.max.max(elapsed);
+        stats.max_load = stats.max_load.max(load);
+        stats.buffers += 1;
+
+        if self.sampling {
+            self.sampling = false;
+            self.sound_costs.clear();
+            self.sound_costs.extend(
+                sounds
+                    .iter()
+                    .zip(&self.costs)
+                    .map(|(sound, cost)| (sound.clone(), *cost)),
+            );
+        }
+    }
+}
@@ -3,9 +3,13 @@ use thiserror::Error;
 /// KittyAudio's error type.
 #[derive(Error, Debug)]
 #[allow(missing_docs)]
+#[non_exhaustive]
 pub enum KaError {
     #[error("failed to get output device")]
     NoOutputDevice,
+    #[error("failed to find output device {0:?} at index {1} ({2} devices with that name)")]
+    #[cfg(feature = "cpal")]
+    DeviceNotFound(String, usize, usize),
     #[error("failed to get output devices: {0}")]
     #[cfg(feature = "cpal")]
     DeviceError(#[from] cpal::DevicesError),
@@ -15,6 +19,15 @@ pub enum KaError {
     #[error("unsupported sample format {0}")]
     #[cfg(feature = "cpal")]
     UnsupportedSampleFormat(cpal::SampleFormat),
+    #[error("unsupported buffer size {0} (supported range is {1}..={2})")]
+    #[cfg(feature = "cpal")]
+    UnsupportedBufferSize(u32, u32, u32),
+    #[error("the device doesn't support any sample format that can be used for output")]
+    #[cfg(feature = "cpal")]
+    NoSupportedSampleFormat,
+    #[error("failed to get supported stream configs: {0}")]
+    #[cfg(feature = "cpal")]
+    SupportedStreamConfigsError(#[from] cpal::SupportedStreamConfigsError),
     #[error("failed to build stream: {0}")]
     #[cfg(feature = "cpal")]
     BuildStreamError(#[from] cpal::BuildStreamError),
@@ -28,6 +41,10 @@ pub enum KaError {
     IoError(#[from] std::io::Error),
     #[error("failed to get default track as no tracks are present")]
     NoTracksArePresent,
+    #[error("no track with id {0} is present")]
+    TrackNotFound(u32),
+    #[error("the decoded audio doesn't match the checksum of the source")]
+    VerificationFailed,
     #[error("failed to get cpal device name: {0}")]
     #[cfg(feature = "cpal")]
     DeviceNameError(#[from] cpal::DeviceNameError),
@@ -40,4 +57,14 @@ pub enum KaError {
     UnsupportedNumberOfChannels(u32),
     #[error("failed to get sample rate, or it is invalid")]
     UnknownSampleRate,
+    #[error("the audio doesn't contain any frames")]
+    EmptyAudio,
+    #[error("sample rates don't match (got {0}, expected {1})")]
+    SampleRateMismatch(u32, u32),
+    #[error("invalid command: {0}")]
+    InvalidCommand(&'static str),
+    #[error("invalid cached sound: {0}")]
+    InvalidCachedSound(&'static str),
+    #[error("the playback state was saved from a sound with different audio data")]
+    PlaybackStateMismatch,
 }
@@ -0,0 +1,144 @@
+use crate::logging::log_warn;
+use crate::{KaError, Sound, SoundHandle};
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+
+/// How often a [`HotSound`] checks whether its file changed.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Callback that is called when reloading a [`HotSound`] fails.
+type ErrorCallback = Box<dyn Fn(&Path, &KaError) + Send + Sync>;
+
+/// State shared between the clones of a [`HotSound`] and its watcher thread.
+struct HotSoundState {
+    path: PathBuf,
+    /// Last sound that was decoded successfully.
+    sound: Mutex<Sound>,
+    /// Amount of times the sound was reloaded.
+    reloads: AtomicU64,
+    on_error: Mutex<Option<ErrorCallback>>,
+}
+
+/// Return the modification time and the length of the file at `path`, used
+/// to detect changes to it.
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Poll the file of a [`HotSound`] and reload it when it changes. Stops once
+/// all clones of the [`HotSound`] are dropped.
+fn watch(state: Weak<HotSoundState>, path: PathBuf, mut stamp: Option<(SystemTime, u64)>) {
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let new_stamp = file_stamp(&path);
+        if new_stamp.is_none() || new_stamp == stamp {
+            continue;
+        }
+        stamp = new_stamp;
+
+        match Sound::from_path(&path) {
+            Ok(sound) => {
+                *state.sound.lock() = sound;
+                state.reloads.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) => {
+                log_warn!("failed to reload {}: {err}", path.display());
+                if let Some(on_error) = state.on_error.lock().as_ref() {
+                    on_error(&path, &err);
+                }
+            }
+        }
+    }
+}
+
+/// A sound that is reloaded when its file changes, to iterate on sounds
+/// without restarting the application. Meant for development builds.
+///
+/// A background thread polls the modification time of the file and decodes
+/// it again when it changes. New plays use the reloaded audio, while sounds
+/// that are already playing keep their old data. If the file fails to
+/// decode (for example while it is still being written), the last good
+/// sound is kept and the error is passed to the callback set with
+/// [`HotSound::set_error_callback`] (and logged with the `log` feature).
+///
+/// Clones share the same sound. The background thread stops once all clones
+/// are dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let jump = HotSound::from_path("assets/jump.ogg")?;
+/// // plays the latest version of the file
+/// mixer.play(&jump);
+/// ```
+///
+/// Required features: `hot_reload`
+#[derive(Clone)]
+pub struct HotSound(Arc<HotSoundState>);
+
+impl std::fmt::Debug for HotSound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotSound")
+            .field("path", &self.0.path)
+            .field("reloads", &self.reloads())
+            .finish_non_exhaustive()
+    }
+}
+
+impl HotSound {
+    /// Decode the file at `path` and start watching it for changes. Returns
+    /// an error if the file can't be decoded initially.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, KaError> {
+        let path = path.as_ref().to_path_buf();
+        let stamp = file_stamp(&path);
+        let sound = Sound::from_path(&path)?;
+        let state = Arc::new(HotSoundState {
+            path: path.clone(),
+            sound: Mutex::new(sound),
+            reloads: AtomicU64::new(0),
+            on_error: Mutex::new(None),
+        });
+
+        let weak = Arc::downgrade(&state);
+        std::thread::spawn(move || watch(weak, path, stamp));
+        Ok(Self(state))
+    }
+
+    /// Return the latest sound that was decoded successfully.
+    #[inline]
+    pub fn sound(&self) -> Sound {
+        self.0.sound.lock().clone()
+    }
+
+    /// Return the path of the watched file.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.0.path
+    }
+
+    /// Return the amount of times the sound was reloaded successfully.
+    #[inline]
+    pub fn reloads(&self) -> u64 {
+        self.0.reloads.load(Ordering::Relaxed)
+    }
+
+    /// Set a callback that is called on the watcher thread when reloading
+    /// the file fails. The previous sound is kept in that case.
+    pub fn set_error_callback(&self, callback: impl Fn(&Path, &KaError) + Send + Sync + 'static) {
+        *self.0.on_error.lock() = Some(Box::new(callback));
+    }
+}
+
+/// Plays the latest version of the sound.
+impl From<&HotSound> for SoundHandle {
+    fn from(sound: &HotSound) -> Self {
+        Self::new(sound.sound())
+    }
+}
@@ -0,0 +1,143 @@
+use std::f64::consts::TAU;
+
+/// Shape of an [`Lfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LfoWaveform {
+    /// A smooth sine wave (default).
+    #[default]
+    Sine,
+    /// A triangle wave, which moves at a constant speed.
+    Triangle,
+    /// A square wave, which jumps between both extremes.
+    Square,
+}
+
+impl LfoWaveform {
+    /// Return the value of the waveform (between -1.0 and 1.0) at `phase`,
+    /// where a phase of 1.0 is one full cycle.
+    pub fn value(self, phase: f64) -> f64 {
+        let phase = phase.rem_euclid(1.0);
+        match self {
+            Self::Sine => (phase * TAU).sin(),
+            Self::Triangle => 1.0 - 4.0 * ((phase + 0.25).rem_euclid(1.0) - 0.5).abs(),
+            Self::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+        }
+    }
+}
+
+/// How fast an [`Lfo`] oscillates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LfoRate {
+    /// Cycles per second.
+    Hz(f64),
+    /// One cycle every `beats` beats at a tempo of `bpm` beats per minute,
+    /// for example `beats: 0.5` for an eighth note tremolo in 4/4.
+    Beats {
+        /// Tempo in beats per minute.
+        bpm: f64,
+        /// Length of one cycle in beats.
+        beats: f64,
+    },
+}
+
+impl LfoRate {
+    /// Return the rate in cycles per second. Invalid rates return 0.0.
+    pub fn hz(self) -> f64 {
+        let hz = match self {
+            Self::Hz(hz) => hz,
+            Self::Beats { bpm, beats } => bpm / 60.0 / beats,
+        };
+        if hz.is_finite() {
+            hz.max(0.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for LfoRate {
+    fn default() -> Self {
+        Self::Hz(4.0)
+    }
+}
+
+/// The sound parameter an [`Lfo`] modulates. See [`crate::Sound::set_lfo`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LfoTarget {
+    /// Tremolo: the volume dips by up to the depth of the LFO, and is back
+    /// at the set volume at the top of each cycle.
+    Volume,
+    /// Auto-pan: the panning swings around its set value by half the depth
+    /// of the LFO to each side, so a depth of 1.0 swings from hard left to
+    /// hard right around the center.
+    Panning,
+}
+
+/// A low frequency oscillator that periodically modulates the volume or the
+/// panning of a sound, see [`crate::Sound::set_lfo`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lfo {
+    /// How fast the LFO oscillates.
+    pub rate: LfoRate,
+    /// How strong the modulation is, between 0.0 and 1.0.
+    pub depth: f32,
+    /// Shape of the oscillation.
+    pub waveform: LfoWaveform,
+    /// Phase the LFO starts at, where 1.0 is one full cycle.
+    pub phase: f64,
+}
+
+impl Default for Lfo {
+    fn default() -> Self {
+        Self {
+            rate: LfoRate::default(),
+            depth: 0.5,
+            waveform: LfoWaveform::default(),
+            phase: 0.0,
+        }
+    }
+}
+
+impl Lfo {
+    /// Create a new [`Lfo`] with a sine waveform that starts at phase 0.
+    #[inline]
+    pub const fn new(rate: LfoRate, depth: f32) -> Self {
+        Self {
+            rate,
+            depth,
+            waveform: LfoWaveform::Sine,
+            phase: 0.0,
+        }
+    }
+
+    /// Return the LFO with a different waveform.
+    #[inline]
+    pub const fn with_waveform(mut self, waveform: LfoWaveform) -> Self {
+        self.waveform = waveform;
+        self
+    }
+
+    /// Return the LFO with a different starting phase.
+    #[inline]
+    pub const fn with_phase(mut self, phase: f64) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Return the value of the LFO (between -1.0 and 1.0) after `elapsed`
+    /// seconds.
+    #[inline]
+    pub fn value(&self, elapsed: f64) -> f64 {
+        self.waveform.value(self.phase + elapsed * self.rate.hz())
+    }
+}
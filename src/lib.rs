@@ -49,25 +49,48 @@
 
 #![warn(missing_docs)] // warn on missing function docs
 
+mod automation;
 #[cfg(feature = "cpal")]
 mod backend;
 
 mod command;
+mod convert;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
 mod error;
+#[cfg(feature = "hot_reload")]
+mod hot_reload;
+mod lfo;
+mod logging;
+mod meter;
+mod metronome;
 mod mixer;
 mod renderer;
 mod resampler;
 mod sound;
+mod streaming;
+mod transition;
 
+pub use automation::*;
 #[cfg(feature = "cpal")]
 pub use backend::*;
 
 pub use command::*;
+pub use convert::*;
+#[cfg(feature = "diagnostics")]
+pub use diagnostics::*;
 pub use error::*;
+#[cfg(feature = "hot_reload")]
+pub use hot_reload::*;
+pub use lfo::*;
+pub use meter::*;
+pub use metronome::*;
 pub use mixer::*;
 pub use renderer::*;
 pub use resampler::*;
 pub use sound::*;
+pub use streaming::*;
+pub use transition::*;
 
 // Re-export the cpal and symphonia crate
 #[cfg(feature = "cpal")]
@@ -0,0 +1,324 @@
+use crate::Frame;
+use std::time::Duration;
+
+/// Controls how fast a [`Meter`] reacts to changes in the signal level.
+///
+/// The defaults follow the EBU Type IIb PPM standard: 10 ms integration
+/// time and a 20 dB fall in 1.7 seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterBallistics {
+    /// Time it takes the peak reading to rise to a new level.
+    pub attack: Duration,
+    /// Time it takes the peak reading to fall by 20 dB.
+    pub release: Duration,
+    /// How long the peak-hold reading stays at the highest peak.
+    pub hold: Duration,
+    /// How fast the peak-hold reading falls after the hold time (in dB per
+    /// second).
+    pub hold_fall_rate: f32,
+    /// Averaging window of the RMS and correlation readings.
+    pub rms_window: Duration,
+}
+
+impl Default for MeterBallistics {
+    fn default() -> Self {
+        Self {
+            attack: Duration::from_millis(10),
+            release: Duration::from_millis(1700),
+            hold: Duration::from_secs(1),
+            hold_fall_rate: 20.0,
+            rms_window: Duration::from_millis(300),
+        }
+    }
+}
+
+/// Levels of a single channel measured by a [`Meter`]. All values are
+/// linear amplitudes.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelLevels {
+    /// Peak level with attack and release ballistics applied.
+    pub peak: f32,
+    /// Highest recent peak level. Stays for the hold time, then falls at
+    /// the configured rate.
+    pub peak_hold: f32,
+    /// RMS level over the averaging window.
+    pub rms: f32,
+}
+
+/// Levels measured by a [`Meter`], suitable for driving a VU/PPM meter.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MeterData {
+    /// Levels of the left channel.
+    pub left: ChannelLevels,
+    /// Levels of the right channel.
+    pub right: ChannelLevels,
+    /// Phase correlation between the channels over the RMS window, see
+    /// [`Meter::correlation`].
+    pub correlation: f32,
+}
+
+/// Mono compatibility of a sound, see [`crate::Sound::mono_compatibility`].
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MonoReport {
+    /// Phase correlation between the channels over the whole sound, see
+    /// [`Meter::correlation`]. Negative values mean that the channels
+    /// partially cancel out when summed to mono.
+    pub correlation: f32,
+    /// How much quieter (in dB) the mono sum `(left + right) / 2` is than the
+    /// stereo signal, by RMS level. 0 dB for mono sounds, about 3 dB for
+    /// unrelated channels, and [`f32::INFINITY`] if the channels cancel out
+    /// completely. Negative values can't happen.
+    pub level_drop_db: f32,
+}
+
+impl MonoReport {
+    /// Analyze a stereo signal.
+    pub(crate) fn analyze(frames: impl Iterator<Item = Frame>) -> Self {
+        let (mut left, mut right, mut product) = (0.0f64, 0.0f64, 0.0f64);
+        for frame in frames {
+            let (l, r) = (frame.left as f64, frame.right as f64);
+            left += l * l;
+            right += r * r;
+            product += l * r;
+        }
+
+        let power = (left * right).sqrt();
+        let correlation = if power <= f64::EPSILON {
+            0.0
+        } else {
+            (product / power).clamp(-1.0, 1.0)
+        };
+
+        let stereo_power = (left + right) / 2.0;
+        let mono_power = (left + right + 2.0 * product) / 4.0;
+        let level_drop_db = if stereo_power <= f64::EPSILON {
+            0.0
+        } else if mono_power <= stereo_power * 1e-12 {
+            f64::INFINITY
+        } else {
+            (10.0 * (stereo_power / mono_power).log10()).max(0.0)
+        };
+
+        Self {
+            correlation: correlation as f32,
+            level_drop_db: level_drop_db as f32,
+        }
+    }
+}
+
+/// Per-frame coefficients of a [`Meter`], derived from the ballistics and
+/// the sample rate.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct MeterCoeffs {
+    /// Sample rate the coefficients were computed for.
+    sample_rate: u32,
+    attack: f32,
+    release: f32,
+    hold_frames: u32,
+    hold_fall: f32,
+    rms: f32,
+}
+
+impl MeterCoeffs {
+    fn new(ballistics: &MeterBallistics, sample_rate: u32) -> Self {
+        let rate = sample_rate as f32;
+        Self {
+            sample_rate,
+            attack: one_pole(ballistics.attack, rate),
+            // fall by 20 dB (a factor of 10) over the release time
+            release: 10.0f32.powf(-1.0 / (ballistics.release.as_secs_f32() * rate)),
+            hold_frames: (ballistics.hold.as_secs_f32() * rate) as u32,
+            hold_fall: 10.0f32.powf(-ballistics.hold_fall_rate / 20.0 / rate),
+            rms: one_pole(ballistics.rms_window, rate),
+        }
+    }
+}
+
+/// Return the coefficient of a one-pole filter with the given time constant.
+#[inline]
+fn one_pole(time: Duration, sample_rate: f32) -> f32 {
+    let frames = time.as_secs_f32() * sample_rate;
+    if frames <= 0.0 {
+        1.0
+    } else {
+        1.0 - (-1.0 / frames).exp()
+    }
+}
+
+/// Metering state of a single channel.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct ChannelMeter {
+    peak: f32,
+    peak_hold: f32,
+    /// Frames left before the peak-hold reading starts to fall.
+    hold_left: u32,
+    mean_square: f32,
+}
+
+impl ChannelMeter {
+    #[inline]
+    fn process(&mut self, sample: f32, coeffs: &MeterCoeffs) {
+        let level = sample.abs();
+
+        if level > self.peak {
+            self.peak += (level - self.peak) * coeffs.attack;
+        } else {
+            self.peak *= coeffs.release;
+        }
+
+        if self.peak > self.peak_hold {
+            self.peak_hold = self.peak;
+            self.hold_left = coeffs.hold_frames;
+        } else if self.hold_left > 0 {
+            self.hold_left -= 1;
+        } else {
+            // never fall below the current peak
+            self.peak_hold = (self.peak_hold * coeffs.hold_fall).max(self.peak);
+        }
+
+        self.mean_square += (level * level - self.mean_square) * coeffs.rms;
+    }
+
+    #[inline]
+    fn levels(&self) -> ChannelLevels {
+        ChannelLevels {
+            peak: self.peak,
+            peak_hold: self.peak_hold,
+            rms: self.mean_square.sqrt(),
+        }
+    }
+}
+
+/// Measures the peak, peak-hold and RMS levels of a stereo signal.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Meter {
+    ballistics: MeterBallistics,
+    coeffs: MeterCoeffs,
+    left: ChannelMeter,
+    right: ChannelMeter,
+    /// Mean of the product of the channels, averaged like the mean squares.
+    mean_product: f32,
+}
+
+impl Meter {
+    /// Create a new [`Meter`] with the given ballistics.
+    pub fn new(ballistics: MeterBallistics) -> Self {
+        Self {
+            ballistics,
+            ..Default::default()
+        }
+    }
+
+    /// Return the ballistics of the meter.
+    #[inline]
+    pub fn ballistics(&self) -> MeterBallistics {
+        self.ballistics
+    }
+
+    /// Change the ballistics of the meter. The current readings are kept.
+    #[inline]
+    pub fn set_ballistics(&mut self, ballistics: MeterBallistics) {
+        self.ballistics = ballistics;
+        self.coeffs.sample_rate = 0; // recompute coefficients on next frame
+    }
+
+    /// Feed the next frame of the signal to the meter.
+    #[inline]
+    pub fn process(&mut self, frame: Frame, sample_rate: u32) {
+        if self.coeffs.sample_rate != sample_rate {
+            self.coeffs = MeterCoeffs::new(&self.ballistics, sample_rate);
+        }
+        self.left.process(frame.left, &self.coeffs);
+        self.right.process(frame.right, &self.coeffs);
+        self.mean_product += (frame.left * frame.right - self.mean_product) * self.coeffs.rms;
+    }
+
+    /// Return the normalized cross-correlation of the left and right
+    /// channels over the RMS window, used to check mono compatibility.
+    ///
+    /// * 1.0 means the channels are identical (mono)
+    /// * 0.0 means the channels are unrelated (or silent)
+    /// * -1.0 means the channels are inverted and cancel out when summed to
+    ///   mono
+    #[inline]
+    pub fn correlation(&self) -> f32 {
+        let power = (self.left.mean_square * self.right.mean_square).sqrt();
+        if power <= f32::EPSILON * f32::EPSILON {
+            return 0.0;
+        }
+        (self.mean_product / power).clamp(-1.0, 1.0)
+    }
+
+    /// Return the current readings of the meter.
+    #[inline]
+    pub fn data(&self) -> MeterData {
+        MeterData {
+            left: self.left.levels(),
+            right: self.right.levels(),
+            correlation: self.correlation(),
+        }
+    }
+
+    /// Reset all readings to zero.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.left = ChannelMeter::default();
+        self.right = ChannelMeter::default();
+        self.mean_product = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 1000;
+
+    #[test]
+    fn peak_hold_holds_then_falls() {
+        let mut meter = Meter::new(MeterBallistics {
+            attack: Duration::ZERO,
+            release: Duration::from_millis(100),
+            hold: Duration::from_millis(100),
+            hold_fall_rate: 20.0,
+            ..Default::default()
+        });
+        meter.process(Frame::from_mono(1.0), SAMPLE_RATE);
+        assert_eq!(meter.data().left.peak, 1.0);
+
+        // the peak falls by 20 dB over the release time, while the
+        // peak-hold reading stays
+        for _ in 0..100 {
+            meter.process(Frame::ZERO, SAMPLE_RATE);
+            assert_eq!(meter.data().left.peak_hold, 1.0);
+        }
+        assert!((meter.data().left.peak - 0.1).abs() < 0.001);
+
+        // then falls by 10 dB in half a second
+        for _ in 0..500 {
+            meter.process(Frame::ZERO, SAMPLE_RATE);
+        }
+        let expected = 10.0f32.powf(-10.0 / 20.0);
+        let peak_hold = meter.data().right.peak_hold;
+        assert!((peak_hold - expected).abs() < 0.01, "{peak_hold}");
+    }
+
+    #[test]
+    fn correlation_of_mono_and_inverted_channels() {
+        let correlation = |right: fn(f32) -> f32| {
+            let mut meter = Meter::default();
+            for i in 0..1000 {
+                let sample = (i as f32 * 0.05).sin();
+                meter.process(Frame::new(sample, right(sample)), SAMPLE_RATE);
+            }
+            meter.correlation()
+        };
+        assert!((correlation(|sample| sample) - 1.0).abs() < 1e-4);
+        assert!((correlation(|sample| -sample) + 1.0).abs() < 1e-4);
+        assert_eq!(correlation(|_| 0.0), 0.0);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{Frame, Renderer, Sound};
+use std::f32::consts::TAU;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Tempo of a [`Metronome`] created with [`Metronome::default`].
+const DEFAULT_BPM: f64 = 120.0;
+
+/// Sample rate of the synthesized metronome clicks.
+const CLICK_SAMPLE_RATE: u32 = 48000;
+
+/// Length (in seconds) of the synthesized metronome clicks.
+const CLICK_SECS: f32 = 0.03;
+
+/// Synthesize a short click: a sine burst at `frequency` with an exponential
+/// decay.
+fn synth_click(frequency: f32) -> Sound {
+    let len = (CLICK_SECS * CLICK_SAMPLE_RATE as f32) as usize;
+    let samples: Vec<f32> = (0..len)
+        .map(|i| {
+            let t = i as f32 / CLICK_SAMPLE_RATE as f32;
+            (t * frequency * TAU).sin() * (-t / CLICK_SECS * 6.0).exp() * 0.5
+        })
+        .collect();
+    Sound::from_mono_samples(CLICK_SAMPLE_RATE, &samples)
+}
+
+/// State shared between the clones of a [`Metronome`]. The audio thread
+/// reads it without locking.
+#[derive(Debug)]
+struct MetronomeState {
+    /// Bits of the tempo in beats per minute.
+    bpm: AtomicU64,
+    /// Bits of the tempo that takes effect on the next beat, or 0 if the
+    /// tempo isn't changing.
+    pending_bpm: AtomicU64,
+    /// Amount of beats in a bar. The first beat of every bar is accented.
+    beats_per_bar: AtomicU32,
+    /// Whether the metronome is running.
+    running: AtomicBool,
+    /// Set when the metronome is started, so the renderer schedules the
+    /// first beat on the next frame.
+    restart: AtomicBool,
+    /// Amount of beats played since the metronome was started.
+    beat: AtomicU64,
+}
+
+impl MetronomeState {
+    /// Apply the tempo that was set while running, if there is one.
+    #[inline]
+    fn apply_pending_bpm(&self) {
+        let pending = self.pending_bpm.swap(0, Ordering::AcqRel);
+        if pending != 0 {
+            self.bpm.store(pending, Ordering::Release);
+        }
+    }
+}
+
+/// Return `click` seeked to its end, so it stays silent until a beat
+/// re-arms it.
+fn silent_voice(mut click: Sound) -> Sound {
+    click.seek_to_index(click.frame_count());
+    click
+}
+
+/// A click track that plays a click on every beat, with an accented click
+/// on the first beat of every bar. Useful for rhythm tools and for checking
+/// the timing of the output.
+///
+/// Beats are scheduled sample-accurately against the frames the metronome
+/// renders. The metronome is a [`Renderer`], so it can be played by a
+/// [`crate::Backend`] or rendered ahead with a [`crate::BufferedRenderer`].
+/// Clones share the tempo, the beat count and whether the metronome is
+/// running, so a clone can be kept as a handle to start and stop the
+/// metronome while it plays.
+///
+/// Rendering doesn't lock or allocate: both clicks are played by voices
+/// that are rewound on every beat.
+///
+/// # Example
+///
+/// ```ignore
+/// let metronome = Metronome::new(90.0).with_beats_per_bar(3);
+/// metronome.start();
+/// backend.start_audio_thread(Device::Default, StreamSettings::default(), metronome.clone().into())?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    state: Arc<MetronomeState>,
+    /// Voice playing the click on the first beat of a bar.
+    accent: Sound,
+    /// Voice playing the click on the other beats.
+    click: Sound,
+    /// Output frames left until the next beat, including the fraction of a
+    /// frame, so the beats don't drift.
+    frames_until_beat: f64,
+}
+
+impl Default for Metronome {
+    fn default() -> Self {
+        Self::new(DEFAULT_BPM)
+    }
+}
+
+impl Metronome {
+    /// Create a new stopped [`Metronome`] at `bpm` beats per minute, with 4
+    /// beats per bar and synthesized clicks. Invalid tempos (not positive or
+    /// not finite) are replaced with 120 BPM.
+    pub fn new(bpm: f64) -> Self {
+        let bpm = if bpm.is_finite() && bpm > 0.0 {
+            bpm
+        } else {
+            DEFAULT_BPM
+        };
+        Self {
+            state: Arc::new(MetronomeState {
+                bpm: AtomicU64::new(bpm.to_bits()),
+                pending_bpm: AtomicU64::new(0),
+                beats_per_bar: AtomicU32::new(4),
+                running: AtomicBool::new(false),
+                restart: AtomicBool::new(false),
+                beat: AtomicU64::new(0),
+            }),
+            accent: silent_voice(synth_click(1760.0)),
+            click: silent_voice(synth_click(880.0)),
+            frames_until_beat: 0.0,
+        }
+    }
+
+    /// Return the metronome with a different amount of beats per bar. See
+    /// [`Metronome::set_beats_per_bar`].
+    #[inline]
+    pub fn with_beats_per_bar(self, beats_per_bar: u32) -> Self {
+        self.set_beats_per_bar(beats_per_bar);
+        self
+    }
+
+    /// Return the metronome with custom sounds for the accented and the
+    /// normal clicks. Only this metronome plays the new clicks, so set them
+    /// before making clones.
+    #[inline]
+    pub fn with_clicks(mut self, accent: Sound, click: Sound) -> Self {
+        self.accent = silent_voice(accent);
+        self.click = silent_voice(click);
+        self
+    }
+
+    /// Start the metronome. The first beat is played on the next rendered
+    /// frame. Starting a running metronome restarts it from the first beat.
+    pub fn start(&self) {
+        self.state.apply_pending_bpm();
+        self.state.beat.store(0, Ordering::Release);
+        self.state.restart.store(true, Ordering::Release);
+        self.state.running.store(true, Ordering::Release);
+    }
+
+    /// Stop the metronome. Clicks that are still ringing play until they end.
+    #[inline]
+    pub fn stop(&self) {
+        self.state.running.store(false, Ordering::Release);
+    }
+
+    /// Return whether the metronome is running.
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::Acquire)
+    }
+
+    /// Set the tempo in beats per minute. While running, the new tempo takes
+    /// effect on the next beat, so the beat that is already scheduled isn't
+    /// moved. Invalid tempos (not positive or not finite) are ignored.
+    pub fn set_bpm(&self, bpm: f64) {
+        if !bpm.is_finite() || bpm <= 0.0 {
+            return;
+        }
+        if self.is_running() {
+            self.state
+                .pending_bpm
+                .store(bpm.to_bits(), Ordering::Release);
+        } else {
+            self.state.bpm.store(bpm.to_bits(), Ordering::Release);
+        }
+    }
+
+    /// Return the tempo in beats per minute. A tempo set while running is
+    /// returned once it took effect.
+    #[inline]
+    pub fn bpm(&self) -> f64 {
+        f64::from_bits(self.state.bpm.load(Ordering::Acquire))
+    }
+
+    /// Set the amount of beats in a bar. The first beat of every bar is
+    /// accented. 0 is treated as 1.
+    #[inline]
+    pub fn set_beats_per_bar(&self, beats_per_bar: u32) {
+        self.state
+            .beats_per_bar
+            .store(beats_per_bar.max(1), Ordering::Release);
+    }
+
+    /// Return the amount of beats in a bar.
+    #[inline]
+    pub fn beats_per_bar(&self) -> u32 {
+        self.state.beats_per_bar.load(Ordering::Acquire)
+    }
+
+    /// Return the amount of beats played since the metronome was started.
+    #[inline]
+    pub fn beat(&self) -> u64 {
+        self.state.beat.load(Ordering::Acquire)
+    }
+}
+
+impl Renderer for Metronome {
+    fn next_frame(&mut self, sample_rate: u32) -> Frame {
+        if self.state.running.load(Ordering::Acquire) {
+            if self.state.restart.swap(false, Ordering::AcqRel) {
+                self.frames_until_beat = 0.0;
+            }
+
+            // the beat falls on the frame nearest to its exact position
+            if self.frames_until_beat < 0.5 {
+                self.state.apply_pending_bpm();
+                let beat = self.state.beat.fetch_add(1, Ordering::AcqRel);
+                let voice = if beat.is_multiple_of(self.beats_per_bar() as u64) {
+                    &mut self.accent
+                } else {
+                    &mut self.click
+                };
+                voice.seek_to_index(0);
+                self.frames_until_beat += 60.0 / self.bpm() * sample_rate as f64;
+            }
+            self.frames_until_beat -= 1.0;
+        }
+
+        let mut out = Frame::ZERO;
+        for voice in [&mut self.accent, &mut self.click] {
+            if let Some(frame) = voice.next_frame(sample_rate) {
+                out += frame;
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 1000;
+
+    /// Render `frames` frames and return the frames that start a click,
+    /// calling `before_frame` before each frame.
+    fn onsets(
+        metronome: &mut Metronome,
+        frames: usize,
+        mut before_frame: impl FnMut(usize, &Metronome),
+    ) -> Vec<usize> {
+        let mut onsets = vec![];
+        let mut last = 0.0;
+        for i in 0..frames {
+            before_frame(i, metronome);
+            let level = metronome.next_frame(SAMPLE_RATE).left;
+            if level > 0.0 && last == 0.0 {
+                onsets.push(i);
+            }
+            last = level;
+        }
+        onsets
+    }
+
+    fn impulse_metronome(bpm: f64) -> Metronome {
+        let impulse = Sound::from_mono_samples(SAMPLE_RATE, &[1.0, 0.0, 0.0, 0.0, 0.0]);
+        Metronome::new(bpm).with_clicks(impulse.clone(), impulse)
+    }
+
+    #[test]
+    fn beats_land_on_the_expected_frames() {
+        // 600 BPM is a beat every 100 frames
+        let mut metronome = impulse_metronome(600.0);
+        assert!(onsets(&mut metronome, 200, |_, _| ()).is_empty());
+
+        metronome.start();
+        let frames = onsets(&mut metronome, 1000, |_, _| ());
+        let first = frames[0];
+        assert!(first < 4, "{first}");
+        let expected: Vec<usize> = (0..10).map(|beat| first + beat * 100).collect();
+        assert_eq!(frames, expected);
+        assert_eq!(metronome.beat(), 10);
+
+        // a fractional beat length doesn't drift
+        let mut metronome = impulse_metronome(60000.0 / 33.3);
+        metronome.start();
+        let frames = onsets(&mut metronome, 1000, |_, _| ());
+        for (beat, onset) in frames.iter().enumerate() {
+            let exact = first as f64 + beat as f64 * 33.3;
+            assert!((*onset as f64 - exact).abs() <= 0.5, "beat {beat}: {onset}");
+        }
+    }
+
+    #[test]
+    fn set_bpm_takes_effect_on_the_next_beat() {
+        let mut metronome = impulse_metronome(600.0);
+        metronome.start();
+        let frames = onsets(&mut metronome, 500, |i, metronome| {
+            if i == 150 {
+                metronome.set_bpm(1200.0);
+            }
+            if i == 199 {
+                assert_eq!(metronome.bpm(), 600.0);
+            }
+        });
+        let first = frames[0];
+        let expected: Vec<usize> = [0, 100, 200, 250, 300, 350, 400, 450]
+            .iter()
+            .map(|frame| first + frame)
+            .collect();
+        assert_eq!(frames, expected);
+        assert_eq!(metronome.bpm(), 1200.0);
+    }
+}
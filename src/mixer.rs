@@ -1,13 +1,108 @@
-use crate::{DefaultRenderer, Frame, Renderer, RendererHandle, SoundHandle};
+use crate::{
+    AutoGain, Command, DefaultRenderer, Easing, Frame, MasterEffect, MeterBallistics, MeterData,
+    OnStreamGap, RenderEvent, RenderPath, Renderer, RendererHandle, SoundHandle, SoundQueue,
+    TransitionRule, VoicePolicy,
+};
 
 #[allow(unused_imports)] // for comments
 use crate::Sound;
 
+use std::time::Duration;
+
+#[cfg(feature = "diagnostics")]
+use crate::RenderLoad;
+
 #[cfg(feature = "cpal")]
-use crate::{Backend, Device, StreamSettings};
+use {
+    crate::{ActualStreamConfig, Backend, BackendEvent, Device, KaError, StreamSettings},
+    parking_lot::{Mutex, MutexGuard},
+    std::sync::{mpsc::RecvTimeoutError, Arc},
+};
+
+/// Settings for starting a sound with [`Mixer::play_ex`] or
+/// [`RecordMixer::play_ex`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PlaySettings {
+    /// Position (in seconds) to start playing from. The sound is seeked
+    /// before it is added to the mixer, so the first rendered frame is the
+    /// frame at this position. If this is past the end, the sound finishes
+    /// immediately.
+    pub start_at: f64,
+    /// How many seconds of source material to play before the sound stops.
+    /// This doesn't depend on the playback rate. If [`None`], the sound
+    /// plays until the end. See [`Sound::set_play_for`].
+    pub play_for: Option<f64>,
+    /// Panning to start playing with. If [`None`], the panning of the sound
+    /// is kept. See [`Sound::set_panning`].
+    pub panning: Option<f32>,
+}
+
+impl PlaySettings {
+    /// Return settings that play the region from `start` to `end`. If `end`
+    /// is not after `start`, the sound finishes immediately.
+    pub fn region(start: Duration, end: Duration) -> Self {
+        Self {
+            start_at: start.as_secs_f64(),
+            play_for: Some(end.saturating_sub(start).as_secs_f64()),
+            ..Default::default()
+        }
+    }
 
-use parking_lot::{Mutex, MutexGuard};
-use std::sync::Arc;
+    /// Apply the settings to a sound.
+    fn apply(&self, sound: &SoundHandle) {
+        let mut sound = sound.guard();
+        // the panning is applied to the frames the resampler is primed with
+        if let Some(panning) = self.panning {
+            sound.set_panning(panning);
+        }
+        if self.start_at != 0.0 {
+            sound.cue(self.start_at);
+        }
+        sound.set_play_for(self.play_for);
+    }
+}
+
+/// Check whether `sound` can be played when at most `max_instances` sounds
+/// sharing its frame data may play at once. If `steal` is true, the instances
+/// that played the furthest are faded out to make room.
+fn make_room(
+    live: Vec<SoundHandle>,
+    sound: &SoundHandle,
+    max_instances: usize,
+    steal: bool,
+) -> bool {
+    if max_instances == 0 {
+        return false;
+    }
+    let frames = sound.guard().frames.clone();
+    let mut instances: Vec<(usize, SoundHandle)> = live
+        .into_iter()
+        .filter(|other| !other.ptr_eq(sound))
+        .filter_map(|other| {
+            let guard = other.guard();
+            let instance = !guard.finished() && !guard.is_stolen() && guard.frames.ptr_eq(&frames);
+            let index = guard.index();
+            drop(guard);
+            instance.then_some((index, other))
+        })
+        .collect();
+    if instances.len() < max_instances {
+        return true;
+    }
+    if !steal {
+        return false;
+    }
+
+    // stop the instances that are the furthest into the sound
+    instances.sort_by_key(|(index, _)| std::cmp::Reverse(*index));
+    let excess = instances.len() + 1 - max_instances;
+    for (_, instance) in &instances[..excess] {
+        instance.guard().steal();
+    }
+    true
+}
 
 /// Audio mixer. The mixing is done by the [`Renderer`] ([`RendererHandle`]),
 /// and the audio playback is handled by the [`Backend`].
@@ -15,9 +110,20 @@ use std::sync::Arc;
 pub struct Mixer {
     /// Handle to the default audio renderer.
     pub renderer: RendererHandle<DefaultRenderer>,
+    /// The renderer's sound queue. Stored separately, so playing a sound
+    /// doesn't have to lock the renderer.
+    queue: SoundQueue,
     /// Handle to the underlying audio backend.
     #[cfg(feature = "cpal")]
     pub backend: Arc<Mutex<Backend>>,
+    /// The backend's event queue. Stored separately, as the backend is
+    /// locked while the stream is running.
+    #[cfg(feature = "cpal")]
+    event_queue: Arc<Mutex<Vec<BackendEvent>>>,
+    /// The backend's stream configuration, stored separately like the
+    /// event queue.
+    #[cfg(feature = "cpal")]
+    stream_config: Arc<Mutex<Option<ActualStreamConfig>>>,
 }
 
 impl Default for Mixer {
@@ -29,10 +135,18 @@ impl Default for Mixer {
 impl Mixer {
     /// Create a new audio mixer.
     pub fn new() -> Self {
+        #[cfg(feature = "cpal")]
+        let backend = Backend::new();
+        let renderer = DefaultRenderer::default();
         Self {
-            renderer: DefaultRenderer::default().into(),
+            queue: renderer.queue(),
+            renderer: renderer.into(),
+            #[cfg(feature = "cpal")]
+            event_queue: backend.event_queue.clone(),
+            #[cfg(feature = "cpal")]
+            stream_config: backend.stream_config.clone(),
             #[cfg(feature = "cpal")]
-            backend: Arc::new(Mutex::new(Backend::new())),
+            backend: Arc::new(Mutex::new(backend)),
         }
     }
 
@@ -43,17 +157,107 @@ impl Mixer {
         self.backend.lock()
     }
 
-    /// Play a [`Sound`].
+    /// Play a [`Sound`]. The sound starts playing on the next rendered frame.
+    ///
+    /// This never waits for the audio thread to finish rendering, see
+    /// [`SoundQueue`].
     ///
     /// Note: Cloning a [`Sound`] *does not* take any extra memory, as [`Sound`]
     /// shares frame data with all clones.
     #[inline]
     pub fn play(&mut self, sound: impl Into<SoundHandle>) -> SoundHandle {
         let handle = sound.into();
-        self.renderer.guard().add_sound(handle.clone());
+        self.queue.push(handle.clone());
         handle
     }
 
+    /// Play a [`Sound`] with custom [`PlaySettings`]. The settings are
+    /// applied before the sound is added to the mixer. Like [`Mixer::play`],
+    /// this never waits for the audio thread.
+    #[inline]
+    pub fn play_ex(
+        &mut self,
+        sound: impl Into<SoundHandle>,
+        settings: PlaySettings,
+    ) -> SoundHandle {
+        let handle = sound.into();
+        settings.apply(&handle);
+        self.queue.push(handle.clone());
+        handle
+    }
+
+    /// Play only the region of a [`Sound`] between `start` and `end`. The
+    /// sound is seeked to `start` and finishes once it played up to `end`,
+    /// without copying its frames into a new sound. See
+    /// [`PlaySettings`].
+    #[inline]
+    pub fn play_region(
+        &mut self,
+        sound: impl Into<SoundHandle>,
+        start: Duration,
+        end: Duration,
+    ) -> SoundHandle {
+        self.play_ex(sound, PlaySettings::region(start, end))
+    }
+
+    /// Play a [`Sound`], allowing at most `max_instances` instances of the
+    /// same source to play at once, for example to keep rapidly repeated
+    /// sound effects from piling up. Sounds are instances of the same source
+    /// if they share frame data, which is the case for clones of a [`Sound`].
+    ///
+    /// If the limit is reached and `steal` is true, the instances that
+    /// played the furthest are faded out quickly and stopped to make room.
+    /// Otherwise, the sound is
+    /// not played and [`None`] is returned.
+    pub fn play_limited(
+        &mut self,
+        sound: impl Into<SoundHandle>,
+        max_instances: usize,
+        steal: bool,
+    ) -> Option<SoundHandle> {
+        let handle = sound.into();
+        // keep the renderer locked until the sound is queued, so concurrent
+        // calls can't both make room for the same slot
+        let renderer = self.renderer.guard();
+        if !make_room(renderer.live_sounds(), &handle, max_instances, steal) {
+            return None;
+        }
+        self.queue.push(handle.clone());
+        Some(handle)
+    }
+
+    /// Replace all playing sounds at once, for example when switching
+    /// levels. No frame is rendered with a mix of the old and the new sounds.
+    /// Sounds that were queued with [`Mixer::play`] but didn't start yet and
+    /// transitions that didn't reach their sync point are discarded. Returns
+    /// the sounds that were playing before.
+    pub fn replace_all(
+        &mut self,
+        sounds: impl IntoIterator<Item = impl Into<SoundHandle>>,
+    ) -> Vec<SoundHandle> {
+        let sounds = sounds.into_iter().map(Into::into).collect();
+        self.renderer.guard().replace_sounds(sounds)
+    }
+
+    /// Switch from a playing sound to another one at the next beat, bar or
+    /// marker of the playing sound, with an optional crossfade. The switch is
+    /// done by the audio thread on the exact frame, so it doesn't depend on
+    /// how often the game updates. Returns the handle of the new sound.
+    ///
+    /// See [`TransitionRule`] for details.
+    pub fn transition(
+        &mut self,
+        from: &SoundHandle,
+        to: impl Into<SoundHandle>,
+        rule: TransitionRule,
+    ) -> SoundHandle {
+        let to = to.into();
+        self.renderer
+            .guard()
+            .transition(from.clone(), to.clone(), rule);
+        to
+    }
+
     /// Handle stream errors.
     #[inline]
     #[cfg(feature = "cpal")]
@@ -61,6 +265,14 @@ impl Mixer {
         self.backend().handle_errors(err_fn);
     }
 
+    /// Handle backend events, such as stream restarts and fallbacks to the
+    /// default device. See [`BackendEvent`].
+    #[inline]
+    #[cfg(feature = "cpal")]
+    pub fn handle_events(&self, event_fn: impl FnMut(BackendEvent)) {
+        self.event_queue.lock().drain(..).for_each(event_fn)
+    }
+
     /// Start the audio thread with default backend settings.
     #[inline]
     #[cfg(feature = "cpal")]
@@ -85,9 +297,58 @@ impl Mixer {
         });
     }
 
+    /// Start the audio thread with default backend settings, and wait until
+    /// the stream is running. See [`Mixer::try_init_ex`].
+    #[inline]
+    #[cfg(feature = "cpal")]
+    pub fn try_init(&self) -> Result<(), KaError> {
+        self.try_init_ex(Device::Default, StreamSettings::default())
+    }
+
+    /// Start the audio thread with custom backend settings, and wait until
+    /// the stream is running. Unlike [`Mixer::init_ex`], errors that keep
+    /// the stream from opening are returned, for example
+    /// [`KaError::NoOutputDevice`] on a system without audio devices (such
+    /// as a headless CI machine).
+    #[cfg(feature = "cpal")]
+    pub fn try_init_ex(&self, device: Device, settings: StreamSettings) -> Result<(), KaError> {
+        let backend = self.backend.clone();
+        let renderer = self.renderer.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            if let Err(err) = backend
+                .lock()
+                .start_audio_thread(device, settings, renderer)
+            {
+                let _ = sender.send(err);
+            }
+        });
+
+        // the stream config is set once the stream is running
+        loop {
+            match receiver.recv_timeout(Duration::from_millis(10)) {
+                Ok(err) => return Err(err),
+                Err(RecvTimeoutError::Timeout) if self.stream_config.lock().is_none() => (),
+                // the stream is running, or it already stopped without errors
+                Err(_) => return Ok(()),
+            }
+        }
+    }
+
+    /// Return the configuration the stream was actually opened with, or
+    /// [`None`] if the stream isn't running (yet). The stream is opened on
+    /// the audio thread, so this is [`None`] for a short time after
+    /// [`Mixer::init_ex`]. After a restart, this is the configuration of the
+    /// new stream, which can differ from the previous one.
+    #[cfg(feature = "cpal")]
+    #[inline]
+    pub fn stream_config(&self) -> Option<ActualStreamConfig> {
+        self.stream_config.lock().clone()
+    }
+
     /// Block the thread until all sounds are finished.
     pub fn wait(&self) {
-        while !self.renderer.guard().sounds.is_empty() {
+        while self.renderer.guard().has_sounds() {
             std::thread::sleep(std::time::Duration::from_millis(50));
         }
     }
@@ -103,6 +364,319 @@ impl Mixer {
     pub fn next_frame(&self, sample_rate: u32) -> Frame {
         self.renderer.guard().next_frame(sample_rate)
     }
+
+    /// Fill `out` with the next frames of the mix, for hosts that run their
+    /// own audio thread instead of using the [cpal] backend. The renderer is
+    /// locked once for the whole block, so no sounds or commands are added
+    /// in the middle of it. The output is the same as calling
+    /// [`Mixer::next_frame`] for every frame.
+    ///
+    /// Note: don't call this while the mixer has a running stream, as both
+    /// would advance the same sounds.
+    pub fn render_block(&self, sample_rate: u32, out: &mut [Frame]) {
+        let mut renderer = self.renderer.guard();
+        for frame in out {
+            *frame = renderer.next_frame(sample_rate);
+        }
+    }
+
+    /// Set how fast the whole mix plays, like varispeed on a tape machine:
+    /// a scale of 0.5 plays all sounds at half speed and an octave lower.
+    /// Useful for slow motion effects. Commands, automations and loops slow
+    /// down along with the sounds. See
+    /// [`DefaultRenderer::set_output_rate_scale`].
+    #[inline]
+    pub fn set_output_rate_scale(&self, scale: f64) {
+        self.renderer.guard().set_output_rate_scale(scale);
+    }
+
+    /// Smoothly change the output rate scale over `duration`. See
+    /// [`Mixer::set_output_rate_scale`].
+    #[inline]
+    pub fn tween_output_rate_scale(&self, scale: f64, duration: Duration, easing: Easing) {
+        self.renderer
+            .guard()
+            .tween_output_rate_scale(scale, duration, easing);
+    }
+
+    /// Return the current output rate scale. See
+    /// [`Mixer::set_output_rate_scale`].
+    #[inline]
+    pub fn output_rate_scale(&self) -> f64 {
+        self.renderer.guard().output_rate_scale()
+    }
+
+    /// Return the amount of playing sounds, including sounds that were
+    /// played but haven't been picked up by the renderer yet.
+    #[inline]
+    pub fn sound_count(&self) -> usize {
+        self.renderer.guard().sound_count()
+    }
+
+    /// Call `f` with every playing sound, for example to bend the pitch of
+    /// all sounds or to collect their positions. Sounds that were played but
+    /// haven't been picked up by the renderer yet are included.
+    ///
+    /// The renderer is locked once to collect the sounds, and is unlocked
+    /// before `f` is called, so `f` can use the mixer without deadlocking.
+    /// Each sound is locked while `f` uses it, which briefly blocks
+    /// rendering.
+    pub fn for_each_sound(&self, f: impl FnMut(&SoundHandle)) {
+        let sounds = self.renderer.guard().live_sounds();
+        sounds.iter().for_each(f);
+    }
+
+    /// Add a command to every playing sound, for example to fade out all
+    /// sounds. Sounds that were played but haven't been picked up by the
+    /// renderer yet are included. The command is added on top of the
+    /// commands the sounds already have.
+    ///
+    /// The renderer stays locked until the command was added to all sounds,
+    /// so they all start it on the same frame.
+    pub fn add_command_to_all(&self, command: Command) {
+        let renderer = self.renderer.guard();
+        for sound in renderer.live_sounds() {
+            sound.add_command(command.clone());
+        }
+    }
+
+    /// Add a command to each of the given sounds, so they all start it on
+    /// the same frame. See [`Mixer::add_command_to_all`].
+    pub fn add_command_to(&self, sounds: &[SoundHandle], command: Command) {
+        let _renderer = self.renderer.guard();
+        for sound in sounds {
+            sound.add_command(command.clone());
+        }
+    }
+
+    /// Pause or resume the mixer time.
+    ///
+    /// While paused, the mixer outputs silence, and no sound advances its
+    /// position or the timers of its commands, so all scheduled commands are
+    /// delayed by the time the mixer was paused. This is different from
+    /// pausing individual sounds with [`Sound::pause`], where the sound
+    /// stops advancing, but its commands keep running in render time.
+    #[inline]
+    pub fn set_paused(&self, paused: bool) {
+        self.renderer.guard().paused = paused;
+    }
+
+    /// Return whether the mixer time is paused. See [`Mixer::set_paused`].
+    #[inline]
+    pub fn paused(&self) -> bool {
+        self.renderer.guard().paused
+    }
+
+    /// Set whether the mixer should pause while no audio stream is active,
+    /// for example while the device is disconnected and the stream is being
+    /// restarted. This way no sounds advance while nothing can be heard.
+    ///
+    /// Note: this also pauses the mixer before [`Mixer::init`] starts the
+    /// first stream.
+    #[inline]
+    pub fn set_pause_without_stream(&self, pause: bool) {
+        self.renderer.guard().pause_without_stream = pause;
+    }
+
+    /// Set what the mixer does with the time that passed while no stream was
+    /// active, for example while the stream was restarted after a device
+    /// change. See [`OnStreamGap`].
+    #[inline]
+    pub fn set_stream_gap_policy(&self, policy: OnStreamGap) {
+        self.renderer.guard().stream_gap_policy = policy;
+    }
+
+    /// Return the position (in seconds) of the sound that is coming out of
+    /// the speakers right now.
+    ///
+    /// This is the sound's position compensated by the buffer size, the
+    /// output latency reported by the device and the offset set with
+    /// [`Mixer::set_latency_offset`]. The accuracy depends on how precisely
+    /// the device reports its latency, expect it to be within a few
+    /// milliseconds on most platforms.
+    pub fn audible_position(&self, sound: &SoundHandle) -> f64 {
+        let delay = self.renderer.guard().output_delay().as_secs_f64();
+        let sound = sound.guard();
+        let position = sound.position_seconds() - delay * sound.playback_rate().as_factor();
+        position.max(0.0)
+    }
+
+    /// Set an extra latency that is added to the latency reported by the
+    /// device. Used for calibrating [`Mixer::audible_position`] on devices
+    /// that misreport their latency.
+    #[inline]
+    pub fn set_latency_offset(&self, offset: Duration) {
+        self.renderer.guard().latency_offset = offset;
+    }
+
+    /// Return the latency offset set with [`Mixer::set_latency_offset`].
+    #[inline]
+    pub fn latency_offset(&self) -> Duration {
+        self.renderer.guard().latency_offset
+    }
+
+    /// Set how long sounds can play for while not paused before they are
+    /// faded out and removed, even if they loop. A safety net for sounds
+    /// that are played and forgotten, so a looping sound can't keep
+    /// [`Mixer::wait`] blocked forever. If [`None`] (default), sounds play
+    /// until they finish. See [`crate::Sound::set_max_play_time`].
+    #[inline]
+    pub fn set_max_sound_age(&self, age: Option<Duration>) {
+        self.renderer.guard().max_sound_age = age;
+    }
+
+    /// Return the maximum sound age set with [`Mixer::set_max_sound_age`].
+    #[inline]
+    pub fn max_sound_age(&self) -> Option<Duration> {
+        self.renderer.guard().max_sound_age
+    }
+
+    /// Set the maximum amount of sounds that are rendered at once. Every few
+    /// buffers, the sounds are ranked by [`crate::Sound::audibility`], and
+    /// the quietest sounds over the limit are stopped or made virtual,
+    /// depending on the [`VoicePolicy`]. If [`None`] (default), all sounds
+    /// are rendered.
+    #[inline]
+    pub fn set_max_voices(&self, max_voices: Option<usize>) {
+        self.renderer.guard().max_voices = max_voices;
+    }
+
+    /// Return the voice limit set with [`Mixer::set_max_voices`].
+    #[inline]
+    pub fn max_voices(&self) -> Option<usize> {
+        self.renderer.guard().max_voices
+    }
+
+    /// Set what happens to the sounds over the voice limit. See
+    /// [`Mixer::set_max_voices`].
+    #[inline]
+    pub fn set_voice_policy(&self, policy: VoicePolicy) {
+        self.renderer.guard().voice_policy = policy;
+    }
+
+    /// Return the voice policy set with [`Mixer::set_voice_policy`].
+    #[inline]
+    pub fn voice_policy(&self) -> VoicePolicy {
+        self.renderer.guard().voice_policy
+    }
+
+    /// Return the output latency of the stream: the duration of a buffer plus
+    /// the latency reported by the device. Returns [`None`] if no buffer was
+    /// rendered yet.
+    #[inline]
+    pub fn output_latency(&self) -> Option<Duration> {
+        let timing = self.renderer.guard().buffer_timing?;
+        Some(timing.duration() + timing.output_latency)
+    }
+
+    /// Return the amount of underruns (gaps where the device played silence
+    /// because the next buffer wasn't rendered in time) detected since the
+    /// mixer was created. If this keeps increasing, try a bigger buffer size.
+    #[inline]
+    pub fn underruns(&self) -> u64 {
+        self.renderer.guard().underruns
+    }
+
+    /// Return the peak, peak-hold and RMS levels and the phase correlation
+    /// of the mixed output.
+    #[inline]
+    pub fn meter_data(&self) -> MeterData {
+        self.renderer.guard().meter.data()
+    }
+
+    /// Return the phase correlation of the mixed output, from -1.0 (inverted
+    /// channels that cancel out in mono) to 1.0 (mono). See
+    /// [`crate::Meter::correlation`].
+    #[inline]
+    pub fn correlation(&self) -> f32 {
+        self.renderer.guard().meter.correlation()
+    }
+
+    /// Set the ballistics of the output meter. See [`MeterBallistics`].
+    #[inline]
+    pub fn set_meter_ballistics(&self, ballistics: MeterBallistics) {
+        self.renderer.guard().meter.set_ballistics(ballistics);
+    }
+
+    /// Set the automatic mixdown gain, which scales the output based on the
+    /// amount of audible sounds. See [`AutoGain`].
+    #[inline]
+    pub fn set_auto_gain(&self, auto_gain: AutoGain) {
+        self.renderer.guard().auto_gain = auto_gain;
+    }
+
+    /// Return the automatic mixdown gain set with [`Mixer::set_auto_gain`].
+    #[inline]
+    pub fn auto_gain(&self) -> AutoGain {
+        self.renderer.guard().auto_gain
+    }
+
+    /// Return the automatic gain that is currently applied to the output.
+    /// Useful for debugging [`Mixer::set_auto_gain`].
+    #[inline]
+    pub fn applied_gain(&self) -> f32 {
+        self.renderer.guard().applied_gain()
+    }
+
+    /// Set the volume of the whole mix. See [`crate::MasterChain`].
+    #[inline]
+    pub fn set_master_volume(&self, volume: f32) {
+        self.renderer.guard().master.volume = volume;
+    }
+
+    /// Return the volume of the whole mix.
+    #[inline]
+    pub fn master_volume(&self) -> f32 {
+        self.renderer.guard().master.volume
+    }
+
+    /// Add an effect to the end of the master chain, for example a limiter.
+    /// See [`crate::MasterChain`].
+    #[inline]
+    pub fn add_master_effect(&self, effect: impl MasterEffect + 'static) {
+        self.renderer.guard().master.push(effect);
+    }
+
+    /// Remove all effects from the master chain.
+    #[inline]
+    pub fn clear_master_effects(&self) {
+        self.renderer.guard().master.effects.clear();
+    }
+
+    /// Return how much of the audio budget rendering takes: the average and
+    /// maximum time it takes to render a buffer, and how that compares to
+    /// the duration of the buffer.
+    ///
+    /// Required features: `diagnostics`
+    #[inline]
+    #[cfg(feature = "diagnostics")]
+    pub fn render_load(&self) -> RenderLoad {
+        self.renderer.guard().diagnostics.render_load()
+    }
+
+    /// Measure how long each sound takes to render every `interval`
+    /// buffers, see [`Mixer::sound_costs`]. If [`None`], sound costs are not
+    /// measured (default).
+    ///
+    /// Required features: `diagnostics`
+    #[inline]
+    #[cfg(feature = "diagnostics")]
+    pub fn set_sound_cost_interval(&self, interval: Option<u32>) {
+        self.renderer
+            .guard()
+            .diagnostics
+            .set_sound_cost_interval(interval);
+    }
+
+    /// Return the time each sound took to render in the last measured
+    /// buffer. Useful for finding expensive sounds. See
+    /// [`Mixer::set_sound_cost_interval`].
+    ///
+    /// Required features: `diagnostics`
+    #[cfg(feature = "diagnostics")]
+    pub fn sound_costs(&self) -> Vec<(SoundHandle, Duration)> {
+        self.renderer.guard().diagnostics.sound_costs().to_vec()
+    }
 }
 
 /// A mixer for recording audio.
@@ -123,11 +697,67 @@ impl Default for RecordMixer {
 impl RecordMixer {
     /// Create a new audio recording mixer.
     pub fn new() -> Self {
+        let mut renderer = DefaultRenderer::default();
+        renderer.path = RenderPath::Record;
         Self {
-            renderer: DefaultRenderer::default().into(),
+            renderer: renderer.into(),
         }
     }
 
+    /// Return the mixer with the given master volume. See
+    /// [`RecordMixer::set_master_volume`].
+    #[inline]
+    pub fn with_master_volume(self, volume: f32) -> Self {
+        self.set_master_volume(volume);
+        self
+    }
+
+    /// Return the mixer with an effect added to the master chain. See
+    /// [`RecordMixer::add_master_effect`].
+    #[inline]
+    pub fn with_master_effect(self, effect: impl MasterEffect + 'static) -> Self {
+        self.add_master_effect(effect);
+        self
+    }
+
+    /// Set the volume of the whole mix. See [`Mixer::set_master_volume`].
+    #[inline]
+    pub fn set_master_volume(&self, volume: f32) {
+        self.renderer.guard().master.volume = volume;
+    }
+
+    /// Return the volume of the whole mix.
+    #[inline]
+    pub fn master_volume(&self) -> f32 {
+        self.renderer.guard().master.volume
+    }
+
+    /// Add an effect to the end of the master chain. See
+    /// [`Mixer::add_master_effect`].
+    #[inline]
+    pub fn add_master_effect(&self, effect: impl MasterEffect + 'static) {
+        self.renderer.guard().master.push(effect);
+    }
+
+    /// Remove all effects from the master chain.
+    #[inline]
+    pub fn clear_master_effects(&self) {
+        self.renderer.guard().master.effects.clear();
+    }
+
+    /// Set the automatic mixdown gain. See [`Mixer::set_auto_gain`].
+    #[inline]
+    pub fn set_auto_gain(&self, auto_gain: AutoGain) {
+        self.renderer.guard().auto_gain = auto_gain;
+    }
+
+    /// Return the automatic mixdown gain set with
+    /// [`RecordMixer::set_auto_gain`].
+    #[inline]
+    pub fn auto_gain(&self) -> AutoGain {
+        self.renderer.guard().auto_gain
+    }
+
     /// Play a [`Sound`] in the recording mixer. The samples of the sound are
     /// only processed when `fill_buffer` is called.
     ///
@@ -140,14 +770,193 @@ impl RecordMixer {
         handle
     }
 
+    /// Play a [`Sound`] with custom [`PlaySettings`] in the recording mixer.
+    /// The settings are applied before the sound is added to the mixer.
+    #[inline]
+    pub fn play_ex(&self, sound: impl Into<SoundHandle>, settings: PlaySettings) -> SoundHandle {
+        let handle: SoundHandle = sound.into();
+        settings.apply(&handle);
+        self.renderer.guard().add_sound(handle.clone());
+        handle
+    }
+
+    /// Play only the region of a [`Sound`] between `start` and `end` in the
+    /// recording mixer. See [`Mixer::play_region`].
+    #[inline]
+    pub fn play_region(
+        &self,
+        sound: impl Into<SoundHandle>,
+        start: Duration,
+        end: Duration,
+    ) -> SoundHandle {
+        self.play_ex(sound, PlaySettings::region(start, end))
+    }
+
+    /// Play a [`Sound`] in the recording mixer, allowing at most
+    /// `max_instances` instances of the same source to play at once. See
+    /// [`Mixer::play_limited`].
+    pub fn play_limited(
+        &self,
+        sound: impl Into<SoundHandle>,
+        max_instances: usize,
+        steal: bool,
+    ) -> Option<SoundHandle> {
+        let handle: SoundHandle = sound.into();
+        let mut renderer = self.renderer.guard();
+        if !make_room(renderer.live_sounds(), &handle, max_instances, steal) {
+            return None;
+        }
+        renderer.add_sound(handle.clone());
+        Some(handle)
+    }
+
     /// Return whether all sounds are finished or not.
     #[inline]
     pub fn is_finished(&self) -> bool {
         !self.renderer.guard().has_sounds()
     }
 
+    /// Return the amount of playing sounds.
+    #[inline]
+    pub fn sound_count(&self) -> usize {
+        self.renderer.guard().sound_count()
+    }
+
+    /// Set how long sounds can play for before they are faded out and
+    /// removed. See [`Mixer::set_max_sound_age`].
+    #[inline]
+    pub fn set_max_sound_age(&self, age: Option<Duration>) {
+        self.renderer.guard().max_sound_age = age;
+    }
+
+    /// Return the maximum sound age set with
+    /// [`RecordMixer::set_max_sound_age`].
+    #[inline]
+    pub fn max_sound_age(&self) -> Option<Duration> {
+        self.renderer.guard().max_sound_age
+    }
+
+    /// Start recording events, such as sounds starting and finishing, loops
+    /// wrapping and commands finishing, along with the index of the output
+    /// frame they happened in. Useful for lining up visuals with an offline
+    /// render. See [`RenderEvent`].
+    ///
+    /// Frames are counted from the first frame the mixer rendered, so for a
+    /// new mixer, the frame index is the position in the rendered audio.
+    #[inline]
+    pub fn enable_event_log(&self) {
+        let mut renderer = self.renderer.guard();
+        if renderer.event_log.is_none() {
+            renderer.event_log = Some(vec![]);
+        }
+    }
+
+    /// Stop recording events and discard the recorded ones.
+    #[inline]
+    pub fn disable_event_log(&self) {
+        self.renderer.guard().event_log = None;
+    }
+
+    /// Take the events recorded since the last call, in the order they
+    /// happened. Returns an empty list if the event log is not enabled. See
+    /// [`RecordMixer::enable_event_log`].
+    #[inline]
+    pub fn take_event_log(&self) -> Vec<RenderEvent> {
+        self.renderer
+            .guard()
+            .event_log
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Set the maximum amount of sounds that are rendered at once. See
+    /// [`Mixer::set_max_voices`].
+    #[inline]
+    pub fn set_max_voices(&self, max_voices: Option<usize>) {
+        self.renderer.guard().max_voices = max_voices;
+    }
+
+    /// Set what happens to the sounds over the voice limit. See
+    /// [`Mixer::set_voice_policy`].
+    #[inline]
+    pub fn set_voice_policy(&self, policy: VoicePolicy) {
+        self.renderer.guard().voice_policy = policy;
+    }
+
+    /// Set how fast the whole mix plays. See [`Mixer::set_output_rate_scale`].
+    #[inline]
+    pub fn set_output_rate_scale(&self, scale: f64) {
+        self.renderer.guard().set_output_rate_scale(scale);
+    }
+
+    /// Smoothly change the output rate scale over `duration`. See
+    /// [`Mixer::set_output_rate_scale`].
+    #[inline]
+    pub fn tween_output_rate_scale(&self, scale: f64, duration: Duration, easing: Easing) {
+        self.renderer
+            .guard()
+            .tween_output_rate_scale(scale, duration, easing);
+    }
+
+    /// Return the current output rate scale.
+    #[inline]
+    pub fn output_rate_scale(&self) -> f64 {
+        self.renderer.guard().output_rate_scale()
+    }
+
+    /// Return the levels and the phase correlation of the rendered output.
+    /// See [`Mixer::meter_data`].
+    #[inline]
+    pub fn meter_data(&self) -> MeterData {
+        self.renderer.guard().meter.data()
+    }
+
+    /// Return the phase correlation of the rendered output. See
+    /// [`Mixer::correlation`].
+    #[inline]
+    pub fn correlation(&self) -> f32 {
+        self.renderer.guard().meter.correlation()
+    }
+
+    /// Set the ballistics of the output meter. See [`MeterBallistics`].
+    #[inline]
+    pub fn set_meter_ballistics(&self, ballistics: MeterBallistics) {
+        self.renderer.guard().meter.set_ballistics(ballistics);
+    }
+
+    /// Call `f` with every playing sound. See [`Mixer::for_each_sound`].
+    pub fn for_each_sound(&self, f: impl FnMut(&SoundHandle)) {
+        let sounds = self.renderer.guard().live_sounds();
+        sounds.iter().for_each(f);
+    }
+
+    /// Add a command to every playing sound. See
+    /// [`Mixer::add_command_to_all`].
+    pub fn add_command_to_all(&self, command: Command) {
+        let renderer = self.renderer.guard();
+        for sound in renderer.live_sounds() {
+            sound.add_command(command.clone());
+        }
+    }
+
+    /// Add a command to each of the given sounds. See
+    /// [`Mixer::add_command_to`].
+    pub fn add_command_to(&self, sounds: &[SoundHandle], command: Command) {
+        let _renderer = self.renderer.guard();
+        for sound in sounds {
+            sound.add_command(command.clone());
+        }
+    }
+
     /// Fill the given buffer with audio samples. When the buffer is processed,
     /// no other samples are rendered before the next call to this function.
+    ///
+    /// The frames are the full precision [`f32`] output of the renderer, with
+    /// the master chain applied like in [`Mixer`]. They are not clamped or
+    /// converted to another sample format, unlike the output of the
+    /// [`Backend`](crate::Backend), which converts them to the format of the
+    /// device. See [`crate::frames_to_i16`] to convert them yourself.
     pub fn fill_buffer(&self, sample_rate: u32, frames: &mut [Frame]) {
         let mut renderer = self.renderer.guard(); // acquire lock for this entire function
         for frame in frames {
@@ -160,4 +969,532 @@ impl RecordMixer {
     pub fn next_frame(&self, sample_rate: u32) -> Frame {
         self.renderer.guard().next_frame(sample_rate)
     }
+
+    /// Render the window from `start` to `end` (in seconds from now) and
+    /// return its frames. The mixer is fast-forwarded to `start` (see
+    /// [`DefaultRenderer::fast_forward`]): sounds, commands and loops advance
+    /// exactly like in a full render without producing output, so the
+    /// window matches the same region of a full render bit for bit. Useful
+    /// for asserting the timing of cues in tests.
+    ///
+    /// The mixer is left at `end`.
+    pub fn render_region(&self, sample_rate: u32, start: f64, end: f64) -> Vec<Frame> {
+        let to_frames = |seconds: f64| (seconds.max(0.0) * sample_rate as f64).round() as usize;
+        let (start, end) = (to_frames(start), to_frames(end));
+
+        let mut renderer = self.renderer.guard();
+        for _ in 0..start {
+            renderer.fast_forward(sample_rate);
+        }
+        (start..end)
+            .map(|_| renderer.next_frame(sample_rate))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Sound;
+
+    const SAMPLE_RATE: u32 = 1000;
+
+    /// Return a sound that plays `value` on both channels for `len` frames.
+    fn constant(value: f32, len: usize) -> Sound {
+        Sound::from_mono_samples(SAMPLE_RATE, &vec![value; len])
+    }
+
+    #[test]
+    fn play_limited_drops_or_steals() {
+        let mixer = RecordMixer::new();
+        let source = constant(0.1, 1000);
+        let first = mixer.play_limited(source.clone(), 2, false).unwrap();
+        mixer.next_frame(SAMPLE_RATE);
+        let second = mixer.play_limited(source.clone(), 2, false).unwrap();
+        mixer.next_frame(SAMPLE_RATE);
+        assert!(mixer.play_limited(source.clone(), 2, false).is_none());
+        // other sources aren't limited
+        assert!(mixer.play_limited(constant(0.1, 1000), 2, false).is_some());
+
+        // the instance that played the furthest is stolen
+        let third = mixer.play_limited(source.clone(), 2, true).unwrap();
+        for _ in 0..50 {
+            mixer.next_frame(SAMPLE_RATE);
+        }
+        assert!(first.finished());
+        assert!(!second.finished());
+        assert!(!third.finished());
+    }
+
+    #[test]
+    fn play_limited_is_atomic() {
+        let mixer = Mixer::new();
+        let source = constant(0.1, 1000);
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let mut mixer = mixer.clone();
+                let source = source.clone();
+                std::thread::spawn(move || mixer.play_limited(source, 3, false).is_some())
+            })
+            .collect();
+        let played = threads
+            .into_iter()
+            .filter_map(|thread| thread.join().ok())
+            .filter(|&played| played)
+            .count();
+        assert_eq!(played, 3);
+        assert_eq!(mixer.sound_count(), 3);
+    }
+
+    #[test]
+    fn add_command_to_all_reaches_queued_sounds() {
+        let mut mixer = Mixer::new();
+        let mut sounds: Vec<SoundHandle> =
+            (0..3).map(|_| mixer.play(constant(0.5, 1000))).collect();
+        for _ in 0..10 {
+            mixer.renderer.guard().next_frame(SAMPLE_RATE);
+        }
+
+        // played, but not picked up by the renderer yet
+        sounds.push(mixer.play(constant(0.5, 1000)));
+        assert_eq!(mixer.renderer.guard().queue().len(), 1);
+
+        mixer.add_command_to_all(Command::new(
+            crate::Change::Volume(0.0),
+            Easing::Linear,
+            0.0,
+            0.0,
+        ));
+        let later = mixer.play(constant(0.5, 1000));
+        for _ in 0..10 {
+            mixer.renderer.guard().next_frame(SAMPLE_RATE);
+        }
+        for (i, sound) in sounds.iter().enumerate() {
+            assert_eq!(sound.volume(), 0.0, "sound {i}");
+        }
+        assert_eq!(later.volume(), 1.0);
+    }
+
+    #[test]
+    fn replace_all_swaps_the_sounds_on_one_frame() {
+        let mut mixer = Mixer::new();
+        let old = mixer.play(constant(0.25, 1000));
+        for _ in 0..10 {
+            assert_eq!(mixer.next_frame(SAMPLE_RATE).left, 0.25);
+        }
+        // queued, but not started yet
+        let pending = mixer.play(constant(0.125, 1000));
+        let pending_index = pending.index();
+
+        let replaced = mixer.replace_all([constant(0.5, 1000), constant(0.0625, 1000)]);
+        assert_eq!(replaced.len(), 1);
+        assert!(replaced[0].ptr_eq(&old));
+        assert_eq!(mixer.sound_count(), 2);
+
+        // only the new sounds are heard, starting with the next frame
+        for _ in 0..10 {
+            assert_eq!(mixer.next_frame(SAMPLE_RATE).left, 0.5625);
+        }
+        assert_eq!(pending.index(), pending_index);
+        assert_eq!(mixer.sound_count(), 2);
+    }
+
+    #[test]
+    fn record_only_sounds_are_recorded_but_not_heard() {
+        let limited = |value: f32, record_only: bool| {
+            let mut sound = constant(value, 1000);
+            if record_only {
+                sound.set_record_only(true);
+            } else {
+                sound.set_monitor_only(true);
+            }
+            sound
+        };
+        let mut live = Mixer::new();
+        let record = RecordMixer::new();
+        live.play(constant(0.25, 1000));
+        record.play(constant(0.25, 1000));
+        let live_click = live.play(limited(0.5, true));
+        let record_click = record.play(limited(0.5, true));
+        let live_cue = live.play(limited(0.125, false));
+        let record_cue = record.play(limited(0.125, false));
+
+        for _ in 0..100 {
+            assert_eq!(live.next_frame(SAMPLE_RATE).left, 0.375);
+            assert_eq!(record.next_frame(SAMPLE_RATE).left, 0.75);
+        }
+        // sounds that aren't heard still advance, so both paths stay in sync
+        assert!(live_click.index() >= 100);
+        assert_eq!(live_click.index(), record_click.index());
+        assert_eq!(live_cue.index(), record_cue.index());
+    }
+
+    #[cfg(feature = "cpal")]
+    #[test]
+    #[ignore = "needs an audio output device"]
+    fn stream_config_is_set_after_init() {
+        let mixer = Mixer::new();
+        assert!(mixer.stream_config().is_none());
+        let settings = StreamSettings {
+            sample_format: Some(cpal::SampleFormat::F32),
+            ..Default::default()
+        };
+        mixer.try_init_ex(Device::Default, settings).unwrap();
+
+        let config = mixer.stream_config().unwrap();
+        assert!(!config.device_name.is_empty());
+        assert!(config.channels > 0);
+        assert!(config.sample_rate > 0);
+        assert_eq!(config.sample_format, cpal::SampleFormat::F32);
+    }
+
+    /// Return a mixer with sounds that exercise resampling, loops, commands,
+    /// LFOs, start delays, fades and stateful filters.
+    fn render_region_scene() -> RecordMixer {
+        let sine = |sample_rate: u32, hz: f32, seconds: f32| {
+            let frames: Vec<Frame> = (0..(sample_rate as f32 * seconds) as usize)
+                .map(|i| {
+                    let phase = i as f32 / sample_rate as f32 * hz * std::f32::consts::TAU;
+                    Frame::new(phase.sin(), phase.cos() * 0.5)
+                })
+                .collect();
+            Sound::from_frames(sample_rate, &frames)
+        };
+        let mixer = RecordMixer::new();
+
+        let mut looped = sine(44100, 220.0, 0.5);
+        looped.set_loop_index(1000..=15000);
+        looped.set_loop_enabled(true);
+        looped.set_playback_rate(crate::PlaybackRate::Factor(1.3));
+        looped.add_command(Command::new(
+            crate::Change::Volume(0.2),
+            Easing::SineInOut,
+            0.2,
+            0.3,
+        ));
+        looped.set_lfo(
+            crate::LfoTarget::Panning,
+            crate::Lfo::new(crate::LfoRate::Hz(3.0), 0.8),
+        );
+        mixer.play(looped);
+
+        let mut delayed = sine(48000, 440.0, 1.0);
+        delayed.set_latency_offset(5000);
+        delayed.stop_with_fade(Duration::from_millis(600), Easing::Linear);
+        mixer.play(delayed);
+
+        let mut filtered = sine(22050, 330.0, 2.0);
+        filtered.set_tone(6.0, -4.0);
+        filtered.set_dc_block(true);
+        mixer.play(filtered);
+        mixer
+    }
+
+    #[test]
+    fn render_region_matches_full_render() {
+        let full = render_region_scene().render_region(48000, 0.0, 1.0);
+        let region = render_region_scene().render_region(48000, 0.7, 1.0);
+        assert_eq!(region.len(), 14400);
+        assert!(region.iter().any(|frame| frame.left != 0.0));
+        assert_eq!(region, full[33600..]);
+    }
+
+    #[test]
+    fn mixer_pause_freezes_command_timers() {
+        // lowers the volume after 100 ms of playback
+        let play = |mixer: &mut Mixer| {
+            let mut sound = constant(1.0, 1000);
+            let change = crate::Change::Volume(0.5);
+            sound.add_command(Command::new(change, Easing::Linear, 0.1, 0.0));
+            mixer.play(sound)
+        };
+        let render = |mixer: &Mixer, frames: usize| -> Vec<f32> {
+            let mut renderer = mixer.renderer.guard();
+            (0..frames)
+                .map(|_| renderer.next_frame(SAMPLE_RATE).left)
+                .collect()
+        };
+
+        // pausing the mixer delays the command by the time it was paused
+        let mut mixer = Mixer::new();
+        play(&mut mixer);
+        render(&mixer, 50);
+        mixer.set_paused(true);
+        assert!(render(&mixer, 100).iter().all(|&level| level == 0.0));
+        mixer.set_paused(false);
+        let levels = render(&mixer, 100);
+        assert!(levels[..49].iter().all(|&level| level == 1.0));
+        assert!(levels[60..].iter().all(|&level| level == 0.5));
+
+        // a paused sound keeps running its commands in render time
+        let mut mixer = Mixer::new();
+        let sound = play(&mut mixer);
+        render(&mixer, 50);
+        sound.pause();
+        render(&mixer, 100);
+        sound.resume();
+        let levels = render(&mixer, 100);
+        assert!(levels[10..].iter().all(|&level| level == 0.5));
+    }
+
+    #[test]
+    fn for_each_sound_visits_playing_sounds() {
+        let mut mixer = Mixer::new();
+        let finished = mixer.play(constant(0.1, 5));
+        let playing = mixer.play(constant(0.1, 1000));
+        {
+            let mut renderer = mixer.renderer.guard();
+            for _ in 0..10 {
+                renderer.next_frame(SAMPLE_RATE);
+            }
+        }
+        assert!(finished.finished());
+        let queued = mixer.play(constant(0.1, 1000));
+
+        let mut visited = Vec::new();
+        mixer.for_each_sound(|sound| {
+            // the mixer can be used without deadlocking
+            assert_eq!(mixer.sound_count(), 2);
+            visited.push(sound.clone());
+        });
+        assert_eq!(visited.len(), 2);
+        assert!(visited.iter().any(|sound| sound.ptr_eq(&playing)));
+        assert!(visited.iter().any(|sound| sound.ptr_eq(&queued)));
+    }
+
+    #[test]
+    fn play_region_renders_only_the_region() {
+        let mixer = RecordMixer::new();
+        let ramp: Vec<f32> = (0..1000).map(|i| i as f32 / 1000.0).collect();
+        let sound = mixer.play_region(
+            Sound::from_mono_samples(SAMPLE_RATE, &ramp),
+            Duration::from_millis(200),
+            Duration::from_millis(500),
+        );
+
+        let rendered: Vec<f32> = (0..400)
+            .map(|_| mixer.next_frame(SAMPLE_RATE).left)
+            .collect();
+        assert_eq!(rendered[..300], ramp[200..500]);
+        assert!(rendered[300..].iter().all(|&sample| sample == 0.0));
+        assert!(sound.finished());
+    }
+
+    #[test]
+    fn offline_render_matches_live_master_chain() {
+        // a stateful effect: a lowpass into a soft clipper
+        let effect = || {
+            let mut state = Frame::ZERO;
+            move |frame: Frame, _sample_rate: u32| {
+                state = state * 0.5 + frame * 0.5;
+                Frame::new(state.left.tanh(), state.right.tanh())
+            }
+        };
+        let sound = || {
+            let samples: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.1).sin() * 1.5).collect();
+            let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &samples);
+            sound.set_playback_rate(crate::PlaybackRate::Factor(1.3));
+            sound
+        };
+
+        let mut live = Mixer::new();
+        live.set_master_volume(0.7);
+        live.add_master_effect(effect());
+        live.play(sound());
+        let live: Vec<Frame> = {
+            let mut renderer = live.renderer.guard();
+            (0..800).map(|_| renderer.next_frame(SAMPLE_RATE)).collect()
+        };
+
+        let offline = RecordMixer::new()
+            .with_master_volume(0.7)
+            .with_master_effect(effect());
+        offline.play(sound());
+        let mut rendered = vec![Frame::ZERO; 800];
+        offline.fill_buffer(SAMPLE_RATE, &mut rendered);
+        assert_eq!(rendered, live);
+
+        // the chain is applied
+        let plain = RecordMixer::new();
+        plain.play(sound());
+        let mut unprocessed = vec![Frame::ZERO; 800];
+        plain.fill_buffer(SAMPLE_RATE, &mut unprocessed);
+        assert_ne!(unprocessed, live);
+    }
+
+    #[test]
+    fn looped_sounds_end_at_their_max_play_time() {
+        let looped = |frames: usize| {
+            let mut sound = constant(1.0, frames);
+            sound.set_loop_enabled(true);
+            sound
+        };
+        let render = |mixer: &RecordMixer, frames: usize| -> Vec<f32> {
+            let mut buffer = vec![Frame::ZERO; frames];
+            mixer.fill_buffer(SAMPLE_RATE, &mut buffer);
+            buffer.iter().map(|frame| frame.left).collect()
+        };
+        // the fade reaches silence on frame `end`, and the sound is removed
+        let assert_ends_at = |mixer: &RecordMixer, levels: &[f32], end: usize| {
+            assert_eq!(levels[end - 6], 1.0);
+            assert!(levels[end - 6..end].windows(2).all(|w| w[1] < w[0]));
+            assert!(levels[end - 1..].iter().all(|&level| level == 0.0));
+            assert!(mixer.is_finished());
+        };
+
+        let mixer = RecordMixer::new();
+        let mut sound = looped(100);
+        sound.set_max_play_time(Duration::from_millis(500));
+        mixer.play(sound);
+        let levels = render(&mixer, 1000);
+        assert!(levels[..494].iter().all(|&level| level == 1.0));
+        assert_ends_at(&mixer, &levels, 500);
+
+        // paused time isn't counted
+        let mixer = RecordMixer::new();
+        let mut sound = looped(100);
+        sound.set_max_play_time(Duration::from_millis(500));
+        let handle = mixer.play(sound);
+        let mut levels = render(&mixer, 200);
+        handle.pause();
+        render(&mixer, 300);
+        handle.resume();
+        levels.extend(render(&mixer, 800));
+        // the resampler is refilled for a few frames after resuming
+        assert!(levels[203..494].iter().all(|&level| level == 1.0));
+        assert_ends_at(&mixer, &levels, 500);
+
+        // the renderer-wide limit applies to sounds without their own limit
+        let mixer = RecordMixer::new();
+        mixer.set_max_sound_age(Some(Duration::from_millis(300)));
+        mixer.play(looped(100));
+        let levels = render(&mixer, 1000);
+        assert!(levels[..294].iter().all(|&level| level == 1.0));
+        assert_ends_at(&mixer, &levels, 300);
+    }
+
+    #[test]
+    fn empty_sounds_play_in_both_mixers() {
+        let empty = || [Sound::default(), Sound::from_frames(SAMPLE_RATE, &[])];
+        for mut sound in empty() {
+            assert_eq!(sound.duration(), Duration::ZERO);
+            assert!(sound.finished());
+            let index = sound.index();
+            sound.seek_to(1.0);
+            sound.seek_by_frames(-10);
+            assert_eq!(sound.index(), index);
+            assert_eq!(sound.next_frame(SAMPLE_RATE), None);
+        }
+
+        let mut mixer = Mixer::new();
+        let mut out = [Frame::new(1.0, 1.0); 64];
+        for sound in empty() {
+            mixer.play(sound);
+        }
+        mixer.render_block(SAMPLE_RATE, &mut out);
+        assert!(out.iter().all(|&frame| frame == Frame::ZERO));
+        assert!(mixer.is_finished());
+
+        let mixer = RecordMixer::new();
+        for sound in empty() {
+            mixer.play(sound);
+        }
+        assert!(mixer
+            .render_region(SAMPLE_RATE, 0.0, 0.1)
+            .iter()
+            .all(|&frame| frame == Frame::ZERO));
+        assert!(mixer.is_finished());
+    }
+
+    #[test]
+    fn render_block_matches_next_frame() {
+        let scene = || {
+            let mut mixer = Mixer::new();
+            let mut sound = constant(0.5, 800);
+            sound.set_playback_rate(crate::PlaybackRate::Factor(0.7));
+            let change = crate::Change::Panning(-0.8);
+            sound.add_command(Command::new(change, Easing::QuadInOut, 0.1, 0.4));
+            mixer.play(sound);
+            mixer
+        };
+
+        let mut blocks = scene();
+        let mut frames = scene();
+        let (mut rendered, mut expected) = (Vec::new(), Vec::new());
+        for (i, len) in [1, 7, 64, 300, 0, 513].into_iter().enumerate() {
+            // sounds played between blocks are heard in the next block
+            if i == 3 {
+                blocks.play(constant(0.25, 200));
+                frames.play(constant(0.25, 200));
+            }
+            let mut out = vec![Frame::ZERO; len];
+            blocks.render_block(SAMPLE_RATE, &mut out);
+            rendered.extend(out);
+            expected.extend((0..len).map(|_| frames.next_frame(SAMPLE_RATE)));
+        }
+        assert_eq!(rendered, expected);
+        assert!(rendered.iter().any(|frame| frame.left != frame.right));
+    }
+
+    #[test]
+    fn event_log_frames_match_the_audio() {
+        use crate::RenderEventKind;
+
+        let render = |mixer: &RecordMixer, frames: usize| -> Vec<f32> {
+            let mut buffer = vec![Frame::ZERO; frames];
+            mixer.fill_buffer(SAMPLE_RATE, &mut buffer);
+            buffer.iter().map(|frame| frame.left).collect()
+        };
+        let mixer = RecordMixer::new();
+        mixer.enable_event_log();
+        let mut levels = render(&mixer, 150);
+
+        // a loop with a loud marker on its first frame, which halves its
+        // volume from 0.2 to 0.3 seconds and stops after 0.45 seconds
+        let mut samples = vec![0.1; 100];
+        samples[0] = 1.0;
+        let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &samples);
+        sound.set_loop_enabled(true);
+        let change = crate::Change::Volume(0.5);
+        sound.add_command(Command::new(change, Easing::Linear, 0.2, 0.1));
+        sound.set_max_play_time(Duration::from_millis(450));
+        mixer.play(sound);
+        levels.extend(render(&mixer, 600));
+
+        let is_marker = |frame: u64| levels[frame as usize] > 0.4;
+        let events = mixer.take_event_log();
+        let frames_of = |kind: fn(&RenderEventKind) -> bool| -> Vec<u64> {
+            events
+                .iter()
+                .filter(|event| kind(&event.kind))
+                .map(|event| event.frame)
+                .collect()
+        };
+
+        let started = frames_of(|kind| matches!(kind, RenderEventKind::Started));
+        assert_eq!(started, [150]);
+        assert!(levels[..150].iter().all(|&level| level == 0.0));
+        assert!(is_marker(150));
+
+        // every wrap is logged on the frame the marker is heard again
+        let wraps = frames_of(|kind| matches!(kind, RenderEventKind::LoopWrapped));
+        assert_eq!(wraps.len(), 4);
+        for frame in wraps {
+            assert!(is_marker(frame) && !is_marker(frame - 1), "{frame}");
+        }
+
+        // the volume stays at the target of the command once it finished
+        let command = frames_of(|kind| matches!(kind, RenderEventKind::CommandFinished(_)));
+        assert_eq!(command, [150 + 299]);
+        assert!(levels[400] > 0.07);
+        assert!(levels[460..540]
+            .iter()
+            .all(|&level| (level - 0.05).abs() < 0.005));
+
+        // the sound is removed right after its fade out reached silence
+        let finished = frames_of(|kind| matches!(kind, RenderEventKind::Finished));
+        assert_eq!(finished, [600]);
+        assert!(levels[590] > 0.0);
+        assert!(levels[599..].iter().all(|&level| level == 0.0));
+    }
 }
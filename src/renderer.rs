@@ -1,6 +1,42 @@
-use crate::{Frame, SoundHandle};
+use crate::{
+    BufferedRenderer, Change, Easing, Frame, Meter, Metronome, OutputRoute, Parameter,
+    PendingTransition, SoundHandle, StreamingSource, TransitionRule, VoiceState,
+};
+
+#[cfg(feature = "cpal")]
+use crate::logging::log_warn;
+
+#[cfg(feature = "diagnostics")]
+use crate::Diagnostics;
 use parking_lot::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Timing information about a buffer requested by the backend.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BufferTiming {
+    /// When the backend requested the buffer.
+    pub callback: Instant,
+    /// Amount of frames in the buffer.
+    pub frames: usize,
+    /// Sample rate of the stream.
+    pub sample_rate: u32,
+    /// Time between the request and the moment the first frame of the buffer
+    /// is played by the device, as reported by [cpal].
+    pub output_latency: Duration,
+}
+
+impl BufferTiming {
+    /// Return the duration of the buffer.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        if self.sample_rate == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.frames as f64 / self.sample_rate as f64)
+    }
+}
 
 /// The audio renderer trait. Can be used to make custom audio renderers.
 pub trait Renderer: Clone + Send + 'static {
@@ -10,6 +46,18 @@ pub trait Renderer: Clone + Send + 'static {
     /// Note: you can use a [`crate::Resampler`] to resample audio data.
     fn next_frame(&mut self, sample_rate: u32) -> Frame;
 
+    /// Render the next audio frame into all output channels of the stream.
+    /// `out` has one sample per channel.
+    ///
+    /// The default implementation writes [`Renderer::next_frame`] to the
+    /// first two channels (or mixes it to mono for single channel streams)
+    /// and silences the rest.
+    fn next_frame_channels(&mut self, sample_rate: u32, out: &mut [f32]) {
+        let frame = self.next_frame(sample_rate);
+        out.fill(0.0);
+        add_to_main(frame, out);
+    }
+
     /// This gets called when an audio buffer is done processing.
     #[cfg(feature = "cpal")]
     fn on_buffer<T>(&mut self, _buffer: &mut [T])
@@ -17,6 +65,350 @@ pub trait Renderer: Clone + Send + 'static {
         T: cpal::SizedSample + cpal::FromSample<f32>,
     {
     }
+
+    /// This gets called when the backend requests a new buffer, before any
+    /// frames of the buffer are rendered.
+    #[cfg(feature = "cpal")]
+    fn on_buffer_timing(&mut self, _timing: BufferTiming) {}
+
+    /// This gets called when the backend starts a stream (`active` is
+    /// `true`) and when the stream stops, for example when the device is
+    /// disconnected (`active` is `false`).
+    #[cfg(feature = "cpal")]
+    fn on_stream_active(&mut self, _active: bool) {}
+}
+
+/// Add a frame to the main outputs (the first two channels). On single
+/// channel streams, both channels are mixed to mono.
+#[inline]
+fn add_to_main(frame: Frame, out: &mut [f32]) {
+    match out {
+        [] => (),
+        [mono] => *mono += (frame.left + frame.right) / 2.0,
+        [left, right, ..] => {
+            *left += frame.left;
+            *right += frame.right;
+        }
+    }
+}
+
+/// How fast sounds play while catching up with [`OnStreamGap::CatchUp`].
+const CATCH_UP_SPEED: f64 = 1.05;
+
+/// How many frames the renderer fast-forwards per output frame while it
+/// skips ahead with [`OnStreamGap::SkipAhead`].
+const SKIP_AHEAD_SPEED: u32 = 16;
+
+/// Specifies what the [`DefaultRenderer`] does with the time that passed
+/// while no stream was active, for example while the stream was being
+/// restarted after a device change.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OnStreamGap {
+    /// Sounds continue from where they were when the stream stopped.
+    #[default]
+    Freeze,
+    /// Sounds play slightly faster (and higher pitched) until they catch up
+    /// with the time that passed.
+    CatchUp,
+    /// Sounds skip ahead by the time that passed when the new stream starts,
+    /// so they stay in sync with the rest of the application.
+    ///
+    /// The sounds are fast-forwarded over the first frames of the new
+    /// stream, a bounded amount per frame, so a long gap doesn't stall the
+    /// audio callback. The output is silent until they caught up.
+    SkipAhead,
+}
+
+/// Lowest output rate scale. See [`DefaultRenderer::set_output_rate_scale`].
+const MIN_RATE_SCALE: f64 = 0.01;
+
+/// A tween of the output rate scale, measured in real time.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct RateScaleTween {
+    target: f64,
+    easing: Easing,
+    /// Seconds since the tween started.
+    elapsed: f64,
+    /// Length of the tween in seconds.
+    duration: f64,
+}
+
+/// Output rate scale of the [`DefaultRenderer`], see
+/// [`DefaultRenderer::set_output_rate_scale`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct RateScale {
+    value: Parameter<f64>,
+    tween: Option<RateScaleTween>,
+}
+
+impl Default for RateScale {
+    fn default() -> Self {
+        Self {
+            value: Parameter::new(1.0),
+            tween: None,
+        }
+    }
+}
+
+impl RateScale {
+    /// Advance the tween by `dt` seconds and return the current scale.
+    #[inline]
+    fn update(&mut self, dt: f64) -> f64 {
+        if let Some(tween) = &mut self.tween {
+            tween.elapsed += dt;
+            let t = if tween.duration > 0.0 {
+                (tween.elapsed / tween.duration).min(1.0)
+            } else {
+                1.0
+            };
+            self.value
+                .update(tween.target, tween.easing.apply(t as f32));
+            if t >= 1.0 {
+                self.value.stop_tween();
+                self.tween = None;
+            }
+        }
+        self.value.value
+    }
+}
+
+/// How long (in seconds) it takes the automatic gain to get most of the way
+/// to a new level.
+const AUTO_GAIN_SMOOTHING_SECS: f32 = 0.1;
+
+/// Automatic mixdown gain of the [`DefaultRenderer`], based on the amount of
+/// audible sounds. Keeps many simultaneous sounds from clipping without
+/// making a few sounds too quiet.
+///
+/// The gain changes smoothly, so it doesn't pump as sounds start and stop.
+#[derive(Debug, Copy, Clone, Default)]
+pub enum AutoGain {
+    /// No automatic gain (default).
+    #[default]
+    Off,
+    /// Scale the output by `1 / sqrt(voices)`.
+    SqrtVoices,
+    /// Scale the output by the value returned by the function for the
+    /// amount of audible sounds.
+    Curve(fn(usize) -> f32),
+}
+
+impl AutoGain {
+    /// Return the gain for the given amount of audible sounds.
+    #[inline]
+    pub fn gain(self, voices: usize) -> f32 {
+        match self {
+            Self::Off => 1.0,
+            Self::SqrtVoices => 1.0 / (voices.max(1) as f32).sqrt(),
+            Self::Curve(curve) => curve(voices),
+        }
+    }
+}
+
+/// How often (in frames) the [`DefaultRenderer`] ranks the sounds when it
+/// limits the amount of voices.
+const VOICE_UPDATE_FRAMES: u32 = 1024;
+
+/// What the [`DefaultRenderer`] does with the quietest sounds when more
+/// sounds play than its voice limit allows. See
+/// [`crate::Mixer::set_max_voices`].
+///
+/// The sounds are ranked by [`crate::Sound::audibility`] every few buffers.
+/// Paused sounds don't use a voice.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum VoicePolicy {
+    /// Stop the quietest sounds (default). They fade out over a few
+    /// milliseconds, so stopping them doesn't click.
+    #[default]
+    Steal,
+    /// Make the quietest sounds virtual: they are not rendered, but their
+    /// position keeps advancing, and they are heard again from the right
+    /// position once they are among the loudest sounds again. See
+    /// [`crate::VoiceState`].
+    Virtualize,
+}
+
+/// What happened in a [`RenderEvent`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderEventKind {
+    /// The sound rendered its first frame.
+    Started,
+    /// The sound started playing the loop again. This is the frame the
+    /// wrap is heard in, which is a few frames after the playhead wrapped.
+    LoopWrapped,
+    /// A command of the sound finished.
+    CommandFinished(Change),
+    /// The sound finished and was removed from the renderer.
+    Finished,
+}
+
+/// An event recorded by a [`DefaultRenderer`] with an event log, for
+/// example to line up visuals with an offline render. See
+/// [`crate::RecordMixer::enable_event_log`].
+#[derive(Debug, Clone)]
+pub struct RenderEvent {
+    /// Index of the output frame the event happened in, counted from the
+    /// first frame the renderer rendered.
+    pub frame: u64,
+    /// The sound the event happened to.
+    pub sound: SoundHandle,
+    /// What happened.
+    pub kind: RenderEventKind,
+}
+
+/// An effect applied to the mixed output of a [`DefaultRenderer`], for
+/// example a limiter. See [`MasterChain`].
+///
+/// Implemented for closures that take a frame and the sample rate.
+pub trait MasterEffect: Send {
+    /// Process the next frame of the mix.
+    fn process(&mut self, frame: Frame, sample_rate: u32) -> Frame;
+}
+
+impl<F: FnMut(Frame, u32) -> Frame + Send> MasterEffect for F {
+    #[inline]
+    fn process(&mut self, frame: Frame, sample_rate: u32) -> Frame {
+        self(frame, sample_rate)
+    }
+}
+
+/// Processing applied to the mix of a [`DefaultRenderer`] after the
+/// automatic gain, before the output is metered. [`crate::Mixer`] and
+/// [`crate::RecordMixer`] apply the same chain, so offline renders match
+/// live playback sample for sample.
+///
+/// Effects are shared between clones of the chain.
+#[derive(Clone)]
+pub struct MasterChain {
+    /// Volume of the whole mix (default 1.0).
+    pub volume: f32,
+    /// Effects applied after the volume, in order.
+    pub effects: Vec<Arc<Mutex<dyn MasterEffect>>>,
+}
+
+impl Default for MasterChain {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            effects: vec![],
+        }
+    }
+}
+
+impl std::fmt::Debug for MasterChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MasterChain")
+            .field("volume", &self.volume)
+            .field("effects", &self.effects.len())
+            .finish()
+    }
+}
+
+impl MasterChain {
+    /// Add an effect to the end of the chain.
+    #[inline]
+    pub fn push(&mut self, effect: impl MasterEffect + 'static) {
+        self.effects.push(Arc::new(Mutex::new(effect)));
+    }
+
+    /// Process the next frame of the mix.
+    #[inline]
+    pub fn process(&mut self, frame: Frame, sample_rate: u32) -> Frame {
+        self.apply_effects(frame * self.volume, sample_rate)
+    }
+
+    /// Run a frame through the effects, without applying the volume.
+    #[inline]
+    fn apply_effects(&mut self, mut frame: Frame, sample_rate: u32) -> Frame {
+        for effect in &self.effects {
+            frame = effect.lock().process(frame, sample_rate);
+        }
+        frame
+    }
+}
+
+/// Sounds waiting to be added to a [`DefaultRenderer`].
+#[derive(Debug, Default)]
+struct StagedSounds {
+    sounds: Mutex<Vec<SoundHandle>>,
+    /// Whether `sounds` is not empty. Checked without locking.
+    pending: AtomicBool,
+}
+
+/// Queue of sounds that will start playing in a [`DefaultRenderer`].
+///
+/// Pushing to the queue never waits for the renderer to finish rendering,
+/// the renderer picks up the queued sounds before rendering its next frame.
+/// This type can be cheaply cloned and sent to other threads.
+#[derive(Debug, Clone, Default)]
+pub struct SoundQueue(Arc<StagedSounds>);
+
+impl SoundQueue {
+    /// Queue a sound to start playing.
+    #[inline]
+    pub fn push(&self, sound: impl Into<SoundHandle>) {
+        let mut sounds = self.0.sounds.lock();
+        sounds.push(sound.into());
+        self.0.pending.store(true, Ordering::Release);
+    }
+
+    /// Return whether no sounds are queued.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        !self.0.pending.load(Ordering::Acquire)
+    }
+
+    /// Return the amount of queued sounds.
+    #[inline]
+    pub fn len(&self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        self.0.sounds.lock().len()
+    }
+
+    /// Append clones of the queued sounds to `sounds`.
+    #[inline]
+    fn clone_into(&self, sounds: &mut Vec<SoundHandle>) {
+        if !self.is_empty() {
+            sounds.extend_from_slice(&self.0.sounds.lock());
+        }
+    }
+
+    /// Remove all queued sounds.
+    #[inline]
+    fn clear(&self) {
+        let mut sounds = self.0.sounds.lock();
+        sounds.clear();
+        self.0.pending.store(false, Ordering::Release);
+    }
+
+    /// Move all queued sounds into `sounds`. Does nothing if the queue is
+    /// being pushed to right now, the sounds are moved on the next call.
+    #[inline]
+    fn take_into(&self, sounds: &mut Vec<SoundHandle>) {
+        if self.is_empty() {
+            return;
+        }
+        if let Some(mut queued) = self.0.sounds.try_lock() {
+            sounds.append(&mut queued);
+            self.0.pending.store(false, Ordering::Release);
+        }
+    }
+}
+
+/// Whether a renderer produces the live output or a recording. Sounds can be
+/// limited to one of the paths, see [`crate::Sound::set_record_only`] and
+/// [`crate::Sound::set_monitor_only`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenderPath {
+    /// The output that is heard, used by [`crate::Mixer`] (default).
+    #[default]
+    Live,
+    /// A recording, used by [`crate::RecordMixer`].
+    Record,
 }
 
 /// Default audio renderer.
@@ -24,8 +416,83 @@ pub trait Renderer: Clone + Send + 'static {
 pub struct DefaultRenderer {
     /// All playing sounds.
     pub sounds: Vec<SoundHandle>,
+    /// Sounds that start playing on the next frame. Shared between clones
+    /// of the renderer. See [`DefaultRenderer::queue`].
+    queue: SoundQueue,
     /// The last buffer size given by the [cpal] backend.
     pub last_buffer_size: usize,
+    /// Timing of the last buffer requested by the [cpal] backend.
+    pub buffer_timing: Option<BufferTiming>,
+    /// Extra latency added to the one reported by the backend, for devices
+    /// that misreport their latency. See [`crate::Mixer::set_latency_offset`].
+    pub latency_offset: Duration,
+    /// Maximum time sounds play for while not paused before they are faded
+    /// out and removed. See [`crate::Mixer::set_max_sound_age`].
+    pub max_sound_age: Option<Duration>,
+    /// Maximum amount of sounds that are rendered at once. See
+    /// [`crate::Mixer::set_max_voices`].
+    pub max_voices: Option<usize>,
+    /// What happens to the sounds over the voice limit.
+    pub voice_policy: VoicePolicy,
+    /// Frames left until the sounds are ranked for the voice limit again.
+    voice_update_countdown: u32,
+    /// Amount of frames rendered so far.
+    frames_rendered: u64,
+    /// Recorded events. If [`None`], events are not recorded. See
+    /// [`crate::RecordMixer::enable_event_log`].
+    pub event_log: Option<Vec<RenderEvent>>,
+    /// Measures the levels of the mixed output.
+    pub meter: Meter,
+    /// Whether the renderer is paused. While paused, the renderer outputs
+    /// silence and does not advance any sounds or command timers.
+    pub paused: bool,
+    /// Whether the renderer should act as if it was paused while no stream
+    /// is active, so no sounds advance while the device is gone.
+    pub pause_without_stream: bool,
+    /// Whether the backend has an active stream.
+    pub stream_active: bool,
+    /// Amount of detected underruns (gaps in the output where the device had
+    /// to play silence because a buffer wasn't ready in time).
+    pub underruns: u64,
+    /// What to do with the time that passed while no stream was active.
+    pub stream_gap_policy: OnStreamGap,
+    /// When the last stream stopped.
+    #[cfg(feature = "cpal")]
+    stream_stopped_at: Option<Instant>,
+    /// Time (in seconds) that sounds still have to catch up with. See
+    /// [`OnStreamGap::CatchUp`].
+    catch_up_left: f64,
+    /// Time (in seconds) that sounds still have to skip ahead by. See
+    /// [`OnStreamGap::SkipAhead`].
+    skip_left: f64,
+    /// Automatic mixdown gain. See [`AutoGain`].
+    pub auto_gain: AutoGain,
+    /// Master volume and effects, applied to the mix after the automatic
+    /// gain.
+    pub master: MasterChain,
+    /// Whether the renderer renders live output or a recording. Sounds that
+    /// don't play on this path advance, but are not mixed.
+    pub path: RenderPath,
+    /// Currently applied automatic gain. [`None`] if no gain was applied
+    /// yet, so the first gain is applied without smoothing.
+    applied_gain: Option<f32>,
+    /// Whether any sound was soloed in the last frame.
+    any_solo: bool,
+    /// Render time measurements.
+    ///
+    /// Required features: `diagnostics`
+    #[cfg(feature = "diagnostics")]
+    pub diagnostics: Diagnostics,
+    /// Music transitions waiting for their sync point.
+    transitions: Vec<PendingTransition>,
+    /// Speed of the mixer time. See [`DefaultRenderer::set_output_rate_scale`].
+    rate_scale: RateScale,
+    /// Whether the output clipped in the current buffer.
+    #[cfg(feature = "log")]
+    clipped: bool,
+    /// Whether the output clipped in the last buffer.
+    #[cfg(feature = "log")]
+    was_clipping: bool,
 }
 
 impl DefaultRenderer {
@@ -36,9 +503,313 @@ impl DefaultRenderer {
         self.sounds.push(sound.into());
     }
 
-    /// Return whether the renderer has any playing sounds.
+    /// Replace all playing sounds with `sounds` in one step, so no frame is
+    /// rendered with only part of the sounds replaced. Queued sounds that
+    /// didn't start playing yet and pending transitions are discarded, so
+    /// they can't bring an old sound back. Returns the sounds that were
+    /// playing before.
+    #[inline]
+    pub fn replace_sounds(&mut self, sounds: Vec<SoundHandle>) -> Vec<SoundHandle> {
+        self.queue.clear();
+        self.transitions.clear();
+        std::mem::replace(&mut self.sounds, sounds)
+    }
+
+    /// Switch from one sound to another at a musically meaningful point, as
+    /// described by the [`TransitionRule`]. The switch is done by the
+    /// renderer on the exact frame where `from` reaches the sync point. If
+    /// `from` loops or finishes before reaching it, the switch happens right
+    /// then.
+    pub fn transition(&mut self, from: SoundHandle, to: SoundHandle, rule: TransitionRule) {
+        self.transitions
+            .push(PendingTransition::new(from, to, rule));
+    }
+
+    /// Set how fast the whole mix plays, like varispeed on a tape machine:
+    /// a scale of 0.5 plays all sounds at half speed and an octave lower.
+    /// Commands, automations and loops slow down along with the sounds.
+    /// Cancels a running tween. Scales are clamped to at least 0.01.
+    #[inline]
+    pub fn set_output_rate_scale(&mut self, scale: f64) {
+        self.rate_scale.tween = None;
+        self.rate_scale.value.start_tween(scale.max(MIN_RATE_SCALE));
+    }
+
+    /// Smoothly change the output rate scale over `duration` (measured in
+    /// real time, not in scaled time). See
+    /// [`DefaultRenderer::set_output_rate_scale`].
+    #[inline]
+    pub fn tween_output_rate_scale(&mut self, scale: f64, duration: Duration, easing: Easing) {
+        // start from the current value of a running tween
+        self.rate_scale.value.stop_tween();
+        self.rate_scale.tween = Some(RateScaleTween {
+            target: scale.max(MIN_RATE_SCALE),
+            easing,
+            elapsed: 0.0,
+            duration: duration.as_secs_f64(),
+        });
+    }
+
+    /// Return the current output rate scale. See
+    /// [`DefaultRenderer::set_output_rate_scale`].
+    #[inline]
+    pub fn output_rate_scale(&self) -> f64 {
+        self.rate_scale.value.value
+    }
+
+    /// Return a queue that starts playing sounds in this renderer without
+    /// locking it. See [`SoundQueue`].
+    #[inline]
+    pub fn queue(&self) -> SoundQueue {
+        self.queue.clone()
+    }
+
+    /// Return whether the renderer has any playing or queued sounds.
     pub fn has_sounds(&self) -> bool {
-        !self.sounds.is_empty()
+        !self.sounds.is_empty() || !self.queue.is_empty()
+    }
+
+    /// Return the amount of playing and queued sounds.
+    #[inline]
+    pub fn sound_count(&self) -> usize {
+        self.sounds.len() + self.queue.len()
+    }
+
+    /// Return handles to all playing and queued sounds.
+    pub fn live_sounds(&self) -> Vec<SoundHandle> {
+        let mut sounds = self.sounds.clone();
+        self.queue.clone_into(&mut sounds);
+        sounds
+    }
+
+    /// Return the time between a frame being rendered and it being heard.
+    ///
+    /// This is the duration of the buffer plus the output latency reported
+    /// by the backend and the [`DefaultRenderer::latency_offset`], minus the
+    /// time that passed since the buffer was requested.
+    pub fn output_delay(&self) -> Duration {
+        let Some(timing) = self.buffer_timing else {
+            return self.latency_offset;
+        };
+        (timing.duration() + timing.output_latency + self.latency_offset)
+            .saturating_sub(timing.callback.elapsed())
+    }
+
+    /// Return the automatic gain that is currently applied to the output.
+    /// See [`AutoGain`].
+    #[inline]
+    pub fn applied_gain(&self) -> f32 {
+        self.applied_gain.unwrap_or(1.0)
+    }
+
+    /// Move the applied automatic gain towards the gain for the given amount
+    /// of audible sounds and return it.
+    fn update_gain(&mut self, voices: usize, sample_rate: u32) -> f32 {
+        if let AutoGain::Off = self.auto_gain {
+            self.applied_gain = None;
+            return 1.0;
+        }
+
+        let target = self.auto_gain.gain(voices);
+        let gain = match self.applied_gain {
+            Some(gain) => {
+                let coeff = 1.0 - (-1.0 / (AUTO_GAIN_SMOOTHING_SECS * sample_rate as f32)).exp();
+                gain + (target - gain) * coeff
+            }
+            None => target,
+        };
+        self.applied_gain = Some(gain);
+        gain
+    }
+
+    /// Advance the renderer by one frame without producing output: sounds
+    /// (including queued ones), commands, loops, transitions and voices
+    /// advance exactly like in [`Renderer::next_frame`], but the sounds
+    /// don't interpolate their output and nothing is mixed or metered. This
+    /// is a lot faster than rendering, and the frames rendered afterwards
+    /// are the same as if all frames were rendered.
+    ///
+    /// If the master chain has effects, the mix is rendered and passed
+    /// through them, as their state depends on it.
+    pub fn fast_forward(&mut self, sample_rate: u32) {
+        if !self.master.effects.is_empty() {
+            self.next_frame(sample_rate);
+            return;
+        }
+        if let Some(voices) = self.mix(sample_rate, false, |_, _| ()) {
+            self.update_gain(voices, sample_rate);
+        }
+    }
+
+    /// Fast-forward the renderer by up to [`SKIP_AHEAD_SPEED`] frames if it
+    /// is skipping ahead after a stream gap. Returns `true` if the frame was
+    /// skipped, in which case the output is silent.
+    fn skip_ahead(&mut self, sample_rate: u32) -> bool {
+        // like the start delay of sounds, the time is rounded to whole frames
+        let frame = 1.0 / sample_rate as f64;
+        if self.skip_left < frame / 2.0 {
+            return false;
+        }
+
+        // the skipped frame passes as well, so it has to be skipped too
+        self.skip_left += frame;
+        for _ in 0..SKIP_AHEAD_SPEED {
+            self.fast_forward(sample_rate);
+            self.skip_left -= frame;
+            if self.skip_left < frame / 2.0 {
+                break;
+            }
+        }
+        true
+    }
+
+    /// Give voices to the loudest sounds, and stop or virtualize the others
+    /// according to the [`VoicePolicy`].
+    fn update_voices(&mut self) {
+        let Some(max_voices) = self.max_voices else {
+            for sound in &self.sounds {
+                sound.guard().set_virtual(false);
+            }
+            return;
+        };
+
+        let mut ranked: Vec<(f32, &SoundHandle)> = self
+            .sounds
+            .iter()
+            .filter_map(|handle| {
+                let sound = handle.guard();
+                // stolen voices are fading out and don't count anymore
+                (!sound.paused && !sound.is_stolen()).then(|| (sound.audibility(), handle))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        for (i, (_, handle)) in ranked.into_iter().enumerate() {
+            let mut sound = handle.guard();
+            if i < max_voices {
+                sound.set_virtual(false);
+            } else {
+                match self.voice_policy {
+                    VoicePolicy::Steal => sound.steal(),
+                    VoicePolicy::Virtualize => sound.set_virtual(true),
+                }
+            }
+        }
+    }
+
+    /// Render the next frame of every playing sound and pass it to `output`
+    /// along with the output route of the sound. Finished sounds are
+    /// removed. Returns the amount of audible sounds, or [`None`] if the
+    /// renderer is paused.
+    ///
+    /// If any sound is soloed, sounds that aren't soloed still advance, but
+    /// are not passed to `output`. The same goes for sounds that don't play
+    /// on the render path of the renderer.
+    ///
+    /// If `render` is false, the sounds are fast-forwarded instead, see
+    /// [`DefaultRenderer::fast_forward`].
+    fn mix(
+        &mut self,
+        sample_rate: u32,
+        render: bool,
+        mut output: impl FnMut(Frame, OutputRoute),
+    ) -> Option<usize> {
+        self.queue.take_into(&mut self.sounds);
+        let frame_index = self.frames_rendered;
+        self.frames_rendered += 1;
+
+        // freeze the mixer time, sounds and their commands don't advance
+        if self.paused || (self.pause_without_stream && !self.stream_active) {
+            return None;
+        }
+
+        // render at a lower sample rate while catching up or with an output
+        // rate scale above 1, so all sounds and commands advance faster (and
+        // the other way around)
+        let mut speed = self.rate_scale.update(1.0 / sample_rate as f64);
+        if self.catch_up_left > 0.0 {
+            self.catch_up_left -= (CATCH_UP_SPEED - 1.0) / sample_rate as f64;
+            speed *= CATCH_UP_SPEED;
+        }
+        let render_rate = if speed == 1.0 {
+            sample_rate
+        } else {
+            ((sample_rate as f64 / speed).round() as u32).max(1)
+        };
+
+        // start the sounds of transitions that reached their sync point
+        let mut i = 0;
+        while i < self.transitions.len() {
+            match self.transitions[i].update() {
+                Some(to) => {
+                    self.transitions.swap_remove(i);
+                    self.sounds.push(to);
+                }
+                None => i += 1,
+            }
+        }
+
+        if self.voice_update_countdown == 0 {
+            self.voice_update_countdown = VOICE_UPDATE_FRAMES;
+            self.update_voices();
+        }
+        self.voice_update_countdown -= 1;
+
+        // solo changes take effect one frame later, so all sounds only have
+        // to be locked once per frame
+        let any_solo = self.any_solo;
+        self.any_solo = false;
+        let mut voices = 0;
+
+        let mut i = 0;
+        while i < self.sounds.len() {
+            #[cfg(feature = "diagnostics")]
+            let start = self.diagnostics.sampling().then(Instant::now);
+
+            let mut sound = self.sounds[i].guard();
+            sound.set_max_age(self.max_sound_age);
+            sound.set_record_events(self.event_log.is_some());
+            let next = sound.advance(render_rate, render);
+            if let Some(log) = &mut self.event_log {
+                let handle = &self.sounds[i];
+                let events = sound.take_events();
+                let finished = next.is_none().then_some(RenderEventKind::Finished);
+                log.extend(events.chain(finished).map(|kind| RenderEvent {
+                    frame: frame_index,
+                    sound: handle.clone(),
+                    kind,
+                }));
+            }
+
+            let Some(frame) = next else {
+                // remove sounds that finished playback. swap with the last
+                // sound, so the other sounds don't have to be moved
+                drop(sound);
+                self.sounds.swap_remove(i);
+                #[cfg(feature = "diagnostics")]
+                self.diagnostics.remove_sound(i);
+                continue;
+            };
+
+            #[cfg(feature = "diagnostics")]
+            if let Some(start) = start {
+                self.diagnostics.add_sound_cost(i, start.elapsed());
+            }
+
+            self.any_solo |= sound.solo();
+            if (!any_solo || sound.solo()) && sound.plays_on(self.path) {
+                output(frame, sound.output_route());
+                if !sound.paused
+                    && !sound.muted()
+                    && !sound.outputting_silence()
+                    && sound.voice_state() == VoiceState::Real
+                {
+                    voices += 1;
+                }
+            }
+            i += 1;
+        }
+        Some(voices)
     }
 }
 
@@ -46,27 +817,145 @@ impl Renderer for DefaultRenderer {
     fn next_frame(&mut self, sample_rate: u32) -> Frame {
         // mix samples from all playing sounds
         let mut out = Frame::ZERO;
+        // skipped frames are silent
+        let voices = if self.skip_ahead(sample_rate) {
+            None
+        } else {
+            self.mix(sample_rate, true, |frame, _| out += frame)
+        };
+        if let Some(voices) = voices {
+            out *= self.update_gain(voices, sample_rate);
+        }
+        let out = self.master.process(out, sample_rate);
+        self.meter.process(out, sample_rate);
 
-        // remove all sounds that finished playback
-        self.sounds.retain_mut(|sound| {
-            let frame = sound.next_frame(sample_rate);
-            if let Some(frame) = frame {
-                out += frame;
-                true
-            } else {
-                false
-            }
-        });
-
+        #[cfg(feature = "log")]
+        {
+            self.clipped |= out.left.abs() > 1.0 || out.right.abs() > 1.0;
+        }
         out
     }
 
+    fn next_frame_channels(&mut self, sample_rate: u32, out: &mut [f32]) {
+        out.fill(0.0);
+        let channels = out.len();
+        let mut main = Frame::ZERO;
+        let mut total = Frame::ZERO;
+
+        // skipped frames are silent
+        let voices = if self.skip_ahead(sample_rate) {
+            None
+        } else {
+            self.mix(sample_rate, true, |frame, route| {
+                total += frame;
+                match route {
+                    // sounds routed to channels the stream doesn't have play on
+                    // the main outputs
+                    OutputRoute::Channels(left, right)
+                        if channels >= 2
+                            && (left as usize) < channels
+                            && (right as usize) < channels =>
+                    {
+                        out[left as usize] += frame.left;
+                        out[right as usize] += frame.right;
+                    }
+                    OutputRoute::All if channels >= 2 => {
+                        for (i, sample) in out.iter_mut().enumerate() {
+                            *sample += if i % 2 == 0 { frame.left } else { frame.right };
+                        }
+                    }
+                    _ => main += frame,
+                }
+            })
+        };
+
+        add_to_main(main, out);
+        if let Some(voices) = voices {
+            let gain = self.update_gain(voices, sample_rate);
+            out.iter_mut().for_each(|sample| *sample *= gain);
+            total *= gain;
+        }
+
+        // the master volume applies to all channels, the effects only to the
+        // main outputs
+        out.iter_mut()
+            .for_each(|sample| *sample *= self.master.volume);
+        total *= self.master.volume;
+        if channels >= 2 && !self.master.effects.is_empty() {
+            let main = Frame::new(out[0], out[1]);
+            let processed = self.master.apply_effects(main, sample_rate);
+            out[0] = processed.left;
+            out[1] = processed.right;
+            total += processed - main;
+        }
+        self.meter.process(total, sample_rate);
+
+        #[cfg(feature = "log")]
+        {
+            self.clipped |= out.iter().any(|sample| sample.abs() > 1.0);
+        }
+    }
+
     #[cfg(feature = "cpal")]
     fn on_buffer<T>(&mut self, buffer: &mut [T])
     where
         T: cpal::SizedSample + cpal::FromSample<f32>,
     {
         self.last_buffer_size = buffer.len();
+
+        // only log when the output starts clipping, not for every buffer
+        #[cfg(feature = "log")]
+        {
+            if self.clipped && !self.was_clipping {
+                log_warn!("output is clipping");
+            }
+            self.was_clipping = std::mem::take(&mut self.clipped);
+        }
+
+        #[cfg(feature = "diagnostics")]
+        if let Some(timing) = &self.buffer_timing {
+            self.diagnostics.end_buffer(timing, &self.sounds);
+        }
+    }
+
+    #[cfg(feature = "cpal")]
+    fn on_buffer_timing(&mut self, timing: BufferTiming) {
+        // the new buffer should start playing right after the previous one
+        // ends, if it starts later, the device played silence in between
+        if let Some(prev) = self.buffer_timing {
+            let prev_end = prev.callback + prev.output_latency + prev.duration();
+            let start = timing.callback + timing.output_latency;
+            let gap = start.saturating_duration_since(prev_end);
+            if gap > prev.duration() / 2 {
+                log_warn!("underrun: the device played {gap:?} of silence");
+                self.underruns += 1;
+            }
+        }
+        self.buffer_timing = Some(timing);
+
+        #[cfg(feature = "diagnostics")]
+        self.diagnostics.begin_buffer();
+    }
+
+    #[cfg(feature = "cpal")]
+    fn on_stream_active(&mut self, active: bool) {
+        self.stream_active = active;
+        if !active {
+            // the next stream doesn't continue the timing of this one
+            self.buffer_timing = None;
+            self.stream_stopped_at = Some(Instant::now());
+            return;
+        }
+
+        // handle the time that passed since the last stream stopped
+        let Some(gap) = self.stream_stopped_at.take().map(|t| t.elapsed()) else {
+            return;
+        };
+        match self.stream_gap_policy {
+            OnStreamGap::Freeze => (),
+            OnStreamGap::CatchUp => self.catch_up_left += gap.as_secs_f64(),
+            OnStreamGap::SkipAhead => self.skip_left += gap.as_secs_f64(),
+        }
     }
 }
 
@@ -80,6 +969,24 @@ impl From<DefaultRenderer> for RendererHandle<DefaultRenderer> {
     }
 }
 
+impl From<StreamingSource> for RendererHandle<StreamingSource> {
+    fn from(val: StreamingSource) -> Self {
+        RendererHandle::new(val)
+    }
+}
+
+impl From<Metronome> for RendererHandle<Metronome> {
+    fn from(val: Metronome) -> Self {
+        RendererHandle::new(val)
+    }
+}
+
+impl<R: Renderer> From<BufferedRenderer<R>> for RendererHandle<BufferedRenderer<R>> {
+    fn from(val: BufferedRenderer<R>) -> Self {
+        RendererHandle::new(val)
+    }
+}
+
 impl<R: Renderer> RendererHandle<R> {
     /// Create a new renderer handle.
     pub fn new(renderer: R) -> Self {
@@ -92,3 +999,211 @@ impl<R: Renderer> RendererHandle<R> {
         self.0.lock()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Command, Sound, TransitionRule, TransitionSync};
+
+    const SAMPLE_RATE: u32 = 1000;
+
+    /// Return a sound that plays `value` on both channels for `len` frames.
+    fn constant(value: f32, len: usize) -> Sound {
+        Sound::from_mono_samples(SAMPLE_RATE, &vec![value; len])
+    }
+
+    /// Render 100 sounds for 10 seconds of 48 kHz output and return how long
+    /// it took.
+    fn time_100_sounds(volume: f32) -> std::time::Duration {
+        const RATE: u32 = 48000;
+        let mut renderer = DefaultRenderer::default();
+        let frames: Vec<Frame> = (0..44100)
+            .map(|i| Frame::new((i as f32 * 0.06).sin(), (i as f32 * 0.05).cos()) * 0.5)
+            .collect();
+        let source = Sound::from_frames(44100, &frames);
+        for i in 0..100 {
+            // distant sounds at different pitches
+            let mut sound = source.clone();
+            sound.set_loop_enabled(true);
+            sound.set_playback_rate(crate::PlaybackRate::Factor(0.8 + i as f64 * 0.005));
+            sound.set_occlusion(0.5);
+            sound.set_volume(volume);
+            renderer.add_sound(sound);
+        }
+        let start = std::time::Instant::now();
+        for _ in 0..RATE * 10 {
+            std::hint::black_box(renderer.next_frame(RATE));
+        }
+        start.elapsed()
+    }
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored`"]
+    fn silent_sounds_are_cheap_to_render() {
+        let audible = time_100_sounds(0.5);
+        let silent = time_100_sounds(0.0);
+        println!("100 sounds, 10 s: {audible:?} audible, {silent:?} silent");
+        assert!(silent * 2 < audible, "{silent:?} >= {audible:?} / 2");
+    }
+
+    #[test]
+    fn stolen_voices_fade_out() {
+        const RATE: u32 = 48000;
+        let mut renderer = DefaultRenderer {
+            max_voices: Some(1),
+            ..Default::default()
+        };
+        let quiet = SoundHandle::new(Sound::from_mono_samples(RATE, &vec![0.25; 48000]));
+        renderer.add_sound(Sound::from_mono_samples(RATE, &vec![1.0; 48000]));
+        renderer.add_sound(quiet.clone());
+
+        let mut last = 1.25;
+        for _ in 0..1000 {
+            let level = renderer.next_frame(RATE).left;
+            assert!(level <= last && last - level < 0.01, "{last} -> {level}");
+            last = level;
+        }
+        assert_eq!(last, 1.0);
+        assert!(quiet.finished());
+        assert_eq!(renderer.sound_count(), 1);
+    }
+
+    #[test]
+    fn replace_sounds_cancels_pending_transitions() {
+        let mut renderer = DefaultRenderer::default();
+        let old = SoundHandle::new(constant(1.0, 4000));
+        renderer.add_sound(old.clone());
+        for _ in 0..100 {
+            assert_eq!(renderer.next_frame(SAMPLE_RATE), Frame::from_mono(1.0));
+        }
+        // switches on the beat at 1 second
+        let rule = TransitionRule {
+            sync: TransitionSync::NextBeat,
+            bpm: 60.0,
+            ..Default::default()
+        };
+        renderer.transition(old.clone(), SoundHandle::new(constant(0.5, 4000)), rule);
+        renderer.next_frame(SAMPLE_RATE);
+
+        // stopping the replaced sounds would fire the transition
+        for sound in renderer.replace_sounds(vec![SoundHandle::new(constant(0.25, 4000))]) {
+            sound.stop();
+        }
+        for _ in 0..2000 {
+            assert_eq!(renderer.next_frame(SAMPLE_RATE), Frame::from_mono(0.25));
+        }
+    }
+
+    #[test]
+    fn solo_and_mute() {
+        let mut renderer = DefaultRenderer::default();
+        let first = SoundHandle::new(constant(0.25, 1000));
+        let second = SoundHandle::new(constant(0.5, 1000));
+        renderer.add_sound(first.clone());
+        renderer.add_sound(second.clone());
+        // solo changes take effect one frame later, and mute changes once
+        // the frames in the resampler were played, like volume changes
+        let mut level = || {
+            for _ in 0..4 {
+                renderer.next_frame(SAMPLE_RATE);
+            }
+            renderer.next_frame(SAMPLE_RATE).left
+        };
+        assert_eq!(level(), 0.75);
+
+        // soloing one sound silences the other
+        first.set_solo(true);
+        assert_eq!(level(), 0.25);
+        first.set_solo(false);
+        assert_eq!(level(), 0.75);
+
+        // mute wins over solo, and unmuting restores the level
+        second.set_muted(true);
+        assert_eq!(level(), 0.25);
+        second.set_solo(true);
+        assert_eq!(level(), 0.0);
+        second.set_solo(false);
+        second.set_muted(false);
+        assert_eq!(level(), 0.75);
+    }
+
+    #[cfg(feature = "cpal")]
+    #[test]
+    fn skip_ahead_advances_playing_and_queued_sounds() {
+        let mut renderer = DefaultRenderer {
+            stream_gap_policy: OnStreamGap::SkipAhead,
+            ..Default::default()
+        };
+        let mut delayed = constant(0.5, 1000);
+        delayed.set_latency_offset(50);
+        let playing = SoundHandle::new(constant(0.25, 1000));
+        renderer.add_sound(playing.clone());
+        for _ in 0..100 {
+            renderer.next_frame(SAMPLE_RATE);
+        }
+
+        let queued = SoundHandle::new(delayed.clone());
+        renderer.queue().push(queued.clone());
+        renderer.on_stream_active(false);
+        renderer.stream_stopped_at = Some(Instant::now() - Duration::from_millis(200));
+        renderer.on_stream_active(true);
+
+        // the first frames of the new stream are silent while the sounds
+        // skip ahead, then they continue like they were rendered for the
+        // length of the gap
+        let levels: Vec<f32> = (0..30)
+            .map(|_| renderer.next_frame(SAMPLE_RATE).left)
+            .collect();
+        assert!(levels[..14].iter().all(|&level| level == 0.0));
+        assert!(levels[14..].iter().all(|&level| level == 0.75));
+
+        let expected = |mut sound: Sound, frames: usize| {
+            for _ in 0..frames {
+                sound.next_frame(SAMPLE_RATE);
+            }
+            sound.index()
+        };
+        let near = |a: usize, b: usize| a.abs_diff(b) <= 1;
+        assert!(near(playing.index(), expected(constant(0.25, 1000), 330)));
+        assert!(near(queued.index(), expected(delayed, 230)));
+    }
+
+    #[cfg(feature = "cpal")]
+    #[test]
+    fn long_stream_gap_skips_ahead_in_bounded_time() {
+        const SAMPLE_RATE: u32 = 48000;
+        let mut renderer = DefaultRenderer {
+            stream_gap_policy: OnStreamGap::SkipAhead,
+            ..Default::default()
+        };
+        let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &vec![0.5; 48000 * 20]);
+        sound.add_command(Command::new(Change::Volume(0.5), Easing::Linear, 0.0, 30.0));
+        let handle = SoundHandle::new(sound);
+        renderer.add_sound(handle.clone());
+        renderer.next_frame(SAMPLE_RATE);
+
+        renderer.on_stream_active(false);
+        renderer.stream_stopped_at = Some(Instant::now() - Duration::from_secs(10));
+        let start = Instant::now();
+        renderer.on_stream_active(true);
+        assert!(start.elapsed() < Duration::from_millis(10));
+
+        // every buffer fast-forwards a bounded amount of frames, and is
+        // silent until the sound caught up
+        let mut buffer = vec![Frame::ZERO; 512];
+        let mut buffers = 0;
+        loop {
+            let index = handle.index();
+            buffer.fill_with(|| renderer.next_frame(SAMPLE_RATE));
+            assert!(handle.index() - index <= 512 * SKIP_AHEAD_SPEED as usize);
+            buffers += 1;
+            if buffer.iter().any(|&frame| frame != Frame::ZERO) {
+                break;
+            }
+        }
+        // 10 seconds at 15 extra frames per output frame
+        assert_eq!(buffers, 63);
+        let frames = 1 + 48000 * 10 + 512 * buffers;
+        assert!(handle.index().abs_diff(frames) <= 4, "{}", handle.index());
+    }
+}
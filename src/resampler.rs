@@ -1,4 +1,5 @@
 use crate::Frame;
+use std::collections::VecDeque;
 
 /// Stores an audio frame and the frame index of that frame.
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
@@ -88,11 +89,272 @@ impl Resampler {
         self.frames[1].index
     }
 
+    /// Return the current frame without interpolating it. This is the same
+    /// as [`Resampler::get`] with a fraction of 0.
+    #[inline]
+    pub const fn current_frame(&self) -> Frame {
+        self.frames[1].frame
+    }
+
     /// Return whether the resampler is outputting silence.
     #[inline]
     pub fn outputting_silence(&self) -> bool {
-        self.frames
-            .iter()
-            .all(|ResamplerFrame { frame, .. }| *frame == Frame::ZERO)
+        self.outputting_silence_below(0.0)
+    }
+
+    /// Return whether the resampler is outputting audio no louder than
+    /// `threshold` (an amplitude) on both channels.
+    #[inline]
+    pub fn outputting_silence_below(&self, threshold: f32) -> bool {
+        self.frames.iter().all(|ResamplerFrame { frame, .. }| {
+            frame.left.abs() <= threshold && frame.right.abs() <= threshold
+        })
+    }
+}
+
+/// Interpolation used by [`resample_frames`] and [`StreamResampler`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResampleQuality {
+    /// Linear interpolation between the two nearest frames. Cheap, but
+    /// adds aliasing and dulls high frequencies.
+    Linear,
+    /// 4-point Hermite interpolation, the same as sound playback (default).
+    /// See [`interpolate_frame`].
+    #[default]
+    Hermite,
+}
+
+impl ResampleQuality {
+    /// Interpolate between `current` and `next` at a fractional value.
+    #[inline]
+    pub fn interpolate(
+        self,
+        previous: Frame,
+        current: Frame,
+        next: Frame,
+        next_next: Frame,
+        fraction: f32,
+    ) -> Frame {
+        match self {
+            Self::Linear => current + (next - current) * fraction,
+            Self::Hermite => interpolate_frame(previous, current, next, next_next, fraction),
+        }
+    }
+}
+
+/// Resample frames from one sample rate to another. The output has
+/// `ceil(input.len() * to_rate / from_rate)` frames, the first output frame
+/// is the first input frame.
+///
+/// For audio that arrives in chunks, use [`StreamResampler`].
+pub fn resample_frames(
+    input: &[Frame],
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Vec<Frame> {
+    if from_rate == to_rate {
+        return input.to_vec();
+    }
+    let mut resampler = StreamResampler::new(from_rate, to_rate, quality);
+    let mut output = Vec::with_capacity((input.len() as f64 / resampler.ratio()).ceil() as usize);
+    resampler.push(input);
+    resampler.flush(&mut output);
+    output
+}
+
+/// Resamples a stream of frames that arrives in chunks, keeping the
+/// fractional position between chunks, so there are no seams.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut resampler = StreamResampler::new(44100, 48000, ResampleQuality::Hermite);
+/// let mut output = vec![];
+/// while let Some(chunk) = decoder.next_chunk() {
+///     resampler.push(&chunk);
+///     resampler.pull(&mut output);
+/// }
+/// resampler.flush(&mut output);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamResampler {
+    /// Source sample rate.
+    from_rate: u64,
+    /// Output sample rate.
+    to_rate: u64,
+    quality: ResampleQuality,
+    /// Frames that were pushed, but not moved into the window yet.
+    input: VecDeque<Frame>,
+    /// Frame order: previous, current, next, next next. Output frames are
+    /// interpolated between current and next.
+    window: [Frame; 4],
+    /// Position after the current frame, in units of `1 / to_rate` frames,
+    /// so it doesn't drift on long streams. Values of `to_rate` and above
+    /// mean the window has to move forward before the next output frame.
+    phase: u64,
+}
+
+impl StreamResampler {
+    /// Create a new [`StreamResampler`] that converts from `from_rate` to
+    /// `to_rate`.
+    pub fn new(from_rate: u32, to_rate: u32, quality: ResampleQuality) -> Self {
+        let to_rate = to_rate.max(1) as u64;
+        Self {
+            from_rate: from_rate.max(1) as u64,
+            to_rate,
+            quality,
+            input: VecDeque::new(),
+            window: [Frame::ZERO; 4],
+            // the window has to move forward 3 frames before the first
+            // pushed frame is the current frame
+            phase: 3 * to_rate,
+        }
+    }
+
+    /// Change the sample rates. The fractional position is kept, so this
+    /// can be called while streaming.
+    pub fn set_rates(&mut self, from_rate: u32, to_rate: u32) {
+        let to_rate = to_rate.max(1) as u64;
+        self.phase = self.phase * to_rate / self.to_rate;
+        self.from_rate = from_rate.max(1) as u64;
+        self.to_rate = to_rate;
+    }
+
+    /// Return the amount of source frames per output frame.
+    #[inline]
+    pub fn ratio(&self) -> f64 {
+        self.from_rate as f64 / self.to_rate as f64
+    }
+
+    /// Return the interpolation quality.
+    #[inline]
+    pub fn quality(&self) -> ResampleQuality {
+        self.quality
+    }
+
+    /// Add source frames to the resampler.
+    #[inline]
+    pub fn push(&mut self, frames: &[Frame]) {
+        self.input.extend(frames);
+    }
+
+    /// Resample as many of the pushed frames as possible and append them to
+    /// `output`. The last 2 pushed frames are kept until more frames are
+    /// pushed, as they are needed for interpolation. Returns the amount of
+    /// frames that were appended.
+    #[inline]
+    pub fn pull(&mut self, output: &mut Vec<Frame>) -> usize {
+        self.resample(output, u64::MAX)
+    }
+
+    /// Resample the pushed frames until the phase reaches `end`, which is
+    /// relative to the current frame, like the phase.
+    fn resample(&mut self, output: &mut Vec<Frame>, mut end: u64) -> usize {
+        let len = output.len();
+        loop {
+            while self.phase >= self.to_rate {
+                let Some(frame) = self.input.pop_front() else {
+                    return output.len() - len;
+                };
+                self.window.copy_within(1.., 0);
+                self.window[3] = frame;
+                self.phase -= self.to_rate;
+                end = end.saturating_sub(self.to_rate);
+            }
+            if self.phase >= end {
+                return output.len() - len;
+            }
+            let [previous, current, next, next_next] = self.window;
+            let fraction = self.phase as f64 / self.to_rate as f64;
+            output.push(self.quality.interpolate(
+                previous,
+                current,
+                next,
+                next_next,
+                fraction as f32,
+            ));
+            self.phase += self.from_rate;
+        }
+    }
+
+    /// Resample the remaining frames, as if the stream was followed by
+    /// silence, append them to `output` and reset the resampler. Call this
+    /// at the end of the stream. Returns the amount of frames that were
+    /// appended.
+    pub fn flush(&mut self, output: &mut Vec<Frame>) -> usize {
+        // stop after the last pushed frame, which is 2 frames after the
+        // current frame once all frames are in the window
+        let end = (self.input.len() as u64 + 3) * self.to_rate;
+        self.push(&[Frame::ZERO; 2]);
+        let count = self.resample(output, end);
+        self.reset();
+        count
+    }
+
+    /// Drop all pushed frames and start a new stream.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.window = [Frame::ZERO; 4];
+        self.phase = 3 * self.to_rate;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    /// Return 1 second of a sine at `hz`.
+    fn sine(sample_rate: u32, hz: f32) -> Vec<Frame> {
+        (0..sample_rate)
+            .map(|i| Frame::from_mono(0.5 * (i as f32 / sample_rate as f32 * hz * TAU).sin()))
+            .collect()
+    }
+
+    /// Return the frequency of `frames` from its rising zero crossings,
+    /// skipping the first and last 100 ms.
+    fn frequency(frames: &[Frame], sample_rate: u32) -> f32 {
+        let margin = sample_rate as usize / 10;
+        let window = &frames[margin..frames.len() - margin];
+        let crossings = window
+            .windows(2)
+            .filter(|pair| pair[0].left < 0.0 && pair[1].left >= 0.0)
+            .count();
+        crossings as f32 / (window.len() as f32 / sample_rate as f32)
+    }
+
+    #[test]
+    fn resampling_keeps_the_frequency() {
+        let input = sine(44100, 440.0);
+        for quality in [ResampleQuality::Linear, ResampleQuality::Hermite] {
+            for to_rate in [48000, 22050, 96000, 8000] {
+                let output = resample_frames(&input, 44100, to_rate, quality);
+                assert_eq!(output.len(), to_rate as usize, "{quality:?} {to_rate}");
+                let frequency = frequency(&output, to_rate);
+                assert!(
+                    (frequency / 440.0 - 1.0).abs() < 0.01,
+                    "{quality:?} {to_rate}: {frequency}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn streaming_matches_resampling_at_once() {
+        let input = sine(44100, 440.0);
+        let expected = resample_frames(&input, 44100, 48000, ResampleQuality::Hermite);
+
+        // chunks of uneven sizes, like packets from a decoder
+        let mut resampler = StreamResampler::new(44100, 48000, ResampleQuality::Hermite);
+        let mut output = Vec::new();
+        for chunk in input.chunks(1000).flat_map(|chunk| chunk.chunks(333)) {
+            resampler.push(chunk);
+            resampler.pull(&mut output);
+        }
+        resampler.flush(&mut output);
+        assert_eq!(output, expected);
     }
 }
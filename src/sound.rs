@@ -1,25 +1,191 @@
-use crate::{lerp_f64, Change, Command, Parameter, Resampler, Tweenable};
+use crate::{
+    lerp_f64, resample_frames, AutomationCurve, AutomationTarget, AutomationTime, Change, Command,
+    Easing, Lfo, LfoTarget, MonoReport, Parameter, RenderEventKind, RenderPath, ResampleQuality,
+    Resampler, Tweenable,
+};
 use parking_lot::{Mutex, MutexGuard};
+use std::any::Any;
 use std::ops::{Add, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 use std::ops::{AddAssign, RangeInclusive};
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use crate::KaError;
+
 #[cfg(feature = "symphonia")]
-use {crate::KaError, std::io::Cursor};
+use std::io::Cursor;
 
 /// Includes a sound in the executable. The `symphonia` feature must be
 /// enabled for this macro to exist.
 ///
 /// This is a shorthand for `Sound::from_cursor(Cursor::new(include_bytes!(path)))`.
+/// The extension of the path is passed to [`symphonia`] as a format hint.
 #[macro_export]
 #[cfg(feature = "symphonia")]
 macro_rules! include_sound {
     ($path:expr) => {
-        $crate::Sound::from_cursor(::std::io::Cursor::new(include_bytes!($path)))
+        $crate::Sound::from_cursor_with_hint(::std::io::Cursor::new(include_bytes!($path)), $path)
+    };
+}
+
+/// Includes a sound in the executable without decoding it, see
+/// [`LazySound`]. Can be used to initialize a `static`. The `symphonia`
+/// feature must be enabled for this macro to exist.
+///
+/// This is a shorthand for `LazySound::new(include_bytes!(path), path)`.
+#[macro_export]
+#[cfg(feature = "symphonia")]
+macro_rules! include_sound_lazy {
+    ($path:expr) => {
+        $crate::LazySound::new(include_bytes!($path), $path)
     };
 }
 
+/// Settings for decoding a [`Sound`] with [`symphonia`], see
+/// [`Sound::from_media_source_ex`].
+///
+/// Required features: `symphonia`
+#[cfg(feature = "symphonia")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DecodeSettings {
+    /// File extension of the source (without the dot), used as a hint to
+    /// speed up detecting the format.
+    pub extension: Option<String>,
+    /// MIME type of the source, used as a hint like the extension.
+    pub mime_type: Option<String>,
+    /// Trim the encoder delay and padding, so MP3 and Vorbis files loop
+    /// without a gap. The formats that don't store the trim amounts are
+    /// decoded as usual.
+    pub gapless: bool,
+    /// Verify the decoded audio against the checksum of the source if the
+    /// format has one. Decoding fails with [`KaError::VerificationFailed`]
+    /// if the audio doesn't match.
+    pub verify: bool,
+    /// Decode the track with this id instead of the default track.
+    pub track_id: Option<u32>,
+    /// What to do when a packet in the middle of the source fails to decode.
+    pub on_error: OnDecodeError,
+}
+
+/// What to do when a corrupt packet is found while decoding a [`Sound`], see
+/// [`DecodeSettings::on_error`].
+///
+/// Required features: `symphonia`
+#[cfg(feature = "symphonia")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OnDecodeError {
+    /// Fail with the decoding error. The frames decoded so far are lost.
+    #[default]
+    Fail,
+    /// Stop decoding and return the frames decoded so far.
+    /// [`DecodeReport::truncated`] is set and the error is stored in
+    /// [`DecodeReport::warning`].
+    TruncateAndWarn,
+    /// Skip the corrupt packet and continue decoding. The skipped packets are
+    /// counted in [`DecodeReport::skipped_packets`]. Errors that can't be
+    /// recovered from by skipping a packet truncate the sound instead.
+    SkipPacket,
+}
+
+/// What happened while decoding a [`Sound`], returned by
+/// [`Sound::from_media_source_with_report`].
+///
+/// Required features: `symphonia`
+#[cfg(feature = "symphonia")]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DecodeReport {
+    /// Number of corrupt packets that were skipped.
+    pub skipped_packets: usize,
+    /// Whether decoding stopped early because of an error.
+    pub truncated: bool,
+    /// Message of the last error that was recovered from.
+    pub warning: Option<String>,
+}
+
+#[cfg(feature = "symphonia")]
+impl DecodeReport {
+    /// Return `true` if the source decoded without any errors.
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.skipped_packets == 0 && !self.truncated
+    }
+}
+
+#[cfg(feature = "symphonia")]
+impl DecodeSettings {
+    /// Return the default settings with the extension of `path` as a format
+    /// hint.
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Self {
+        Self {
+            extension: path
+                .as_ref()
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .map(String::from),
+            ..Default::default()
+        }
+    }
+}
+
+/// Encoded audio that is decoded into a [`Sound`] the first time it is
+/// used, for example a sound included with [`include_sound_lazy!`]. This
+/// avoids decoding sounds that are never played at startup.
+///
+/// The decoded sound is cached, so later uses only clone it. Decoding
+/// happens on the thread that first uses the sound, never on the audio
+/// thread.
+///
+/// Required features: `symphonia`
+#[cfg(feature = "symphonia")]
+#[derive(Debug)]
+pub struct LazySound {
+    bytes: &'static [u8],
+    /// File name or path of the sound, used as a format hint.
+    path: &'static str,
+    sound: std::sync::OnceLock<Sound>,
+}
+
+#[cfg(feature = "symphonia")]
+impl LazySound {
+    /// Create a new [`LazySound`] from encoded audio. The extension of
+    /// `path` is used as a format hint.
+    #[inline]
+    pub const fn new(bytes: &'static [u8], path: &'static str) -> Self {
+        Self {
+            bytes,
+            path,
+            sound: std::sync::OnceLock::new(),
+        }
+    }
+
+    /// Return the decoded sound, decoding it if this is the first call. If
+    /// decoding fails, the error is returned and the next call tries again.
+    pub fn get(&self) -> Result<Sound, KaError> {
+        if let Some(sound) = self.sound.get() {
+            return Ok(sound.clone());
+        }
+        let sound = Sound::from_cursor_with_hint(Cursor::new(self.bytes), self.path)?;
+        Ok(self.sound.get_or_init(|| sound).clone())
+    }
+
+    /// Return whether the sound was decoded already.
+    #[inline]
+    pub fn is_decoded(&self) -> bool {
+        self.sound.get().is_some()
+    }
+}
+
+/// Decodes the sound if needed. If decoding fails, the handle plays an
+/// empty sound, which finishes immediately. Use [`LazySound::get`] to
+/// handle decoding errors.
+#[cfg(feature = "symphonia")]
+impl From<&LazySound> for SoundHandle {
+    fn from(sound: &LazySound) -> Self {
+        Self::new(sound.get().unwrap_or_default())
+    }
+}
+
 #[cfg(feature = "symphonia")]
 use symphonia::core::{
     audio::Signal,
@@ -64,10 +230,18 @@ impl Frame {
     /// * `x` of 0.5 means center panning (default)
     /// * `x` of 1.0 means hard right panning
     pub fn panned(self, x: f32) -> Self {
-        if x == 0.5 {
-            return self;
+        self.panned_with(x, PanLaw::CenterUnity)
+    }
+
+    /// Pan a frame left or right with the given [`PanLaw`]. See
+    /// [`Frame::panned`].
+    pub fn panned_with(self, x: f32, law: PanLaw) -> Self {
+        let panned = Self::new(self.left * (1.0 - x).sqrt(), self.right * x.sqrt());
+        match law {
+            PanLaw::CenterUnity if x == 0.5 => self,
+            PanLaw::CenterUnity => panned * std::f32::consts::SQRT_2,
+            PanLaw::EqualPower => panned,
         }
-        Self::new(self.left * (1.0 - x).sqrt(), self.right * x.sqrt()) * std::f32::consts::SQRT_2
     }
 }
 
@@ -157,6 +331,128 @@ impl Neg for Frame {
     }
 }
 
+/// Audio data of a [`Sound`], shared between all clones of the sound. Read
+/// it with [`Sound::frames`].
+///
+/// Sounds with identical channels can be stored as mono, which takes half
+/// the memory. Both variants read back as [`Frame`]s, and compare equal if
+/// they hold the same frames. See [`Sound::compacted`].
+#[derive(Debug, Clone)]
+pub(crate) enum FrameData {
+    /// A left and right sample per frame.
+    Stereo(Arc<[Frame]>),
+    /// A single sample per frame, played on both channels.
+    Mono(Arc<[f32]>),
+}
+
+impl FrameData {
+    /// Return the number of frames.
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Stereo(frames) => frames.len(),
+            Self::Mono(samples) => samples.len(),
+        }
+    }
+
+    /// Return `true` if there are no frames.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the frame at `index`, or [`None`] if it is out of range.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<Frame> {
+        match self {
+            Self::Stereo(frames) => frames.get(index).copied(),
+            Self::Mono(samples) => samples.get(index).copied().map(Frame::from_mono),
+        }
+    }
+
+    /// Return an iterator over the frames.
+    pub fn iter(&self) -> impl Iterator<Item = Frame> + '_ {
+        (0..self.len()).map(|index| self.get(index).unwrap_or(Frame::ZERO))
+    }
+
+    /// Copy the frames into a [`Vec`].
+    pub fn to_vec(&self) -> Vec<Frame> {
+        match self {
+            Self::Stereo(frames) => frames.to_vec(),
+            Self::Mono(samples) => samples.iter().copied().map(Frame::from_mono).collect(),
+        }
+    }
+
+    /// Return a hash of the frames, used to tell if a [`PlaybackState`]
+    /// belongs to this audio data. Mono and stereo data with the same frames
+    /// have the same hash.
+    fn fingerprint(&self) -> u64 {
+        // FNV-1a
+        const PRIME: u64 = 0x100000001b3;
+        let mut hash = 0xcbf29ce484222325u64;
+        let mut write = |bits: u64| {
+            hash ^= bits;
+            hash = hash.wrapping_mul(PRIME);
+        };
+        write(self.len() as u64);
+        for frame in self.iter() {
+            write(frame.left.to_bits() as u64);
+            write(frame.right.to_bits() as u64);
+        }
+        hash
+    }
+
+    /// Return `true` if the data is stored as mono.
+    #[inline]
+    pub fn is_mono(&self) -> bool {
+        matches!(self, Self::Mono(_))
+    }
+
+    /// Return `true` if both point to the same shared audio data. Used to
+    /// tell if two sounds are clones of each other.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Stereo(a), Self::Stereo(b)) => Arc::ptr_eq(a, b),
+            (Self::Mono(a), Self::Mono(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Default for FrameData {
+    fn default() -> Self {
+        Self::Stereo(Arc::new([]))
+    }
+}
+
+impl PartialEq for FrameData {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl From<Arc<[Frame]>> for FrameData {
+    #[inline]
+    fn from(frames: Arc<[Frame]>) -> Self {
+        Self::Stereo(frames)
+    }
+}
+
+impl From<Vec<Frame>> for FrameData {
+    #[inline]
+    fn from(frames: Vec<Frame>) -> Self {
+        Self::Stereo(frames.into())
+    }
+}
+
+impl From<&[Frame]> for FrameData {
+    #[inline]
+    fn from(frames: &[Frame]) -> Self {
+        Self::Stereo(frames.into())
+    }
+}
+
 /// Specifies how quickly the sound is played.
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -223,6 +519,428 @@ impl Tweenable for PlaybackRate {
     }
 }
 
+/// What a sound does when its playback rate is exactly zero and no command
+/// is changing it. See [`Sound::set_zero_rate_policy`] and
+/// [`Sound::tape_stop`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ZeroRatePolicy {
+    /// Keep the sound at a standstill, it stays in the mixer (default).
+    #[default]
+    Hold,
+    /// Pause the sound. The sound is resumed by [`Sound::tape_start`] or
+    /// [`Sound::resume`].
+    Pause,
+    /// Stop the sound, so it finishes and is removed from the mixer.
+    Stop,
+}
+
+/// Whether a sound is rendered, see [`Sound::voice_state`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VoiceState {
+    /// The sound is rendered and heard (default).
+    #[default]
+    Real,
+    /// The sound lost its voice to louder sounds. It is not rendered, but
+    /// its position keeps advancing, so it continues from the right place
+    /// once it gets a voice again. See [`crate::VoicePolicy::Virtualize`].
+    Virtual,
+}
+
+/// What [`Sound::restart`] does with the commands of the sound.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RestartCommands {
+    /// Rewind the timers of pending and running commands, so they replay
+    /// exactly like they did since the last restart (default). Commands
+    /// that already finished are not replayed.
+    #[default]
+    Rearm,
+    /// Remove all commands. Parameters that were being tweened keep their
+    /// current value.
+    Clear,
+}
+
+/// A parameter that can be shared between sounds with
+/// [`SoundHandle::link`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkedParameter {
+    /// The volume of the sound.
+    Volume,
+    /// The panning of the sound.
+    Panning,
+    /// The playback rate of the sound.
+    PlaybackRate,
+}
+
+impl LinkedParameter {
+    /// Encode the parameter of a sound.
+    #[inline]
+    fn encode(self, sound: &Sound) -> LinkWords {
+        match self {
+            Self::Volume => f32::encode(sound.volume),
+            Self::Panning => f32::encode(sound.panning),
+            Self::PlaybackRate => PlaybackRate::encode(sound.playback_rate),
+        }
+    }
+
+    /// Set the parameter of a sound to an encoded value.
+    #[inline]
+    fn decode(self, words: LinkWords, sound: &mut Sound) {
+        match self {
+            Self::Volume => sound.volume = f32::decode(words),
+            Self::Panning => sound.panning = f32::decode(words),
+            Self::PlaybackRate => sound.playback_rate = PlaybackRate::decode(words),
+        }
+    }
+}
+
+/// A [`Parameter`] encoded as the bits of its value and base value, and the
+/// variants of playback rates.
+type LinkWords = [u64; 3];
+
+/// A value that can be shared between sounds with [`SoundHandle::link`].
+trait LinkValue: Tweenable {
+    fn encode(parameter: Parameter<Self>) -> LinkWords;
+    fn decode(words: LinkWords) -> Parameter<Self>;
+}
+
+impl LinkValue for f32 {
+    #[inline]
+    fn encode(parameter: Parameter<Self>) -> LinkWords {
+        [
+            parameter.value.to_bits() as u64,
+            parameter.base_value.to_bits() as u64,
+            0,
+        ]
+    }
+
+    #[inline]
+    fn decode(words: LinkWords) -> Parameter<Self> {
+        Parameter {
+            value: f32::from_bits(words[0] as u32),
+            base_value: f32::from_bits(words[1] as u32),
+        }
+    }
+}
+
+impl LinkValue for PlaybackRate {
+    #[inline]
+    fn encode(parameter: Parameter<Self>) -> LinkWords {
+        // the third word has a bit for each value that is in semitones
+        let encode = |rate: PlaybackRate| match rate {
+            PlaybackRate::Factor(factor) => (factor.to_bits(), 0),
+            PlaybackRate::Semitones(semitones) => (semitones.to_bits(), 1),
+        };
+        let (value, value_semitones) = encode(parameter.value);
+        let (base, base_semitones) = encode(parameter.base_value);
+        [value, base, value_semitones | base_semitones << 1]
+    }
+
+    #[inline]
+    fn decode(words: LinkWords) -> Parameter<Self> {
+        let decode = |bits: u64, semitones: bool| {
+            if semitones {
+                PlaybackRate::Semitones(f64::from_bits(bits))
+            } else {
+                PlaybackRate::Factor(f64::from_bits(bits))
+            }
+        };
+        Parameter {
+            value: decode(words[0], words[2] & 1 != 0),
+            base_value: decode(words[1], words[2] & 2 != 0),
+        }
+    }
+}
+
+/// Holds the [`LinkWords`] of a shared parameter in atomics, so threads can
+/// share it without locking. This is a sequence lock: readers retry if the
+/// value was written while they read it, and writers only wait for each
+/// other, which is rare and short.
+#[derive(Debug, Default)]
+struct LinkCell {
+    /// Incremented before and after every write, so it is odd while the
+    /// value is being written.
+    version: AtomicU64,
+    words: [AtomicU64; 3],
+}
+
+impl LinkCell {
+    /// Return the value and its version.
+    #[inline]
+    fn read(&self) -> (LinkWords, u64) {
+        loop {
+            let version = self.version.load(Ordering::Acquire);
+            if version.is_multiple_of(2) {
+                let words = [0, 1, 2].map(|i| self.words[i].load(Ordering::Relaxed));
+                fence(Ordering::Acquire);
+                if self.version.load(Ordering::Relaxed) == version {
+                    return (words, version);
+                }
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Write the value if it wasn't written since `version` was read.
+    /// Returns the new version, or [`None`] if the value was written.
+    #[inline]
+    fn write_if(&self, version: u64, words: LinkWords) -> Option<u64> {
+        self.version
+            .compare_exchange(version, version + 1, Ordering::Acquire, Ordering::Relaxed)
+            .ok()?;
+        fence(Ordering::Release);
+        for (word, value) in self.words.iter().zip(words) {
+            word.store(value, Ordering::Relaxed);
+        }
+        self.version.store(version + 2, Ordering::Release);
+        Some(version + 2)
+    }
+
+    /// Write the value. Returns the new version.
+    #[inline]
+    fn write(&self, words: LinkWords) -> u64 {
+        loop {
+            let version = self.version.load(Ordering::Relaxed);
+            if version.is_multiple_of(2) {
+                if let Some(version) = self.write_if(version, words) {
+                    return version;
+                }
+            }
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// A parameter shared between linked sounds. See [`SoundHandle::link`].
+///
+/// Values written in a frame are used by all linked sounds from the next
+/// frame on: the first linked sound that renders a frame commits
+/// [`ParameterLink::next`] to [`ParameterLink::current`], so all linked
+/// sounds render a frame with the same value, in whichever order they are
+/// rendered.
+#[derive(Debug, Default)]
+struct ParameterLink {
+    /// Incremented every time a frame is committed.
+    generation: AtomicU64,
+    /// The value used in the current frame.
+    current: LinkCell,
+    /// The last written value.
+    next: LinkCell,
+}
+
+impl ParameterLink {
+    /// Make a link that holds `words`.
+    fn new(words: LinkWords) -> Self {
+        let link = Self {
+            generation: AtomicU64::new(1),
+            ..Default::default()
+        };
+        link.current.write(words);
+        link.next.write(words);
+        link
+    }
+}
+
+/// Membership of a sound in a [`ParameterLink`].
+#[derive(Debug, Clone)]
+struct LinkSlot {
+    parameter: LinkedParameter,
+    link: Arc<ParameterLink>,
+    /// Generation of the link when this sound last read it.
+    seen: u64,
+    /// The value the sound read or wrote last.
+    words: LinkWords,
+    /// Version of [`ParameterLink::next`] when the sound read or wrote it
+    /// last.
+    version: u64,
+}
+
+impl PartialEq for LinkSlot {
+    fn eq(&self, other: &Self) -> bool {
+        self.parameter == other.parameter && Arc::ptr_eq(&self.link, &other.link)
+    }
+}
+
+/// A snapshot of the playback state of a [`Sound`], for example for save
+/// games. See [`Sound::playback_state`] and
+/// [`Sound::restore_playback_state`].
+///
+/// The audio data is not part of the snapshot, it can only be restored onto
+/// a sound with the same audio data. Commands with a custom easing function
+/// (see [`Command::with_ease`]) are saved with their regular easing when
+/// serialized.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaybackState {
+    /// Number of frames of the audio data.
+    pub frame_count: usize,
+    /// Hash of the audio data.
+    pub fingerprint: u64,
+    /// Sample rate of the sound, including the override.
+    pub sample_rate: u32,
+    /// Position of the playhead in frames.
+    pub index: usize,
+    /// Position between the current and the next frame.
+    pub fractional_position: f64,
+    /// Volume, including the value before the running command started.
+    pub volume: Parameter<f32>,
+    /// Panning, including the value before the running command started.
+    pub panning: Parameter<f32>,
+    /// Playback rate, including the value before the running command
+    /// started.
+    pub playback_rate: Parameter<PlaybackRate>,
+    /// Loop start as a frame index.
+    pub loop_start: usize,
+    /// Loop end as a frame index.
+    pub loop_end: usize,
+    /// Whether looping is enabled.
+    pub loop_enabled: bool,
+    /// Whether the sound is paused.
+    pub paused: bool,
+    /// Frames left to play, see [`Sound::set_play_for`].
+    pub frames_left: Option<usize>,
+    /// Pending and running commands. Their `start_after` is the time left
+    /// until they start, or minus the time they have been running.
+    pub commands: Vec<Command>,
+    /// Seconds of commands rendered since the last restart, see
+    /// [`Sound::restart`].
+    pub command_time: f64,
+}
+
+/// Specifies which output channels of the stream a sound plays on.
+///
+/// Channel indices start at 0, so `OutputRoute::Channels(2, 3)` means
+/// outputs 3 and 4 of the device. If the stream doesn't have the requested
+/// channels, the sound plays on the main outputs instead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputRoute {
+    /// Play on the first two channels (default).
+    #[default]
+    Main,
+    /// Play the left and right channels on the given output channels.
+    Channels(u16, u16),
+    /// Play on every pair of output channels. The left channel goes to
+    /// channels with an even index, the right one to channels with an odd
+    /// index.
+    All,
+}
+
+/// How panning splits the level of a sound between the channels, see
+/// [`Sound::set_pan_law`]. Both laws are constant-power, so the loudness
+/// doesn't change while the sound moves across the stereo field.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PanLaw {
+    /// Unity gain at the center, so centered sounds play unchanged. Hard
+    /// left or right boosts the panned channel by 3 dB (default).
+    #[default]
+    CenterUnity,
+    /// Equal-power panning: both channels are 3 dB quieter at the center,
+    /// and the panned channel plays at unity gain at hard left or right.
+    EqualPower,
+}
+
+/// Move `value` towards `target` with a one-pole filter with a time constant
+/// of `time` seconds.
+#[inline]
+fn smooth(value: &mut f32, target: f32, time: f32, sample_rate: u32) {
+    let coeff = 1.0 - (-1.0 / (time * sample_rate as f32)).exp();
+    *value += (target - *value) * coeff;
+    // snap to the target, so silent sounds can skip rendering
+    if (target - *value).abs() < 1e-6 {
+        *value = target;
+    }
+}
+
+/// Convert a position in seconds to a frame index. Negative and NaN values
+/// map to 0, values that don't fit in a [`usize`] map to [`usize::MAX`].
+#[inline]
+fn secs_to_index(seconds: f64, sample_rate: u32) -> usize {
+    let index = seconds * sample_rate as f64;
+    if index.is_nan() || index <= 0.0 {
+        0
+    } else {
+        // float to int casts saturate
+        index as usize
+    }
+}
+
+/// Length (in seconds) of the grains used to stretch audio in
+/// [`Sound::pitch_shifted`].
+const STRETCH_GRAIN_SECS: f64 = 0.04;
+
+/// Stretch `input` to `len` frames without changing its pitch, using
+/// waveform similarity overlap-add (WSOLA): Hann windowed grains of the
+/// input are overlapped by half, and each grain is moved by up to a quarter
+/// of its length so its waveform lines up with the previous grain.
+fn time_stretch(input: &[Frame], len: usize, grain: usize) -> Vec<Frame> {
+    let grain = grain.max(4) & !1;
+    let hop = grain / 2;
+    let tolerance = (grain / 4) as isize;
+    let stride = input.len() as f64 / len.max(1) as f64;
+    let window: Vec<f32> = (0..grain)
+        .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / grain as f32).cos())
+        .collect();
+    let at = |index: isize| {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| input.get(index))
+            .map_or(Frame::ZERO, |&frame| frame)
+    };
+    let mono = |index: isize| {
+        let frame = at(index);
+        frame.left + frame.right
+    };
+
+    let mut output = vec![Frame::ZERO; len + grain];
+    let mut weights = vec![0.0f32; len + grain];
+    let mut prev_start = 0;
+    for out_start in (0..len).step_by(hop) {
+        let nominal = (out_start as f64 * stride).round() as isize;
+        let start = if out_start == 0 {
+            0
+        } else {
+            // pick the grain that matches the input that would naturally
+            // follow the previous grain the best
+            let natural = prev_start + hop as isize;
+            let similarity = |start: isize| -> f32 {
+                (0..hop as isize)
+                    .map(|i| mono(natural + i) * mono(start + i))
+                    .sum()
+            };
+            // keep the grains inside of the input, so the end doesn't fade
+            // out early
+            let last_start = input.len().saturating_sub(grain) as isize;
+            let (low, high) = (
+                (nominal - tolerance).clamp(0, last_start),
+                (nominal + tolerance).clamp(0, last_start),
+            );
+            (low..=high)
+                .map(|start| (start, similarity(start)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map_or(low, |(start, _)| start)
+        };
+
+        for (i, &weight) in window.iter().enumerate() {
+            output[out_start + i] += at(start + i as isize) * weight;
+            weights[out_start + i] += weight;
+        }
+        prev_start = start;
+    }
+
+    output.truncate(len);
+    for (frame, weight) in output.iter_mut().zip(weights) {
+        if weight > 1e-3 {
+            *frame /= weight;
+        }
+    }
+    output
+}
+
 /// Specifies a loop region.
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) struct LoopPoints {
@@ -252,8 +970,8 @@ impl LoopPoints {
     #[inline]
     pub fn from_range_secs(range: RangeInclusive<f64>, sample_rate: u32) -> Self {
         Self {
-            start: (range.start() * sample_rate as f64) as usize,
-            end: (range.end() * sample_rate as f64) as usize,
+            start: secs_to_index(*range.start(), sample_rate),
+            end: secs_to_index(*range.end(), sample_rate),
         }
     }
 
@@ -279,21 +997,299 @@ impl Tweenable for LoopPoints {
     }
 }
 
-/// Audio data stored in memory. This type can be cheaply cloned, as the
-/// audio data is shared between all clones.
+/// How many semitones [`Sound::tape_stop`] and [`Sound::tape_start`] glide
+/// through. The rate jumps between zero and 1/16 of the speed at the end of
+/// the glide, where it is no longer audible as pitch.
+const TAPE_GLIDE_SEMITONES: f64 = 48.0;
+
+/// How long (in seconds) it takes a scrubbing sound to get most of the way to
+/// its target position.
+const SCRUB_SMOOTHING_SECS: f64 = 0.005;
+
+/// How long (in seconds) a sound fades out before it reaches its maximum play
+/// time. See [`Sound::set_max_play_time`].
+const MAX_PLAY_TIME_FADE_SECS: f64 = 0.005;
+
+/// Magic bytes at the start of the data returned by [`Sound::to_bytes`].
+const CACHED_SOUND_MAGIC: [u8; 4] = *b"KASD";
+
+/// Version of the layout written by [`Sound::to_bytes`]. Bump it when the
+/// layout changes, so old caches are rejected instead of misread.
+const CACHED_SOUND_VERSION: u16 = 1;
+
+/// Length of the header written by [`Sound::to_bytes`]: magic, version,
+/// stored channels, source channels, sample rate and frame count.
+const CACHED_SOUND_HEADER_LEN: usize = 4 + 2 + 1 + 4 + 4 + 8;
+
+/// State of an active scrub gesture. See [`Sound::begin_scrub`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Scrub {
+    /// The position the playhead is chasing, as a frame index.
+    target: f64,
+    /// Whether the playhead is currently moving backwards.
+    backwards: bool,
+}
+
+/// How long (in seconds) a new automation curve fades in from the current
+/// value of its parameter, so setting a curve doesn't snap the parameter.
+const AUTOMATION_FADE_SECS: f64 = 0.05;
+
+/// An [`AutomationCurve`] applied to a parameter of a [`Sound`].
 #[derive(Debug, Clone, PartialEq)]
-pub struct Sound {
-    /// Sample rate of the sound.
+struct Automation {
+    target: AutomationTarget,
+    curve: AutomationCurve,
+    time: AutomationTime,
+    /// Seconds rendered since the automation was set.
+    elapsed: f64,
+    /// Value of the parameter when the automation was set.
+    fade_from: f64,
+}
+
+/// An [`Lfo`] that modulates a parameter of a sound.
+#[derive(Debug, Clone, PartialEq)]
+struct ActiveLfo {
+    target: LfoTarget,
+    lfo: Lfo,
+    /// Seconds rendered since the LFO was set.
+    elapsed: f64,
+}
+
+/// Cutoff frequency (in Hz) of the DC blocker. See [`Sound::set_dc_block`].
+const DC_BLOCK_CUTOFF: f32 = 20.0;
+
+/// First-order highpass filter that removes DC offset:
+/// `y[n] = x[n] - x[n-1] + R * y[n-1]`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct DcBlocker {
+    prev_input: Frame,
+    prev_output: Frame,
+    /// Sample rate the coefficient was computed for.
     sample_rate: u32,
-    /// Audio data. Not mutable. Shared between all clones.
-    pub frames: Arc<[Frame]>,
-    /// Whether the sound is paused.
-    pub paused: bool,
-    /// The current playback position in frames.
-    index: Parameter<usize>,
-    /// The resampler used to resample the audio data.
-    resampler: Resampler,
-    /// The current playback rate of the sound. See [`PlaybackRate`] for more
+    /// The `R` coefficient.
+    coeff: f32,
+}
+
+impl DcBlocker {
+    #[inline]
+    fn process(&mut self, input: Frame, sample_rate: u32) -> Frame {
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.coeff = (-2.0 * std::f32::consts::PI * DC_BLOCK_CUTOFF / sample_rate as f32).exp();
+        }
+        let output = input - self.prev_input + self.prev_output * self.coeff;
+        self.prev_input = input;
+        self.prev_output = output;
+        output
+    }
+}
+
+/// Corner frequency (in Hz) of the bass shelf. See [`Sound::set_tone`].
+const BASS_SHELF_FREQ: f32 = 250.0;
+
+/// Corner frequency (in Hz) of the treble shelf. See [`Sound::set_tone`].
+const TREBLE_SHELF_FREQ: f32 = 4000.0;
+
+/// Maximum boost or cut (in decibels) of the tone shelves.
+const TONE_MAX_DB: f32 = 12.0;
+
+/// Playback rate factors applied one per output frame. See
+/// [`Sound::set_rate_automation`].
+#[derive(Debug, Clone, PartialEq)]
+struct RateAutomation {
+    factors: Arc<[f64]>,
+    /// Index of the factor applied to the next output frame.
+    position: usize,
+}
+
+/// Lowest and highest fundamental frequency (in Hz) found by
+/// [`Sound::estimate_pitch`].
+const PITCH_RANGE: (f32, f32) = (40.0, 4000.0);
+
+/// Minimum normalized autocorrelation of a pitch estimate. Noisy or
+/// unpitched sounds stay below it.
+const PITCH_CONFIDENCE: f32 = 0.6;
+
+/// Two first-order shelving filters. Each shelf splits the signal with a
+/// one-pole lowpass and scales the band, so the gains can change every
+/// frame without recomputing the coefficients.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct ToneFilter {
+    /// Lowpass state of the bass shelf.
+    bass: Frame,
+    /// Lowpass state of the treble shelf.
+    treble: Frame,
+    /// Sample rate the coefficients were computed for.
+    sample_rate: u32,
+    bass_coeff: f32,
+    treble_coeff: f32,
+}
+
+impl ToneFilter {
+    #[inline]
+    fn process(&mut self, input: Frame, bass_db: f32, treble_db: f32, sample_rate: u32) -> Frame {
+        if self.sample_rate != sample_rate {
+            let coeff =
+                |freq: f32| 1.0 - (-2.0 * std::f32::consts::PI * freq / sample_rate as f32).exp();
+            self.sample_rate = sample_rate;
+            self.bass_coeff = coeff(BASS_SHELF_FREQ);
+            self.treble_coeff = coeff(TREBLE_SHELF_FREQ);
+        }
+        let gain = |db: f32| 10.0f32.powf(db.clamp(-TONE_MAX_DB, TONE_MAX_DB) / 20.0);
+
+        self.bass += (input - self.bass) * self.bass_coeff;
+        self.treble += (input - self.treble) * self.treble_coeff;
+        let lows = self.bass;
+        let highs = input - self.treble;
+        input + lows * (gain(bass_db) - 1.0) + highs * (gain(treble_db) - 1.0)
+    }
+}
+
+/// Describes how the occlusion amount of a sound maps to a lowpass filter
+/// and a volume drop. See [`Sound::set_occlusion`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OcclusionModel {
+    /// Lowpass cutoff (in Hz) of a fully occluded sound. The cutoff sweeps
+    /// from [`OcclusionModel::max_cutoff`] to this value on a logarithmic
+    /// scale, so each step of the amount sounds equally large.
+    pub min_cutoff: f32,
+    /// Lowpass cutoff (in Hz) of a barely occluded sound.
+    pub max_cutoff: f32,
+    /// Volume change (in decibels) of a fully occluded sound. The change is
+    /// linear in decibels.
+    pub attenuation_db: f32,
+    /// Shapes the occlusion amount before it is mapped: values above 1.0
+    /// keep light occlusion subtle, values below 1.0 make it stronger.
+    pub curve: f32,
+    /// How long it takes the occlusion to follow a new amount, so frequent
+    /// updates (for example from raycasts) don't cause zipper noise.
+    pub smoothing: Duration,
+}
+
+impl Default for OcclusionModel {
+    fn default() -> Self {
+        Self {
+            min_cutoff: 600.0,
+            max_cutoff: 20000.0,
+            attenuation_db: -12.0,
+            curve: 1.0,
+            smoothing: Duration::from_millis(50),
+        }
+    }
+}
+
+impl OcclusionModel {
+    /// Return the lowpass cutoff (in Hz) and the gain for an occlusion
+    /// amount.
+    #[inline]
+    pub fn map(&self, amount: f32) -> (f32, f32) {
+        let amount = amount.clamp(0.0, 1.0).powf(self.curve.max(0.0));
+        let cutoff = self.max_cutoff * (self.min_cutoff / self.max_cutoff).powf(amount);
+        let gain = 10.0f32.powf(self.attenuation_db * amount / 20.0);
+        (cutoff, gain)
+    }
+}
+
+/// Occlusion state of a sound. The lowpass is two cascaded one-pole
+/// filters (12 dB per octave).
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+struct Occlusion {
+    /// Requested occlusion amount.
+    target: f32,
+    /// Smoothed occlusion amount.
+    amount: f32,
+    /// Filter states.
+    stages: [Frame; 2],
+}
+
+impl Occlusion {
+    #[inline]
+    fn process(&mut self, input: Frame, model: &OcclusionModel, sample_rate: u32) -> Frame {
+        let smoothing = model.smoothing.as_secs_f32();
+        if smoothing > 0.0 {
+            let coeff = 1.0 - (-1.0 / (smoothing * sample_rate as f32)).exp();
+            self.amount += (self.target - self.amount) * coeff;
+            if (self.target - self.amount).abs() < 1e-5 {
+                self.amount = self.target;
+            }
+        } else {
+            self.amount = self.target;
+        }
+
+        if self.amount == 0.0 {
+            self.stages = [input; 2];
+            return input;
+        }
+
+        let (cutoff, gain) = model.map(self.amount);
+        let nyquist = sample_rate as f32 * 0.5;
+        let coeff = 1.0
+            - (-2.0 * std::f32::consts::PI * cutoff.clamp(1.0, nyquist) / sample_rate as f32).exp();
+        self.stages[0] += (input - self.stages[0]) * coeff;
+        self.stages[1] += (self.stages[0] - self.stages[1]) * coeff;
+        self.stages[1] * gain
+    }
+}
+
+/// State of a loop seam preview. See [`Sound::preview_loop_seam`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct SeamPreview {
+    /// Amount of frames played before the loop end.
+    lead: usize,
+    /// Amount of frames played after the loop start.
+    tail: usize,
+    /// Whether the playhead has passed the seam and plays the tail.
+    after_seam: bool,
+    /// Position of the sound before the preview started.
+    index: usize,
+    /// Whether the sound was paused before the preview started.
+    paused: bool,
+}
+
+/// User data attached to a [`Sound`]. Ignored when comparing sounds.
+#[derive(Clone, Default)]
+struct UserData(Option<Arc<dyn Any + Send + Sync>>);
+
+impl PartialEq for UserData {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl std::fmt::Debug for UserData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(_) => f.write_str("Some(..)"),
+            None => f.write_str("None"),
+        }
+    }
+}
+
+/// Audio data stored in memory. This type can be cheaply cloned, as the
+/// audio data is shared between all clones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    /// Sample rate of the sound. This is the override if one is set.
+    sample_rate: u32,
+    /// Sample rate the sound was created with, restored when the override
+    /// is cleared. See [`Sound::set_sample_rate_override`].
+    source_sample_rate: u32,
+    /// Audio data. Not mutable. Shared between all clones. See
+    /// [`Sound::frames`].
+    pub(crate) frames: FrameData,
+    /// Amount of channels of the source audio, before it was converted to
+    /// [`Frame`]s. See [`Sound::channels_in_source`].
+    source_channels: u32,
+    /// Whether the sound is paused.
+    pub paused: bool,
+    /// Whether the sound was stopped. A stopped sound is always finished.
+    stopped: bool,
+    /// The current playback position in frames.
+    index: Parameter<usize>,
+    /// The resampler used to resample the audio data.
+    resampler: Resampler,
+    /// The current playback rate of the sound. See [`PlaybackRate`] for more
     /// details.
     playback_rate: Parameter<PlaybackRate>,
     /// Fractional position between samples. Always in the range of 0-1.
@@ -312,14 +1308,133 @@ pub struct Sound {
     /// * Panning of 0.5 means center panning (default)
     /// * Panning of 1.0 means hard right panning
     panning: Parameter<f32>,
+    /// Amount of source frames left to play before the sound finishes. See
+    /// [`Sound::set_play_for`].
+    frames_left: Option<usize>,
+    /// See [`Sound::set_latency_offset`].
+    latency_offset: i64,
+    /// Time (in seconds) of silence left to output before the sound starts,
+    /// from a positive latency offset.
+    start_delay: f64,
+    /// Time (in seconds) the sound was rendered for while not paused.
+    play_time: f64,
+    /// See [`Sound::set_max_play_time`].
+    max_play_time: Option<f64>,
+    /// Maximum age set by the renderer, see
+    /// [`crate::Mixer::set_max_sound_age`].
+    max_age: Option<f64>,
+    /// Play time at which a sound whose voice was stolen stops, see
+    /// [`Sound::steal`].
+    stolen_at: Option<f64>,
+    /// Scrubbing state. If [`Some`], the playhead chases a target position
+    /// instead of following the playback rate.
+    scrub: Option<Scrub>,
+    /// Per-output-frame factors of the playback rate, see
+    /// [`Sound::set_rate_automation`].
+    rate_automation: Option<RateAutomation>,
+    /// Output channels the sound plays on.
+    output_route: OutputRoute,
+    /// Pan law used to apply the panning, see [`Sound::set_pan_law`].
+    pan_law: PanLaw,
+    /// Whether the sound is muted. Muted sounds keep playing, but output
+    /// silence.
+    muted: bool,
+    /// Whether the sound is soloed. See [`Sound::set_solo`].
+    solo: bool,
+    /// Whether the sound is only heard by recording renderers. See
+    /// [`Sound::set_record_only`].
+    record_only: bool,
+    /// Whether the sound is only heard by live renderers. See
+    /// [`Sound::set_monitor_only`].
+    monitor_only: bool,
+    /// Whether the sound is silent and skips rendering. See
+    /// [`Sound::next_frame`].
+    skipping_silence: bool,
+    /// Whether the renderer took the voice of the sound away. See
+    /// [`Sound::voice_state`].
+    virtual_voice: bool,
+    /// Whether the sound rendered a frame yet.
+    rendered: bool,
+    /// Whether events are recorded for the renderer's event log.
+    record_events: bool,
+    /// Events that happened since the renderer last took them.
+    events: Vec<RenderEventKind>,
+    /// Loop wraps that are not heard yet, because the resampler still
+    /// outputs the frames from before the wrap.
+    pending_wraps: u32,
+    /// Index of the frame the resampler output last.
+    last_output_index: usize,
+    /// Amplitude below which the sound counts as silent, see
+    /// [`Sound::set_silence_threshold`].
+    silence_threshold: f32,
+    /// How long (in seconds) the sound has to stay below the silence
+    /// threshold before it counts as silent.
+    silence_hold: f64,
+    /// How long (in seconds) the sound has been below the silence threshold.
+    quiet_time: f64,
+    /// Parameters shared with other sounds, see [`SoundHandle::link`].
+    links: Vec<LinkSlot>,
+    /// User data, see [`Sound::set_user_data`].
+    user_data: UserData,
+    /// Automation curves applied to the parameters of the sound.
+    automations: Vec<Automation>,
+    /// LFOs that modulate the volume or panning. See [`Sound::set_lfo`].
+    lfos: Vec<ActiveLfo>,
+    /// Volume factor of the tremolo LFO in the current frame.
+    lfo_gain: f32,
+    /// Panning offset of the auto-pan LFO in the current frame.
+    lfo_pan: f32,
+    /// Named positions in the sound (id, frame index). See
+    /// [`Sound::set_marker`].
+    markers: Vec<(u32, usize)>,
+    /// DC blocking filter. If [`None`], the filter is disabled.
+    dc_blocker: Option<DcBlocker>,
+    /// Loop seam preview. See [`Sound::preview_loop_seam`].
+    seam_preview: Option<SeamPreview>,
+    /// Gain of the bass shelf in decibels. See [`Sound::set_tone`].
+    bass_db: Parameter<f32>,
+    /// Gain of the treble shelf in decibels. See [`Sound::set_tone`].
+    treble_db: Parameter<f32>,
+    /// Tone control filters.
+    tone: ToneFilter,
+    /// Time constant of the volume smoother in seconds. If 0, the volume
+    /// is not smoothed. See [`Sound::set_volume_smoothing`].
+    volume_smoothing: f32,
+    /// Smoothed volume, used instead of the volume if smoothing is enabled.
+    smoothed_volume: f32,
+    /// Time constant of the panning smoother in seconds. If 0, the panning
+    /// is not smoothed. See [`Sound::set_panning_smoothing`].
+    panning_smoothing: f32,
+    /// Smoothed panning, used instead of the panning if smoothing is
+    /// enabled.
+    smoothed_panning: f32,
+    /// Occlusion amount and filter. See [`Sound::set_occlusion`].
+    occlusion: Occlusion,
+    /// Position on the last frame while looping, used to tell whether the
+    /// playhead crossed a loop point that is being tweened.
+    loop_prev_index: usize,
+    /// Latest `start_after` (in seconds) accepted by
+    /// [`Sound::try_add_command`].
+    max_command_delay: Option<f64>,
+    /// Seconds that passed on the command timers since the last restart, or
+    /// since commands were added to a sound without commands. Used by
+    /// [`Sound::restart`] to rewind the timers.
+    command_time: f64,
+    /// See [`Sound::set_zero_rate_policy`].
+    zero_rate_policy: ZeroRatePolicy,
+    /// See [`Sound::set_occlusion_model`].
+    occlusion_model: OcclusionModel,
 }
 
 impl Default for Sound {
     fn default() -> Self {
         Self {
             sample_rate: 0,
-            frames: Arc::new([]),
+            source_sample_rate: 0,
+            frames: FrameData::default(),
+            source_channels: 2,
             paused: false,
+            stopped: false,
             index: Parameter::new(0),
             resampler: Resampler::new(0),
             playback_rate: Parameter::new(PlaybackRate::Factor(1.0)),
@@ -329,6 +1444,53 @@ impl Default for Sound {
             loop_points: Parameter::new(LoopPoints::NO_LOOP),
             loop_enabled: false,
             panning: Parameter::new(0.5),
+            frames_left: None,
+            latency_offset: 0,
+            start_delay: 0.0,
+            play_time: 0.0,
+            max_play_time: None,
+            max_age: None,
+            stolen_at: None,
+            scrub: None,
+            rate_automation: None,
+            output_route: OutputRoute::Main,
+            pan_law: PanLaw::CenterUnity,
+            muted: false,
+            solo: false,
+            record_only: false,
+            monitor_only: false,
+            skipping_silence: false,
+            virtual_voice: false,
+            rendered: false,
+            record_events: false,
+            events: vec![],
+            pending_wraps: 0,
+            last_output_index: 0,
+            silence_threshold: 0.0,
+            silence_hold: 0.0,
+            quiet_time: 0.0,
+            links: vec![],
+            user_data: UserData(None),
+            automations: vec![],
+            lfos: vec![],
+            lfo_gain: 1.0,
+            lfo_pan: 0.0,
+            markers: vec![],
+            dc_blocker: None,
+            seam_preview: None,
+            bass_db: Parameter::new(0.0),
+            treble_db: Parameter::new(0.0),
+            tone: ToneFilter::default(),
+            volume_smoothing: 0.0,
+            smoothed_volume: 1.0,
+            panning_smoothing: 0.0,
+            smoothed_panning: 0.5,
+            occlusion: Occlusion::default(),
+            max_command_delay: None,
+            command_time: 0.0,
+            zero_rate_policy: ZeroRatePolicy::Hold,
+            loop_prev_index: 0,
+            occlusion_model: OcclusionModel::default(),
         }
     }
 }
@@ -375,9 +1537,12 @@ where
 
 impl Sound {
     /// Make a new [`Sound`] with a given sample rate and frames.
-    fn new(sample_rate: u32, frames: Arc<[Frame]>) -> Self {
+    fn new(sample_rate: u32, frames: impl Into<FrameData>) -> Self {
+        let frames = frames.into();
         let mut sound = Sound {
             sample_rate,
+            source_sample_rate: sample_rate,
+            source_channels: if frames.is_mono() { 1 } else { 2 },
             frames,
             ..Default::default()
         };
@@ -396,6 +1561,31 @@ impl Sound {
     /// Required features: `symphonia`
     #[cfg(feature = "symphonia")]
     pub fn from_boxed_media_source(media_source: Box<dyn MediaSource>) -> Result<Self, KaError> {
+        Self::from_boxed_media_source_ex(media_source, &DecodeSettings::default())
+    }
+
+    /// Make a [`Sound`] from [`symphonia`]'s [`Box`]'ed [`MediaSource`], with
+    /// custom [`DecodeSettings`].
+    ///
+    /// Required features: `symphonia`
+    #[cfg(feature = "symphonia")]
+    pub fn from_boxed_media_source_ex(
+        media_source: Box<dyn MediaSource>,
+        settings: &DecodeSettings,
+    ) -> Result<Self, KaError> {
+        Self::from_boxed_media_source_with_report(media_source, settings).map(|(sound, _)| sound)
+    }
+
+    /// Make a [`Sound`] from [`symphonia`]'s [`Box`]'ed [`MediaSource`], with
+    /// custom [`DecodeSettings`]. Also return a [`DecodeReport`] describing
+    /// the errors that were recovered from, see [`DecodeSettings::on_error`].
+    ///
+    /// Required features: `symphonia`
+    #[cfg(feature = "symphonia")]
+    pub fn from_boxed_media_source_with_report(
+        media_source: Box<dyn MediaSource>,
+        settings: &DecodeSettings,
+    ) -> Result<(Self, DecodeReport), KaError> {
         use std::io::ErrorKind::UnexpectedEof;
         use symphonia::core::codecs::DecoderOptions;
         use symphonia::core::errors::Error;
@@ -408,20 +1598,38 @@ impl Sound {
         let mss = MediaSourceStream::new(media_source, Default::default());
 
         // create a hint to help the format registry to guess what format
-        // the media source is using, we'll let symphonia figure that out for us
-        let hint = Hint::new();
+        // the media source is using. without a hint, we'll let symphonia
+        // figure that out for us
+        let mut hint = Hint::new();
+        if let Some(extension) = &settings.extension {
+            hint.with_extension(extension);
+        }
+        if let Some(mime_type) = &settings.mime_type {
+            hint.mime_type(mime_type);
+        }
 
-        // use default options for reading and encoding
-        let format_opts: FormatOptions = Default::default();
+        let format_opts = FormatOptions {
+            enable_gapless: settings.gapless,
+            ..Default::default()
+        };
         let metadata_opts: MetadataOptions = Default::default();
-        let decoder_opts: DecoderOptions = Default::default();
+        let decoder_opts = DecoderOptions {
+            verify: settings.verify,
+        };
 
         // probe the media source for a format
         let probed =
             symphonia::default::get_probe().format(&hint, mss, &format_opts, &metadata_opts)?;
 
         let mut format = probed.format;
-        let track = format.default_track().ok_or(KaError::NoTracksArePresent)?;
+        let track = match settings.track_id {
+            Some(id) => format
+                .tracks()
+                .iter()
+                .find(|track| track.id == id)
+                .ok_or(KaError::TrackNotFound(id))?,
+            None => format.default_track().ok_or(KaError::NoTracksArePresent)?,
+        };
 
         // create a decoder for the track
         let mut decoder =
@@ -437,20 +1645,41 @@ impl Sound {
             .ok_or(KaError::UnknownSampleRate)?;
 
         let mut frames = Vec::new(); // audio data
+        let mut channels = 2;
+        let mut report = DecodeReport::default();
+
+        // decide what to do with a decoding error. returns `true` if
+        // decoding should continue with the next packet
+        let recover = |error: Error, report: &mut DecodeReport| -> Result<bool, KaError> {
+            match settings.on_error {
+                OnDecodeError::Fail => Err(error.into()),
+                // only a corrupt packet can be skipped, anything else (like
+                // an io error) would most likely fail again
+                OnDecodeError::SkipPacket if matches!(error, Error::DecodeError(_)) => {
+                    report.skipped_packets += 1;
+                    report.warning = Some(error.to_string());
+                    Ok(true)
+                }
+                _ => {
+                    report.truncated = true;
+                    report.warning = Some(error.to_string());
+                    Ok(false)
+                }
+            }
+        };
 
         loop {
             // get the next packet from the format reader
             let packet = match format.next_packet() {
                 Ok(p) => p,
-                Err(Error::IoError(e)) => {
-                    // if we reached eof, stop decoding
-                    if e.kind() == UnexpectedEof {
-                        break;
+                // if we reached eof, stop decoding
+                Err(Error::IoError(e)) if e.kind() == UnexpectedEof => break,
+                Err(e) => {
+                    if recover(e, &mut report)? {
+                        continue;
                     }
-                    // ...otherwise return KaError
-                    return Err(Error::IoError(e).into());
+                    break;
                 }
-                Err(e) => return Err(e.into()), // not io error
             };
 
             // if the packet does not belong to the selected track, skip it
@@ -459,11 +1688,30 @@ impl Sound {
             }
 
             // decode packet
-            let buffer = decoder.decode(&packet)?;
-            frames.append(&mut load_frames_from_buffer_ref(&buffer)?);
+            match decoder.decode(&packet) {
+                Ok(buffer) => {
+                    channels = buffer.spec().channels.count() as u32;
+                    frames.append(&mut load_frames_from_buffer_ref(&buffer)?);
+                }
+                Err(e) => {
+                    if !recover(e, &mut report)? {
+                        break;
+                    }
+                }
+            }
+        }
+
+        // a truncated or patched up sound can't match the checksum
+        if report.is_clean() && decoder.finalize().verify_ok == Some(false) {
+            return Err(KaError::VerificationFailed);
         }
 
-        Ok(Self::new(sample_rate, frames.into()))
+        if frames.is_empty() {
+            return Err(KaError::EmptyAudio);
+        }
+        let mut sound = Self::new(sample_rate, frames);
+        sound.source_channels = channels;
+        Ok((sound, report))
     }
 
     /// Make a [`Sound`] from [`symphonia`]'s [`MediaSource`].
@@ -475,6 +1723,34 @@ impl Sound {
         Self::from_boxed_media_source(Box::new(media_source))
     }
 
+    /// Make a [`Sound`] from [`symphonia`]'s [`MediaSource`], with custom
+    /// [`DecodeSettings`], for example to enable gapless playback.
+    ///
+    /// Required features: `symphonia`
+    #[cfg(feature = "symphonia")]
+    #[inline]
+    pub fn from_media_source_ex(
+        media_source: impl MediaSource + 'static,
+        settings: &DecodeSettings,
+    ) -> Result<Self, KaError> {
+        Self::from_boxed_media_source_ex(Box::new(media_source), settings)
+    }
+
+    /// Make a [`Sound`] from [`symphonia`]'s [`MediaSource`], with custom
+    /// [`DecodeSettings`], and return a [`DecodeReport`] alongside it. Use
+    /// this with [`DecodeSettings::on_error`] to keep the audio that decoded
+    /// fine when the source is partly corrupt.
+    ///
+    /// Required features: `symphonia`
+    #[cfg(feature = "symphonia")]
+    #[inline]
+    pub fn from_media_source_with_report(
+        media_source: impl MediaSource + 'static,
+        settings: &DecodeSettings,
+    ) -> Result<(Self, DecodeReport), KaError> {
+        Self::from_boxed_media_source_with_report(Box::new(media_source), settings)
+    }
+
     /// Make a [`Sound`] from a [`Cursor`] of bytes. Uses [`symphonia`] to decode audio.
     ///
     /// Required features: `symphonia`
@@ -486,13 +1762,29 @@ impl Sound {
         Self::from_media_source(cursor)
     }
 
+    /// Make a [`Sound`] from a [`Cursor`] of bytes, using the extension of
+    /// `path` (for example `"sounds/jump.ogg"`) as a format hint, which
+    /// speeds up detecting the format. Uses [`symphonia`] to decode audio.
+    ///
+    /// Required features: `symphonia`
+    #[cfg(feature = "symphonia")]
+    pub fn from_cursor_with_hint<T: AsRef<[u8]> + Send + Sync + 'static>(
+        cursor: Cursor<T>,
+        path: &str,
+    ) -> Result<Self, KaError> {
+        Self::from_boxed_media_source_ex(Box::new(cursor), &DecodeSettings::from_path(path))
+    }
+
     /// Make a [`Sound`] from a file path. Uses [`symphonia`] to decode audio.
+    /// The extension of the path is used as a format hint.
     ///
     /// Required features: `symphonia`
     #[cfg(feature = "symphonia")]
     #[inline]
     pub fn from_path(path: impl AsRef<std::path::Path>) -> Result<Self, KaError> {
-        Self::from_media_source(std::fs::File::open(path)?)
+        let path = path.as_ref();
+        let settings = DecodeSettings::from_path(path);
+        Self::from_boxed_media_source_ex(Box::new(std::fs::File::open(path)?), &settings)
     }
 
     /// Make a [`Sound`] from a [`Vec`] of bytes ([`u8`]). Uses [`symphonia`] to decode audio.
@@ -507,452 +1799,3156 @@ impl Sound {
     /// Make a [`Sound`] from a slice of [`Frame`]s and a sample rate.
     #[inline]
     pub fn from_frames(sample_rate: u32, frames: &[Frame]) -> Self {
-        Self::new(sample_rate, frames.into())
-    }
-
-    /// Return the sample rate of the sound.
-    #[inline]
-    pub const fn sample_rate(&self) -> u32 {
-        self.sample_rate
+        Self::new(sample_rate, frames)
     }
 
-    /// Return the duration of the sound.
-    ///
-    /// Returns [`Duration`].
+    /// Make a [`Sound`] from a slice of mono samples and a sample rate. Each
+    /// sample is played on both channels.
     #[inline]
-    pub fn duration(&self) -> Duration {
-        Duration::from_secs_f64(self.duration_seconds())
+    pub fn from_mono_samples(sample_rate: u32, samples: &[f32]) -> Self {
+        let frames: Arc<[Frame]> = samples
+            .iter()
+            .map(|&sample| Frame::from_mono(sample))
+            .collect();
+        let mut sound = Self::new(sample_rate, frames);
+        sound.source_channels = 1;
+        sound
     }
 
-    /// Return the duration of the sound in seconds.
+    /// Make a [`Sound`] from a slice of interleaved `[left, right]` samples
+    /// and a sample rate.
     #[inline]
-    pub fn duration_seconds(&self) -> f64 {
-        self.frames.len() as f64 / self.sample_rate as f64
+    pub fn from_stereo_samples(sample_rate: u32, samples: &[[f32; 2]]) -> Self {
+        let frames: Arc<[Frame]> = samples
+            .iter()
+            .map(|&samples| Frame::from(samples))
+            .collect();
+        Self::new(sample_rate, frames)
     }
 
-    /// Push the current frame (pointed by `self.index`) to the resampler.
-    pub fn push_frame_to_resampler(&mut self) {
-        let frame_index = self.index.value;
-        let frame = self
-            .frames
-            .get(frame_index)
-            .unwrap_or(&Frame::ZERO) // push silence if index is out of the range
-            .panned(self.panning.value.max(0.0))
-            * self.volume.value;
-        self.resampler.push_frame(frame, frame_index);
-    }
+    /// Make a [`Sound`] from data written by [`Sound::to_bytes`], without
+    /// decoding it again. This is much faster than decoding compressed audio,
+    /// so assets can be decoded once at build time and loaded from the cache.
+    ///
+    /// Returns [`KaError::InvalidCachedSound`] if the data wasn't written by
+    /// [`Sound::to_bytes`], was written by an incompatible version, or is
+    /// truncated.
+    pub fn from_cached_bytes(bytes: &[u8]) -> Result<Self, KaError> {
+        let Some((header, data)) = bytes.split_first_chunk::<CACHED_SOUND_HEADER_LEN>() else {
+            return Err(KaError::InvalidCachedSound("the header is truncated"));
+        };
+        if header[0..4] != CACHED_SOUND_MAGIC {
+            return Err(KaError::InvalidCachedSound("the magic bytes don't match"));
+        }
+        if u16::from_le_bytes([header[4], header[5]]) != CACHED_SOUND_VERSION {
+            return Err(KaError::InvalidCachedSound("unsupported version"));
+        }
+        let stored_channels = header[6] as usize;
+        let source_channels = u32::from_le_bytes([header[7], header[8], header[9], header[10]]);
+        let sample_rate = u32::from_le_bytes([header[11], header[12], header[13], header[14]]);
+        let mut len = [0; 8];
+        len.copy_from_slice(&header[15..23]);
+        let len = u64::from_le_bytes(len);
 
-    /// Return whether the sound is playing backward.
-    #[inline]
-    pub fn is_playing_backwards(&mut self) -> bool {
-        self.playback_rate.value.as_factor().is_sign_negative()
-    }
+        if stored_channels != 1 && stored_channels != 2 {
+            return Err(KaError::InvalidCachedSound("invalid channel layout"));
+        }
+        if sample_rate == 0 {
+            return Err(KaError::InvalidCachedSound("invalid sample rate"));
+        }
+        let expected = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_mul(stored_channels * 4));
+        if expected != Some(data.len()) {
+            return Err(KaError::InvalidCachedSound(
+                "the sample data doesn't match the frame count",
+            ));
+        }
 
-    /// Increment/decrement the position value in the sound, pushing the
-    /// previous sound frame to the resampler.
-    pub fn update_position(&mut self) {
-        if self.paused {
-            self.resampler.push_frame(Frame::ZERO, self.index.value);
+        let mut samples = data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+        let frames = if stored_channels == 1 {
+            FrameData::Mono(samples.collect())
         } else {
-            self.push_frame_to_resampler();
-
-            // increment/decrement index
-            if self.is_playing_backwards() {
-                self.index.value -= 1;
-            } else {
-                self.index.value += 1
+            let mut frames = Vec::with_capacity(len as usize);
+            while let (Some(left), Some(right)) = (samples.next(), samples.next()) {
+                frames.push(Frame::new(left, right));
             }
-        }
-    }
+            FrameData::from(frames)
+        };
 
-    /// Return whether the sound has finished playback.
-    #[inline]
-    pub fn finished(&self) -> bool {
-        self.index.value >= self.frames.len()
+        let mut sound = Self::new(sample_rate, frames);
+        sound.source_channels = source_channels;
+        Ok(sound)
     }
 
-    /// Render the next frame. If the sound has ended, return [`None`].
+    /// Mix several sounds into a single sound, for example to pre-render a
+    /// layered sound effect. The result is as long as the longest sound.
+    ///
+    /// The frames are summed as they are, the volume, panning and commands of
+    /// the sounds are ignored. Use [`Sound::mix_with_gain`] to change the
+    /// level of each sound.
+    ///
+    /// Returns [`KaError::SampleRateMismatch`] if the sounds don't have the
+    /// same sample rate.
     #[inline]
-    pub fn next_frame(&mut self, sample_rate: u32) -> Option<Frame> {
-        if self.finished() {
-            return None;
-        }
-
-        if self.loop_enabled {
-            self.update_loop(self.loop_points.value.start, self.loop_points.value.end);
-        }
-
-        // update commands
-        if !self.commands.is_empty() {
-            self.update_commands(1.0 / sample_rate as f64);
-        }
-
-        // get resampled frame
-        let frame = self.resampler.get(self.fractional_position as f32);
-
-        // increment fractional position
-        self.fractional_position += (self.sample_rate as f64 / sample_rate as f64)
-            * self.playback_rate.value.as_factor().abs();
+    pub fn mix(sounds: &[&Sound]) -> Result<Self, KaError> {
+        let sounds: Vec<_> = sounds.iter().map(|&sound| (sound, 1.0)).collect();
+        Self::mix_with_gain(&sounds)
+    }
 
-        // step the corrent amount of samples forward/backward
-        while self.fractional_position >= 1.0 {
-            self.fractional_position -= 1.0;
-            self.update_position();
+    /// Mix several sounds into a single sound, multiplying the frames of each
+    /// sound by its gain. See [`Sound::mix`].
+    pub fn mix_with_gain(sounds: &[(&Sound, f32)]) -> Result<Self, KaError> {
+        let sample_rate = sounds.first().map_or(0, |(sound, _)| sound.sample_rate);
+        let mut len = 0;
+        for (sound, _) in sounds {
+            if sound.sample_rate != sample_rate {
+                return Err(KaError::SampleRateMismatch(sound.sample_rate, sample_rate));
+            }
+            len = len.max(sound.frames.len());
         }
 
-        Some(frame)
-    }
-
-    fn update_loop(&mut self, start: usize, end: usize) {
-        let index = self.index.value;
-        if self.is_playing_backwards() {
-            if index <= start {
-                self.seek_to_index(end);
+        let mut frames = vec![Frame::ZERO; len];
+        for (sound, gain) in sounds {
+            for (out, frame) in frames.iter_mut().zip(sound.frames.iter()) {
+                *out += frame * *gain;
             }
-        } else if index >= end {
-            self.seek_to_index(start);
         }
+        Ok(Self::new(sample_rate, frames))
     }
 
-    /// Reset the sound to the beginning.
+    /// Return the sample rate of the sound. If the sample rate is
+    /// overridden, this is the override. See
+    /// [`Sound::set_sample_rate_override`].
     #[inline]
-    pub fn reset(&mut self) {
-        self.seek_to_index(0);
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
     }
 
-    /// Set the playback rate of the sound. See [`PlaybackRate`] for more
-    /// details. Returns the previous playback rate.
+    /// Return the audio data of the sound. The data is shared between all
+    /// clones of the sound, so this is cheap, unless the sound stores its
+    /// frames as mono (see [`Sound::compacted`]), in which case they are
+    /// copied into new frames.
     #[inline]
-    pub fn set_playback_rate(&mut self, playback_rate: PlaybackRate) -> PlaybackRate {
-        let prev_playback_rate = self.playback_rate.value;
-        self.playback_rate.start_tween(playback_rate);
-        prev_playback_rate
+    pub fn frames(&self) -> Arc<[Frame]> {
+        match &self.frames {
+            FrameData::Stereo(frames) => frames.clone(),
+            FrameData::Mono(samples) => samples.iter().copied().map(Frame::from_mono).collect(),
+        }
     }
 
-    /// Return the current playback rate value. Can be modified with commands.
+    /// Return the number of frames in the sound.
     #[inline]
-    pub fn playback_rate(&self) -> PlaybackRate {
-        self.playback_rate.value
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
     }
 
-    /// Return the current base playback rate value. Can't be modified with commands.
+    /// Play the frames as if they were recorded at `sample_rate`, for
+    /// example for files with wrong sample rate metadata. The frame data is
+    /// not touched, but the playback speed, the duration and every
+    /// conversion between seconds and frames use the override. Can be
+    /// changed during playback. A sample rate of 0 is ignored.
+    ///
+    /// Positions that were already converted to frames, like loop points
+    /// and markers, stay on the same frames.
     #[inline]
-    pub fn base_playback_rate(&self) -> PlaybackRate {
-        self.playback_rate.base_value
+    pub fn set_sample_rate_override(&mut self, sample_rate: u32) {
+        if sample_rate != 0 {
+            self.sample_rate = sample_rate;
+        }
     }
 
-    /// Set the current volume. Return the previous volume value.
+    /// Remove the sample rate override and go back to the sample rate the
+    /// sound was created with.
     #[inline]
-    pub fn set_volume(&mut self, volume: f32) -> f32 {
-        let prev_volume = self.volume.value;
-        self.volume.start_tween(volume);
-        prev_volume
+    pub fn clear_sample_rate_override(&mut self) {
+        self.sample_rate = self.source_sample_rate;
     }
 
-    /// Return the current volume value. Can be modified with commands.
+    /// Return the sample rate override, or [`None`] if the sound plays at
+    /// the sample rate it was created with.
     #[inline]
-    pub fn volume(&self) -> f32 {
-        self.volume.value
+    pub fn sample_rate_override(&self) -> Option<u32> {
+        (self.sample_rate != self.source_sample_rate).then_some(self.sample_rate)
     }
 
-    /// Return the current base volume value. Can't be modified with commands.
+    /// Return the amount of channels of the source audio, before it was
+    /// converted to stereo [`Frame`]s: 1 for sounds decoded from mono files
+    /// or made with [`Sound::from_mono_samples`], 2 otherwise. Useful for
+    /// handling the panning of mono sources differently.
     #[inline]
-    pub fn base_volume(&self) -> f32 {
-        self.volume.base_value
+    pub fn channels_in_source(&self) -> u32 {
+        self.source_channels
     }
 
-    /// Seek to an index in the source data.
+    /// Return the duration of the sound.
+    ///
+    /// Returns [`Duration`].
     #[inline]
-    pub fn seek_to_index(&mut self, index: usize) {
-        self.index.start_tween(index);
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration_seconds())
+    }
 
-        // if the sound is playing, push this frame to the resampler so it
-        // doesn't get skipped
-        if !self.paused {
-            self.push_frame_to_resampler();
+    /// Return the duration of the sound in seconds. Returns 0.0 for empty
+    /// sounds and sounds with a sample rate of 0.
+    #[inline]
+    pub fn duration_seconds(&self) -> f64 {
+        if !self.has_audio() {
+            return 0.0;
         }
+        self.frames.len() as f64 / self.sample_rate as f64
     }
 
-    /// Seek to the end of the sound.
+    /// Return whether the sound has any frames and a valid sample rate.
+    /// Sounds without audio are always finished.
     #[inline]
-    pub fn seek_to_end(&mut self) {
-        self.seek_to_index(self.frames.len().saturating_sub(1));
+    fn has_audio(&self) -> bool {
+        self.sample_rate != 0 && !self.frames.is_empty()
     }
 
-    /// Seek by a specified amount of seconds.
-    #[inline]
-    pub fn seek_by(&mut self, seconds: f64) {
-        let cur_position = self.index.value as f64 / self.sample_rate as f64;
-        let position = cur_position + seconds;
-        let index = (position * self.sample_rate as f64) as usize;
-        self.seek_to_index(index);
+    /// Return how many seconds of playback are left, taking the playback
+    /// rate, the playback direction and [`Sound::set_play_for`] into
+    /// account. Useful for countdowns.
+    ///
+    /// For looping sounds, this is the time until the playhead reaches the
+    /// loop end (or the loop start when playing backwards). Returns
+    /// [`f64::INFINITY`] if the playback rate is zero, and 0.0 if the sound
+    /// finished. The pause state is ignored.
+    pub fn remaining_seconds(&self) -> f64 {
+        if self.finished() {
+            return 0.0;
+        }
+        let index = self.index.value;
+        let region = self.loop_region().filter(|_| self.loop_enabled);
+        let frames = if self.is_playing_backwards() {
+            match region {
+                Some((start, _)) if index > start => index - start,
+                _ => index,
+            }
+        } else {
+            match region {
+                Some((_, end)) if index < end => end - index,
+                _ => self.frames.len() - index,
+            }
+        };
+        let frames = self.frames_left.map_or(frames, |left| frames.min(left));
+
+        let rate = self.playback_rate.value.as_factor().abs();
+        if rate == 0.0 {
+            return f64::INFINITY;
+        }
+        frames as f64 / self.sample_rate as f64 / rate
     }
 
-    /// Seek to a specified position in seconds.
+    /// Return how much time of playback is left. See
+    /// [`Sound::remaining_seconds`]. Returns [`Duration::MAX`] if the
+    /// playback rate is zero.
     #[inline]
-    pub fn seek_to(&mut self, seconds: f64) {
-        let index = (seconds * self.sample_rate as f64) as usize;
-        self.seek_to_index(index);
+    pub fn remaining(&self) -> Duration {
+        Duration::try_from_secs_f64(self.remaining_seconds()).unwrap_or(Duration::MAX)
     }
 
-    /// Reverse the playback rate so the sound plays backwards.
-    #[inline]
-    pub fn reverse(&mut self) {
-        self.playback_rate
-            .start_tween(self.playback_rate.value.reverse())
-    }
-
-    /// Add a command to the sound. See [`Command`] for more details.
-    #[inline]
-    pub fn add_command(&mut self, command: Command) {
-        self.commands.push(command)
-    }
+    /// Estimate the fundamental frequency (in Hz) of the sound, for example
+    /// to map a sample to the right key.
+    ///
+    /// Runs a normalized autocorrelation over a short window of the mono
+    /// mix, starting at the first audible frame. Finds pitches between 40 Hz
+    /// and 4 kHz at the sample rate of the sound. Returns [`None`] if the
+    /// sound is too short, silent, or not periodic enough (noise, drums).
+    ///
+    /// This is an offline analysis and is too slow for the audio thread.
+    pub fn estimate_pitch(&self) -> Option<f32> {
+        let sample_rate = self.sample_rate as f32;
+        let min_lag = (sample_rate / PITCH_RANGE.1).max(1.0) as usize;
+        let max_lag = (sample_rate / PITCH_RANGE.0) as usize;
 
-    fn update_commands(&mut self, dt: f64) {
-        self.commands.retain_mut(|command| {
-            let is_nonzero_duration = command.duration > 0.0;
-            if !is_nonzero_duration {
-                // this is on the top of this loop because the command.start_after <= check
-                // must be triggered before the stop tween check so commands with
-                // 0 duration are triggered
-                command.start_after -= dt;
-            }
+        // skip the leading silence, then take a window of a few periods of
+        // the lowest pitch
+        let peak = self
+            .frames
+            .iter()
+            .map(|frame| ((frame.left + frame.right) * 0.5).abs())
+            .fold(0.0f32, f32::max);
+        if peak <= f32::EPSILON {
+            return None;
+        }
+        let start = self
+            .frames
+            .iter()
+            .position(|frame| ((frame.left + frame.right) * 0.5).abs() > peak * 0.1)?;
+        let window: Vec<f32> = self
+            .frames
+            .iter()
+            .skip(start)
+            .take(max_lag * 3)
+            .map(|frame| (frame.left + frame.right) * 0.5)
+            .collect();
+        let max_lag = max_lag.min(window.len() / 2);
+        if max_lag <= min_lag + 1 {
+            return None;
+        }
 
-            if command.start_after <= 0.0 {
-                // compute value with easing
-                // start_after will be negative, and it counts the amount of time the sound has been running for
-                // t is a value between 0 and 1
-                let t = if is_nonzero_duration {
-                    // if we divide by 0 here we'll get a NaN
-                    command.value((-command.start_after / command.duration) as f32)
+        // normalized square difference function (McLeod), 1.0 means the
+        // window repeats exactly after `lag` frames
+        let nsdf: Vec<f32> = (0..=max_lag)
+            .map(|lag| {
+                let (mut acf, mut energy) = (0.0, 0.0);
+                for i in 0..window.len() - lag {
+                    acf += window[i] * window[i + lag];
+                    energy += window[i] * window[i] + window[i + lag] * window[i + lag];
+                }
+                if energy > 0.0 {
+                    2.0 * acf / energy
                 } else {
-                    // commands with 0 or less duration end immediately
-                    1.0
-                };
+                    0.0
+                }
+            })
+            .collect();
 
-                // apply change
-                match &command.change {
-                    Change::Volume(vol) => self.volume.update(*vol, t),
-                    Change::Index(index) => {
-                        self.index.update(*index, t);
-                        // TODO: push frame to resampler
-                    }
-                    Change::Position(position) => {
-                        let index = position * self.sample_rate as f64;
-                        self.index.update(index as usize, t);
-                        // TODO: push frame to resampler
-                    }
-                    Change::Pause(pause) => {
-                        if t >= 0.5 {
-                            self.paused = *pause;
-                        }
-                    }
-                    Change::PlaybackRate(rate) => self.playback_rate.update(*rate, t),
-                    Change::LoopSeconds(range) => self.loop_points.update(
-                        LoopPoints::from_range_secs(range.clone(), self.sample_rate),
-                        t,
-                    ),
-                    Change::LoopIndex(range) => self
-                        .loop_points
-                        .update(LoopPoints::from_range(range.clone()), t),
-                    Change::Panning(panning) => self.panning.update(*panning, t),
+        // find the highest peak of each positive lobe after the first zero
+        // crossing, and take the first one that is close to the best one,
+        // which avoids picking a multiple of the period
+        let mut peaks = Vec::new();
+        let mut lag = nsdf.iter().position(|&value| value < 0.0)?.max(min_lag);
+        while lag < max_lag {
+            if nsdf[lag] > 0.0 {
+                let lobe_start = lag;
+                while lag < max_lag && nsdf[lag] > 0.0 {
+                    lag += 1;
                 }
+                let best = (lobe_start..lag).max_by(|&a, &b| nsdf[a].total_cmp(&nsdf[b]))?;
+                peaks.push(best);
             }
+            lag += 1;
+        }
+        let best = peaks.iter().map(|&lag| nsdf[lag]).fold(0.0f32, f32::max);
+        if best < PITCH_CONFIDENCE {
+            return None;
+        }
+        let lag = *peaks.iter().find(|&&lag| nsdf[lag] >= best * 0.9)?;
 
-            if is_nonzero_duration {
-                // if start_after is negative, it measures the elapsed time the command
-                // has been running
-                command.start_after -= dt;
-            }
+        // parabolic interpolation for sub-frame precision
+        let (prev, current, next) = (nsdf[lag - 1], nsdf[lag], nsdf[lag + 1]);
+        let denominator = prev - 2.0 * current + next;
+        let offset = if denominator.abs() > f32::EPSILON {
+            0.5 * (prev - next) / denominator
+        } else {
+            0.0
+        };
+        Some(sample_rate / (lag as f32 + offset))
+    }
 
-            // if the command has finished, stop the tween
-            let is_running = -command.start_after < command.duration;
-            if !is_running {
-                match command.change {
-                    Change::Volume(_) => self.volume.stop_tween(),
-                    Change::Index(_) => self.index.stop_tween(),
-                    Change::Position(_) => self.index.stop_tween(),
-                    Change::Pause(_) => (),
-                    Change::PlaybackRate(_) => self.playback_rate.stop_tween(),
-                    Change::LoopSeconds(_) | Change::LoopIndex(_) => self.loop_points.stop_tween(),
-                    Change::Panning(_) => self.panning.stop_tween(),
-                }
-            }
-            is_running // only keep commands that are running
-        });
+    /// Return the playback rate that makes the sound last `target` when played
+    /// from start to end. The playback direction is kept.
+    ///
+    /// This changes the pitch of the sound. If `target` is zero or the sound
+    /// is empty, the current playback rate is returned.
+    pub fn playback_rate_for_duration(&self, target: Duration) -> PlaybackRate {
+        let duration = self.duration_seconds();
+        if target.is_zero() || duration == 0.0 || !duration.is_finite() {
+            return self.playback_rate.value;
+        }
+        let factor = duration / target.as_secs_f64();
+        if self.is_playing_backwards() {
+            PlaybackRate::Factor(-factor)
+        } else {
+            PlaybackRate::Factor(factor)
+        }
     }
 
-    /// Set the loop points as a frame index.
+    /// Set the playback rate so the sound lasts `target` when played from
+    /// start to end. See [`Sound::playback_rate_for_duration`]. Returns the
+    /// previous playback rate.
     #[inline]
-    pub fn set_loop_index(&mut self, loop_region: RangeInclusive<usize>) {
-        self.loop_points
-            .start_tween(LoopPoints::from_range(loop_region));
+    pub fn set_duration(&mut self, target: Duration) -> PlaybackRate {
+        self.set_playback_rate(self.playback_rate_for_duration(target))
     }
 
-    /// Set the current loop state (enabled/disabled). Return the previous loop state.
-    #[inline]
-    pub fn set_loop_enabled(&mut self, enabled: bool) -> bool {
-        let prev_enabled = self.loop_enabled;
-        self.loop_enabled = enabled;
-        prev_enabled
+    /// Return how many frames [`Sound::next_frame`] will output before the
+    /// sound finishes when rendered at `sample_rate`, assuming the playback
+    /// rate stays the same. This is useful for preallocating offline renders.
+    ///
+    /// Returns [`None`] if the sound never finishes: when looping is enabled,
+    /// the sound is paused or the playback rate is zero.
+    pub fn output_frame_count(&self, sample_rate: u32) -> Option<usize> {
+        if self.finished() {
+            return Some(0);
+        }
+        let rate = self.playback_rate.value.as_factor().abs();
+        if self.loop_enabled || self.paused || rate == 0.0 || sample_rate == 0 {
+            return None;
+        }
+
+        // amount of source frames that are left to play
+        let remaining = if self.is_playing_backwards() {
+            self.index.value + 1
+        } else {
+            self.frames.len() - self.index.value
+        };
+
+        // every output frame advances the position by `step` source frames
+        let step = (self.sample_rate as f64 / sample_rate as f64) * rate;
+        Some(((remaining as f64 - self.fractional_position) / step).ceil() as usize)
     }
 
-    /// Set the loop points as a position in seconds.
-    #[inline]
-    pub fn set_loop(&mut self, loop_region: RangeInclusive<f64>) {
-        self.loop_points =
-            Parameter::new(LoopPoints::from_range_secs(loop_region, self.sample_rate));
+    /// Push the current frame (pointed by `self.index`) to the resampler.
+    pub fn push_frame_to_resampler(&mut self) {
+        let frame_index = self.index.value;
+        let frame = self
+            .frames
+            .get(frame_index)
+            .unwrap_or(Frame::ZERO) // push silence if index is out of the range
+            .panned_with(self.pan(), self.pan_law)
+            * self.gain();
+        self.resampler.push_frame(frame, frame_index);
     }
 
-    /// Return the starting point of the loop as a frame index.
+    /// Return the gain applied to the frames pushed to the resampler.
     #[inline]
-    pub fn loop_start(&self) -> usize {
-        self.loop_points.value.start
+    fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else if self.volume_smoothing > 0.0 {
+            self.smoothed_volume * self.lfo_gain
+        } else {
+            self.volume.value * self.lfo_gain
+        }
     }
 
-    /// Return the ending point of the loop as a frame index.
+    /// Return the panning applied to the frames pushed to the resampler,
+    /// clamped to the valid range.
     #[inline]
-    pub fn loop_end(&self) -> usize {
-        self.loop_points.value.end
+    fn pan(&self) -> f32 {
+        let panning = if self.panning_smoothing > 0.0 {
+            self.smoothed_panning
+        } else {
+            self.panning.value
+        };
+        (panning + self.lfo_pan).clamp(0.0, 1.0)
     }
 
-    /// Return the starting point of the loop as seconds.
+    /// Move the smoothed volume and panning towards their targets.
     #[inline]
-    pub fn loop_start_secs(&self) -> f64 {
-        self.loop_points.value.start_secs(self.sample_rate)
+    fn update_smoothing(&mut self, sample_rate: u32) {
+        if self.volume_smoothing > 0.0 {
+            smooth(
+                &mut self.smoothed_volume,
+                self.volume.value,
+                self.volume_smoothing,
+                sample_rate,
+            );
+        }
+        if self.panning_smoothing > 0.0 {
+            smooth(
+                &mut self.smoothed_panning,
+                self.panning.value,
+                self.panning_smoothing,
+                sample_rate,
+            );
+        }
     }
 
-    /// Return the ending point of the loop as seconds.
+    /// Return whether the sound can skip rendering: it is silent, nothing
+    /// can make it audible without a call to the [`Sound`] (no commands are
+    /// running), and the resampler finished outputting the previous audio.
     #[inline]
-    pub fn loop_end_secs(&self) -> f64 {
-        self.loop_points.value.end_secs(self.sample_rate)
+    fn can_skip_silence(&self) -> bool {
+        self.gain() == 0.0
+            && self.commands.is_empty()
+            && self.automations.is_empty()
+            && self.lfos.is_empty()
+            && !self.paused
+            && self.scrub.is_none()
+            && self.outputting_silence()
     }
 
-    /// Return the current index in the source sound data. Can be modified with commands.
-    #[inline]
-    pub fn index(&self) -> usize {
-        self.index.value
+    /// Refill the resampler with the frames before the current position, so
+    /// the sound continues seamlessly after its resampler was not updated.
+    fn prime_resampler(&mut self) {
+        let index = self.index.value;
+        let backwards = self.moving_backwards();
+        for offset in (1..=4).rev() {
+            let frame_index = if backwards {
+                index.checked_add(offset)
+            } else {
+                index.checked_sub(offset)
+            };
+            match frame_index {
+                Some(frame_index) => {
+                    let frame = self
+                        .frames
+                        .get(frame_index)
+                        .unwrap_or(Frame::ZERO)
+                        .panned_with(self.pan(), self.pan_law)
+                        * self.gain();
+                    self.resampler.push_frame(frame, frame_index);
+                }
+                None => self.resampler.push_frame(Frame::ZERO, index),
+            }
+        }
     }
 
-    /// Return the current index in the source sound data. Cannot be modified with commands.
+    /// Return whether the sound is playing backward.
     #[inline]
-    pub fn base_index(&self) -> usize {
-        self.index.base_value
+    pub fn is_playing_backwards(&self) -> bool {
+        self.playback_rate.value.as_factor().is_sign_negative()
     }
 
-    /// Return whether the sound is currently outputting silence.
-    #[inline]
-    pub fn outputting_silence(&self) -> bool {
-        self.resampler.outputting_silence()
+    /// Increment/decrement the position value in the sound, pushing the
+    /// previous sound frame to the resampler.
+    pub fn update_position(&mut self) {
+        if self.paused {
+            self.resampler.push_frame(Frame::ZERO, self.index.value);
+        } else {
+            self.push_frame_to_resampler();
+            self.step_index();
+        }
     }
 
-    /// Pause the sound if it is playing. This won't cut off the audio signal, but smoothly
-    /// interpolate the last audio value to 0 for 4 frames.
+    /// Move the position one frame forward or backward.
     #[inline]
-    pub fn pause(&mut self) {
-        self.paused = true;
+    fn step_index(&mut self) {
+        if let Some(frames_left) = &mut self.frames_left {
+            *frames_left = frames_left.saturating_sub(1);
+        }
+
+        // increment/decrement index. playing backwards past the first frame
+        // moves the index past the end, which finishes the sound
+        if self.moving_backwards() {
+            self.index.value = self.index.value.checked_sub(1).unwrap_or(self.frames.len());
+        } else {
+            self.index.value += 1
+        }
     }
 
-    /// Return whether the sound is paused. This does not take the playback speed for account.
+    /// Return whether the sound has finished playback. Empty sounds and
+    /// sounds with a sample rate of 0 are always finished.
     #[inline]
-    pub fn paused(&self) -> bool {
-        self.paused
+    pub fn finished(&self) -> bool {
+        self.stopped
+            || self.frames_left == Some(0)
+            || self.index.value >= self.frames.len()
+            || self.sample_rate == 0
     }
 
-    /// Resume the sound if paused. This won't immediately start the audio signal, but smoothly
-    /// interpolate the last audio value for 4 frames.
+    /// Return whether the sound can skip interpolation: it plays forwards
+    /// at a playback rate of exactly 1.0, at the sample rate of the stream.
     #[inline]
-    pub fn resume(&mut self) {
-        self.paused = false;
+    fn is_passthrough(&self, sample_rate: u32) -> bool {
+        self.sample_rate == sample_rate
+            && self.playback_rate.value.as_factor() == 1.0
+            && !self.paused
+            && self.scrub.is_none()
+            && self.rate_automation.is_none()
     }
 
-    /// Set the audio panning.
+    /// Return how many source frames the playhead moves for one output frame
+    /// at `sample_rate`, and advance the rate automation.
+    fn rate_step(&mut self, sample_rate: u32) -> f64 {
+        let step = (self.sample_rate as f64 / sample_rate as f64)
+            * self.playback_rate.value.as_factor().abs();
+        match &mut self.rate_automation {
+            Some(automation) => {
+                // hold the last factor once all of them were used
+                let index = automation.position.min(automation.factors.len() - 1);
+                automation.position = (automation.position + 1).min(automation.factors.len());
+                step * automation.factors[index].max(0.0)
+            }
+            None => step,
+        }
+    }
+
+    /// Render the next frame. If the sound has ended, return [`None`].
     ///
-    /// * Panning of 0.0 means hard left panning
-    /// * Panning of 0.5 means center panning (default)
-    /// * Panning of 1.0 means hard right panning
+    /// If the sound plays forwards at a playback rate of exactly 1.0 and its
+    /// sample rate matches `sample_rate`, the source frames are output
+    /// without interpolation, so they are bit-exact (apart from volume and
+    /// panning).
     ///
-    /// # Returns
+    /// Silent sounds (muted or with a volume of 0) without running commands
+    /// only advance their position, which makes them a lot cheaper to
+    /// render.
     ///
-    /// Returns the previous panning value
+    /// Volume and panning are applied to the source frames before they are
+    /// resampled. Panning uses a constant-power law, by default normalized
+    /// to unity gain at the center: hard left or right silences the other
+    /// channel and boosts the panned one by 3 dB. See [`Sound::set_pan_law`].
     #[inline]
-    pub fn set_panning(&mut self, panning: f32) -> f32 {
-        let prev_panning = self.panning.value;
-        self.panning.start_tween(panning);
-        prev_panning
+    pub fn next_frame(&mut self, sample_rate: u32) -> Option<Frame> {
+        self.advance(sample_rate, true)
     }
 
-    /// Get the audio panning.
-    ///
-    /// * Panning of 0.0 means hard left panning
-    /// * Panning of 0.5 means center panning (default)
-    /// * Panning of 1.0 means hard right panning
-    ///
-    /// # Returns
-    ///
-    /// Returns the panning value
+    /// Return whether a filter with state is active, so its output is needed
+    /// even while fast-forwarding.
     #[inline]
-    pub fn panning(&mut self) -> f32 {
-        self.panning.value
+    fn filters_active(&self) -> bool {
+        self.dc_blocker.is_some()
+            || self.bass_db.value != 0.0
+            || self.treble_db.value != 0.0
+            || self.occlusion.target != 0.0
+            || self.occlusion.amount != 0.0
     }
-}
 
-/// Wraps a [`Sound`] so it can be returned to the user after `play`.
-///
-/// This type can be cheaply cloned, and it will share the same data.
-#[derive(Debug, Clone)]
-pub struct SoundHandle(Arc<Mutex<Sound>>);
+    /// Advance the sound by one frame. If `output` is false, the sound is
+    /// fast-forwarded: its state (position, loops, commands, timers and the
+    /// source frames in the resampler) changes exactly like in
+    /// [`Sound::next_frame`], but the output isn't interpolated, and silence
+    /// is returned instead. Returns [`None`] if the sound has ended.
+    pub(crate) fn advance(&mut self, sample_rate: u32, output: bool) -> Option<Frame> {
+        // wrap around before checking if the sound finished, so loops that
+        // end at the end of the sound don't finish it
+        if self.seam_preview.is_some() {
+            self.update_seam_preview();
+        } else if self.loop_enabled {
+            self.update_loop();
+        }
 
-impl From<Sound> for SoundHandle {
-    fn from(sound: Sound) -> Self {
-        Self::new(sound)
-    }
-}
+        if self.finished() {
+            return None;
+        }
+        if !self.rendered {
+            self.rendered = true;
+            self.push_event(RenderEventKind::Started);
+        }
 
-impl SoundHandle {
-    /// Make a new [`SoundHandle`] from a [`Sound`].
-    #[inline]
-    pub fn new(sound: Sound) -> Self {
-        Self(Arc::new(Mutex::new(sound)))
-    }
+        // output silence until the start delay is over. the delay is rounded
+        // to whole frames, so it is exact when the sample rates match
+        if self.start_delay * sample_rate as f64 >= 0.5 {
+            self.start_delay -= 1.0 / sample_rate as f64;
+            return Some(Frame::ZERO);
+        }
 
-    /// Lock the [`Sound`] for modification. Returns a [`MutexGuard`].
-    #[inline]
-    pub fn guard(&self) -> MutexGuard<Sound> {
-        self.0.lock()
-    }
+        let play_time_limit = self.play_time_limit();
+        if play_time_limit.is_some_and(|limit| self.play_time >= limit) {
+            self.stopped = true;
+            return None;
+        }
 
-    /// Delegate to the underlying [`Sound`].
-    #[inline]
-    pub fn loop_enabled(&self) -> bool {
-        return self.guard().loop_enabled;
-    }
-}
+        // the resampler holds the audio that was output last
+        if self
+            .resampler
+            .outputting_silence_below(self.silence_threshold)
+        {
+            self.quiet_time += 1.0 / sample_rate as f64;
+        } else {
+            self.quiet_time = 0.0;
+        }
 
-macro_rules! delegate {
-    ($($name:ident($($arg:ident: $type:ty),*) $(-> $ret:ty)?),* $(,)?) => {
-        $(
-            /// Delegate to the underlying [`Sound`]'s method.
-            #[inline]
-            pub fn $name(&self, $($arg: $type),*) $(-> $ret)? {
-                self.guard().$name($($arg),*)
-            }
-        )*
-    };
-}
+        if !self.links.is_empty() {
+            self.read_links();
+        }
 
-// best solution for now?
-impl SoundHandle {
-    delegate! {
-        sample_rate() -> u32,
-        duration() -> Duration,
-        duration_seconds() -> f64,
-        push_frame_to_resampler(),
+        // automation sets the base values of the parameters, so commands
+        // are applied on top of it
+        if !self.automations.is_empty() {
+            self.update_automations(1.0 / sample_rate as f64);
+        }
+
+        // update commands
+        if !self.commands.is_empty() {
+            self.update_commands(1.0 / sample_rate as f64);
+        }
+
+        if !self.links.is_empty() {
+            self.write_links();
+        }
+
+        if self.zero_rate_policy != ZeroRatePolicy::Hold && self.at_standstill() {
+            match self.zero_rate_policy {
+                ZeroRatePolicy::Pause => self.paused = true,
+                ZeroRatePolicy::Stop => {
+                    self.stopped = true;
+                    return None;
+                }
+                ZeroRatePolicy::Hold => (),
+            }
+        }
+        if !self.paused {
+            self.play_time += 1.0 / sample_rate as f64;
+        }
+
+        self.update_smoothing(sample_rate);
+        if !self.lfos.is_empty() {
+            self.update_lfos(1.0 / sample_rate as f64);
+        }
+
+        // silent and virtual sounds only advance their position, the
+        // resampler is primed again once they become audible
+        if self.can_skip_silence() || (self.virtual_voice && !self.paused && self.scrub.is_none()) {
+            self.skipping_silence = true;
+            self.fractional_position += self.rate_step(sample_rate);
+            while self.fractional_position >= 1.0 {
+                self.fractional_position -= 1.0;
+                self.step_index();
+            }
+            if let Some(dc_blocker) = &mut self.dc_blocker {
+                *dc_blocker = DcBlocker::default();
+            }
+            self.tone = ToneFilter::default();
+            self.occlusion.amount = self.occlusion.target;
+            self.occlusion.stages = [Frame::ZERO; 2];
+            for _ in 0..std::mem::take(&mut self.pending_wraps) {
+                self.push_event(RenderEventKind::LoopWrapped);
+            }
+            return Some(Frame::ZERO);
+        }
+        if self.skipping_silence {
+            self.skipping_silence = false;
+            self.prime_resampler();
+        }
+
+        // a loop wrap is heard once the resampler outputs the frames from
+        // after the wrap, so the playhead jumps back in the output
+        let output_index = self.resampler.current_frame_index();
+        if self.pending_wraps > 0
+            && (if self.moving_backwards() {
+                output_index > self.last_output_index
+            } else {
+                output_index < self.last_output_index
+            })
+        {
+            self.pending_wraps -= 1;
+            self.push_event(RenderEventKind::LoopWrapped);
+        }
+        self.last_output_index = output_index;
+
+        // filters need their input to keep the same state as in a full render
+        let output = output || self.filters_active();
+
+        let frame = if self.is_passthrough(sample_rate) {
+            // the sound plays at the rate of the stream, so output the
+            // source frames as they are, without interpolating them
+            let frame = self.resampler.current_frame();
+            self.fractional_position = 0.0;
+            self.update_position();
+            frame
+        } else {
+            // get resampled frame
+            let frame = if output {
+                self.resampler.get(self.fractional_position as f32)
+            } else {
+                Frame::ZERO
+            };
+
+            // increment fractional position. a paused sound fades out at the
+            // rate of the stream and keeps its position between frames, so
+            // its reported position doesn't drift
+            if self.paused {
+                self.fractional_position += 1.0;
+            } else if let Some(scrub) = self.scrub {
+                self.fractional_position += self.update_scrub(scrub.target, sample_rate);
+            } else {
+                self.fractional_position += self.rate_step(sample_rate);
+            }
+
+            // step the corrent amount of samples forward/backward
+            while self.fractional_position >= 1.0 {
+                self.fractional_position -= 1.0;
+                self.update_position();
+            }
+            frame
+        };
+
+        if !output {
+            // the tone filter is reset while it is inactive, see below
+            self.tone = ToneFilter::default();
+            return Some(Frame::ZERO);
+        }
+
+        let frame = match &mut self.dc_blocker {
+            Some(dc_blocker) => dc_blocker.process(frame, sample_rate),
+            None => frame,
+        };
+
+        let (bass_db, treble_db) = (self.bass_db.value, self.treble_db.value);
+        let frame = if bass_db == 0.0 && treble_db == 0.0 {
+            self.tone = ToneFilter::default();
+            frame
+        } else {
+            self.tone.process(frame, bass_db, treble_db, sample_rate)
+        };
+
+        let frame = if self.occlusion.target == 0.0 && self.occlusion.amount == 0.0 {
+            frame
+        } else {
+            self.occlusion
+                .process(frame, &self.occlusion_model, sample_rate)
+        };
+
+        if self.muted {
+            return Some(Frame::ZERO);
+        }
+
+        // fade out before the maximum play time, so the sound is silent when
+        // it is removed
+        match play_time_limit {
+            Some(limit) => {
+                let fade = MAX_PLAY_TIME_FADE_SECS.min(limit);
+                let gain = if fade > 0.0 {
+                    ((limit - self.play_time) / fade).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                Some(frame * gain as f32)
+            }
+            None => Some(frame),
+        }
+    }
+
+    /// Return the earliest play time at which the sound stops, from either
+    /// [`Sound::set_max_play_time`], the maximum age set by the renderer or
+    /// a stolen voice.
+    #[inline]
+    fn play_time_limit(&self) -> Option<f64> {
+        [self.max_play_time, self.max_age, self.stolen_at]
+            .into_iter()
+            .flatten()
+            .reduce(f64::min)
+    }
+
+    /// Return whether the playhead is moving backwards, taking scrubbing into
+    /// account.
+    #[inline]
+    fn moving_backwards(&self) -> bool {
+        match self.scrub {
+            Some(scrub) => scrub.backwards,
+            None => self.is_playing_backwards(),
+        }
+    }
+
+    /// Move the scrub velocity towards the target position. Returns how many
+    /// source frames the playhead should advance by for this output frame.
+    fn update_scrub(&mut self, target: f64, sample_rate: u32) -> f64 {
+        // one-pole smoothing, so the playhead glides towards the target
+        // instead of jumping to it
+        let coeff = 1.0 - (-1.0 / (SCRUB_SMOOTHING_SECS * sample_rate as f64)).exp();
+        let velocity = (target - self.index.value as f64) * coeff;
+        if let Some(scrub) = &mut self.scrub {
+            scrub.backwards = velocity < 0.0;
+        }
+        velocity.abs()
+    }
+
+    /// Return the loop region with the end clamped to the length of the
+    /// sound, or [`None`] if the region is empty or inverted.
+    #[inline]
+    fn loop_region(&self) -> Option<(usize, usize)> {
+        let LoopPoints { start, end } = self.loop_points.value;
+        let end = end.min(self.frames.len());
+        (start < end).then_some((start, end))
+    }
+
+    /// Return whether a command is currently tweening the loop points.
+    #[inline]
+    fn loop_tween_active(&self) -> bool {
+        self.commands.iter().any(|command| {
+            command.start_after <= 0.0
+                && matches!(
+                    command.change,
+                    Change::LoopSeconds(_) | Change::LoopIndex(_)
+                )
+        })
+    }
+
+    fn update_loop(&mut self) {
+        let index = self.index.value;
+        let prev_index = std::mem::replace(&mut self.loop_prev_index, index);
+
+        // empty regions (for example while loop points are tweened past
+        // each other) don't wrap
+        let Some((start, end)) = self.loop_region() else {
+            return;
+        };
+
+        // while the loop points are tweened, only wrap when the playhead
+        // crosses the loop point in its direction of travel. a loop point
+        // that moves past the playhead doesn't make it jump; if the playhead
+        // is still outside of the loop when the tween completes, the wrap
+        // happens then
+        //
+        // frames the playhead moved past the loop point are carried over,
+        // and so is the fractional position, so the loop period stays exact
+        let tweening = self.loop_tween_active();
+        let length = end - start;
+        if self.is_playing_backwards() {
+            if index <= start && (!tweening || prev_index > start) {
+                self.wrap_to_index(end - 1 - (start - index) % length);
+                self.pending_wraps += self.record_events as u32;
+            }
+        } else if index >= end && (!tweening || prev_index < end) {
+            self.wrap_to_index(start + (index - end) % length);
+            self.pending_wraps += self.record_events as u32;
+        }
+        self.loop_prev_index = self.index.value;
+    }
+
+    /// Disable looping if the loop region is empty or inverted.
+    #[inline]
+    fn validate_loop(&mut self) {
+        if self.loop_region().is_none() {
+            self.loop_enabled = false;
+        }
+    }
+
+    /// Return the loop region the seam preview cycles around, clamped so
+    /// that the loop end is never the end of the sound (which would finish
+    /// it).
+    #[inline]
+    fn seam(&self) -> (usize, usize) {
+        let end = self
+            .loop_points
+            .value
+            .end
+            .min(self.frames.len().saturating_sub(1));
+        (self.loop_points.value.start.min(end), end)
+    }
+
+    fn update_seam_preview(&mut self) {
+        let Some(mut preview) = self.seam_preview else {
+            return;
+        };
+        let (start, end) = self.seam();
+        let index = self.index.value;
+        if preview.after_seam {
+            if index >= start.saturating_add(preview.tail).min(end) {
+                preview.after_seam = false;
+                self.wrap_to_index(end.saturating_sub(preview.lead));
+            }
+        } else if index >= end {
+            preview.after_seam = true;
+            self.wrap_to_index(start);
+        }
+        self.seam_preview = Some(preview);
+    }
+
+    /// Audition the loop seam: repeatedly play the last `lead` seconds
+    /// before the loop end, followed by the first `tail` seconds after the
+    /// loop start. The loop points can be changed while previewing. The
+    /// seams go through the resampler, like regular loops, so they don't
+    /// click.
+    ///
+    /// The sound is resumed if it was paused. Call [`Sound::stop_preview`]
+    /// to return to the position (and pause state) from before the preview.
+    ///
+    /// Note: the preview always plays forwards. Loop ends past the end of
+    /// the sound are clamped to the last frame.
+    pub fn preview_loop_seam(&mut self, lead: f64, tail: f64) {
+        let preview = SeamPreview {
+            lead: secs_to_index(lead.max(0.0), self.sample_rate),
+            tail: secs_to_index(tail.max(0.0), self.sample_rate),
+            after_seam: false,
+            index: self.index.value,
+            paused: self.paused,
+        };
+        // keep the original state if a preview is already running
+        let (index, paused) = self
+            .seam_preview
+            .map_or((preview.index, preview.paused), |old| {
+                (old.index, old.paused)
+            });
+        self.seam_preview = Some(SeamPreview {
+            index,
+            paused,
+            ..preview
+        });
+
+        // start on the preview window right away, without hearing the frames
+        // from before the preview
+        self.paused = false;
+        let (_, end) = self.seam();
+        self.index.start_tween(end.saturating_sub(preview.lead));
+        self.fractional_position = 0.0;
+        self.prime_resampler();
+    }
+
+    /// Stop the loop seam preview started with [`Sound::preview_loop_seam`]
+    /// and restore the previous position and pause state. Does nothing if no
+    /// preview is running.
+    pub fn stop_preview(&mut self) {
+        let Some(preview) = self.seam_preview.take() else {
+            return;
+        };
+        self.paused = preview.paused;
+        self.seek_to_index(preview.index);
+    }
+
+    /// Return whether a loop seam preview is running. See
+    /// [`Sound::preview_loop_seam`].
+    #[inline]
+    pub fn previewing_seam(&self) -> bool {
+        self.seam_preview.is_some()
+    }
+
+    /// Advance the sound as if it was played for `duration`, without
+    /// producing any output. The sound goes through the same state as if it
+    /// was rendered at its own sample rate: commands, automations, LFOs,
+    /// loops, the start delay and the maximum play time all advance, and the
+    /// sound finishes if it would have finished while playing.
+    ///
+    /// This steps through every frame of `duration`, so it takes longer for
+    /// longer durations, but a lot less time than rendering.
+    pub fn skip(&mut self, duration: Duration) {
+        let sample_rate = self.sample_rate;
+        let frames = (duration.as_secs_f64() * sample_rate as f64).round() as u64;
+        for _ in 0..frames {
+            if self.advance(sample_rate, false).is_none() {
+                break;
+            }
+        }
+    }
+
+    /// Seek so that the next rendered frame is the frame at `seconds`, with
+    /// the resampler filled with the frames before it. Unlike
+    /// [`Sound::seek_to`], no frames from before the seek are heard.
+    pub(crate) fn cue(&mut self, seconds: f64) {
+        self.cue_index(secs_to_index(seconds, self.sample_rate));
+    }
+
+    /// Seek so that the next rendered frame is the frame at `index`. See
+    /// [`Sound::cue`].
+    fn cue_index(&mut self, index: usize) {
+        // the resampler outputs the frame that was pushed 3 frames ago
+        let index = index.saturating_add(3);
+        self.index.start_tween(index.min(self.frames.len()));
+        self.fractional_position = 0.0;
+        self.prime_resampler();
+    }
+
+    /// Shift the sound by `samples` frames (at the sample rate of the sound)
+    /// to compensate for processing latency, for example to align stems
+    /// that were recorded with different latencies. A positive offset delays
+    /// the sound by outputting silence before it starts, a negative offset
+    /// skips the first frames, as if the sound started earlier.
+    ///
+    /// The offset is applied from the current position, so it should be set
+    /// before the sound starts playing. Setting it again replaces the delay
+    /// of a positive offset, but skips further ahead for a negative one.
+    pub fn set_latency_offset(&mut self, samples: i64) {
+        self.latency_offset = samples;
+        self.start_delay = samples.max(0) as f64 / self.sample_rate as f64;
+        if samples < 0 {
+            // skip ahead of the frame that would be rendered next, with the
+            // resampler filled with the frames before it, so the sound lines
+            // up with sounds that start from the same position
+            let playhead = self.resampler.current_frame_index();
+            self.cue_index(playhead.saturating_add(samples.unsigned_abs() as usize));
+        }
+    }
+
+    /// Return the latency offset set with [`Sound::set_latency_offset`].
+    #[inline]
+    pub fn latency_offset(&self) -> i64 {
+        self.latency_offset
+    }
+
+    /// Reset the sound to the beginning. The state of the filters is
+    /// cleared, so nothing from the previous playback is heard.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.seek_to_index(0);
+        self.tone = ToneFilter::default();
+        self.occlusion.amount = self.occlusion.target;
+        self.occlusion.stages = [Frame::ZERO; 2];
+        if let Some(dc_blocker) = &mut self.dc_blocker {
+            *dc_blocker = DcBlocker::default();
+        }
+    }
+
+    /// Save the playback state of the sound: the position, volume, panning,
+    /// playback rate, loop, paused state and the pending commands. See
+    /// [`Sound::restore_playback_state`].
+    pub fn playback_state(&self) -> PlaybackState {
+        PlaybackState {
+            frame_count: self.frames.len(),
+            fingerprint: self.frames.fingerprint(),
+            sample_rate: self.sample_rate,
+            index: self.index.value,
+            fractional_position: self.fractional_position,
+            volume: self.shared(LinkedParameter::Volume, self.volume),
+            panning: self.shared(LinkedParameter::Panning, self.panning),
+            playback_rate: self.shared(LinkedParameter::PlaybackRate, self.playback_rate),
+            loop_start: self.loop_points.value.start,
+            loop_end: self.loop_points.value.end,
+            loop_enabled: self.loop_enabled,
+            paused: self.paused,
+            frames_left: self.frames_left,
+            commands: self.commands.clone(),
+            command_time: self.command_time,
+        }
+    }
+
+    /// Restore a playback state saved with [`Sound::playback_state`]. The
+    /// resampler is filled with the frames before the saved position, so
+    /// playback continues without a click. The state of the filters is
+    /// cleared, like in [`Sound::reset`].
+    ///
+    /// Returns [`KaError::PlaybackStateMismatch`] if the state was saved
+    /// from a sound with different audio data. The sound is not changed in
+    /// that case.
+    pub fn restore_playback_state(&mut self, state: &PlaybackState) -> Result<(), KaError> {
+        if state.frame_count != self.frames.len() || state.fingerprint != self.frames.fingerprint()
+        {
+            return Err(KaError::PlaybackStateMismatch);
+        }
+
+        self.sample_rate = state.sample_rate;
+        self.volume = state.volume;
+        self.panning = state.panning;
+        self.playback_rate = state.playback_rate;
+        self.write_link(LinkedParameter::Volume);
+        self.write_link(LinkedParameter::Panning);
+        self.write_link(LinkedParameter::PlaybackRate);
+        self.smoothed_volume = self.volume.value;
+        self.smoothed_panning = self.panning.value;
+        self.loop_points = Parameter::new(LoopPoints {
+            start: state.loop_start,
+            end: state.loop_end,
+        });
+        self.loop_enabled = state.loop_enabled;
+        self.validate_loop();
+        self.paused = state.paused;
+        self.stopped = false;
+        self.frames_left = state.frames_left;
+        self.commands.clear();
+        for command in &state.commands {
+            self.add_command(command.clone());
+        }
+        self.command_time = state.command_time;
+
+        self.reset();
+        self.index = Parameter::new(state.index.min(self.frames.len()));
+        self.loop_prev_index = self.index.value;
+        self.fractional_position = state.fractional_position;
+        if self.paused {
+            // like a seek while paused, resuming fades in from the position
+            self.resampler = Resampler::new(self.index.value);
+        } else {
+            self.prime_resampler();
+        }
+        Ok(())
+    }
+
+    /// Restart the sound from the beginning, like [`Sound::reset`], and
+    /// rewind or clear its commands, so a restarted sound replays its
+    /// automation from the top. See [`RestartCommands`].
+    ///
+    /// When rearming, every pending or running command is moved back by the
+    /// time that passed since the last restart. Automation curves that
+    /// follow the render time are restarted as well.
+    pub fn restart(&mut self, commands: RestartCommands) {
+        self.reset();
+        match commands {
+            RestartCommands::Rearm => {
+                let mut commands = std::mem::take(&mut self.commands);
+                for command in &mut commands {
+                    if command.start_after <= 0.0 {
+                        // go back to the value from before the command started
+                        self.rewind_tween(&command.change);
+                    }
+                    command.start_after += self.command_time;
+                }
+                self.commands = commands;
+                for automation in &mut self.automations {
+                    automation.elapsed = 0.0;
+                }
+                for lfo in &mut self.lfos {
+                    lfo.elapsed = 0.0;
+                }
+                if let Some(automation) = &mut self.rate_automation {
+                    automation.position = 0;
+                }
+            }
+            RestartCommands::Clear => {
+                for command in std::mem::take(&mut self.commands) {
+                    if command.start_after <= 0.0 {
+                        self.stop_shared_tween(&command.change);
+                    }
+                }
+            }
+        }
+        self.command_time = 0.0;
+    }
+
+    /// Set the playback rate of the sound. See [`PlaybackRate`] for more
+    /// details. Returns the previous playback rate.
+    #[inline]
+    pub fn set_playback_rate(&mut self, playback_rate: PlaybackRate) -> PlaybackRate {
+        let prev_playback_rate = self.playback_rate.value;
+        self.playback_rate.start_tween(playback_rate);
+        self.write_link(LinkedParameter::PlaybackRate);
+        prev_playback_rate
+    }
+
+    /// Return the current playback rate value. Can be modified with commands.
+    #[inline]
+    pub fn playback_rate(&self) -> PlaybackRate {
+        self.shared(LinkedParameter::PlaybackRate, self.playback_rate)
+            .value
+    }
+
+    /// Return the current base playback rate value. Can't be modified with commands.
+    #[inline]
+    pub fn base_playback_rate(&self) -> PlaybackRate {
+        self.shared(LinkedParameter::PlaybackRate, self.playback_rate)
+            .base_value
+    }
+
+    /// Set the current volume. Return the previous volume value.
+    #[inline]
+    pub fn set_volume(&mut self, volume: f32) -> f32 {
+        let prev_volume = self.volume.value;
+        self.volume.start_tween(volume);
+        self.write_link(LinkedParameter::Volume);
+        prev_volume
+    }
+
+    /// Return the current volume value. Can be modified with commands.
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        self.shared(LinkedParameter::Volume, self.volume).value
+    }
+
+    /// Smooth all volume changes with a one-pole filter with the given time
+    /// constant, so calling [`Sound::set_volume`] rapidly (for example from
+    /// a UI slider) ramps the volume instead of stepping it, which avoids
+    /// zipper noise. A few milliseconds are usually enough. If zero (the
+    /// default), the volume is not smoothed.
+    ///
+    /// Note: this also delays volume changes made by commands.
+    #[inline]
+    pub fn set_volume_smoothing(&mut self, time: Duration) {
+        if self.volume_smoothing == 0.0 {
+            // start from the current volume instead of fading in
+            self.smoothed_volume = self.volume.value;
+        }
+        self.volume_smoothing = time.as_secs_f32();
+    }
+
+    /// Return the time constant of the volume smoother. See
+    /// [`Sound::set_volume_smoothing`].
+    #[inline]
+    pub fn volume_smoothing(&self) -> Duration {
+        Duration::from_secs_f32(self.volume_smoothing)
+    }
+
+    /// Smooth all panning changes with a one-pole filter with the given time
+    /// constant, like [`Sound::set_volume_smoothing`]. If zero (the
+    /// default), the panning is not smoothed.
+    ///
+    /// Note: this also delays panning changes made by commands.
+    pub fn set_panning_smoothing(&mut self, time: Duration) {
+        if self.panning_smoothing == 0.0 {
+            // start from the current panning instead of sweeping from center
+            self.smoothed_panning = self.panning.value;
+        }
+        self.panning_smoothing = time.as_secs_f32();
+    }
+
+    /// Return the time constant of the panning smoother. See
+    /// [`Sound::set_panning_smoothing`].
+    #[inline]
+    pub fn panning_smoothing(&self) -> Duration {
+        Duration::from_secs_f32(self.panning_smoothing)
+    }
+
+    /// Return the current base volume value. Can't be modified with commands.
+    #[inline]
+    pub fn base_volume(&self) -> f32 {
+        self.shared(LinkedParameter::Volume, self.volume).base_value
+    }
+
+    /// Seek to an index in the source data. The index is clamped to the
+    /// length of the sound.
+    ///
+    /// If the sound is paused, the audio from before the seek is dropped, so
+    /// resuming fades in from the new position instead of interpolating
+    /// across the jump. The position between frames is reset, so seeking
+    /// to an index always starts exactly on that frame. Does nothing for
+    /// sounds without audio.
+    #[inline]
+    pub fn seek_to_index(&mut self, index: usize) {
+        // there is nothing to seek in a sound without audio
+        if !self.has_audio() {
+            return;
+        }
+        self.wrap_to_index(index);
+        self.fractional_position = 0.0;
+    }
+
+    /// Move the playhead to an index without resetting the position between
+    /// frames, used for loop wraps. See [`Sound::seek_to_index`].
+    #[inline]
+    fn wrap_to_index(&mut self, index: usize) {
+        self.index.start_tween(index.min(self.frames.len()));
+
+        // if the sound is playing, push this frame to the resampler so it
+        // doesn't get skipped. if it's paused, drop the frames from before
+        // the seek, so resuming fades in from the new position
+        if self.paused {
+            self.resampler = Resampler::new(self.index.value);
+            self.fractional_position = 0.0;
+        } else {
+            self.push_frame_to_resampler();
+        }
+    }
+
+    /// Seek to the end of the sound.
+    #[inline]
+    pub fn seek_to_end(&mut self) {
+        self.seek_to_index(self.frames.len().saturating_sub(1));
+    }
+
+    /// Seek by a specified amount of frames. Negative values seek backwards.
+    /// The resulting index is clamped to the length of the sound.
+    #[inline]
+    pub fn seek_by_frames(&mut self, frames: i64) {
+        let index = if frames.is_negative() {
+            self.index
+                .value
+                .saturating_sub(frames.unsigned_abs().try_into().unwrap_or(usize::MAX))
+        } else {
+            self.index
+                .value
+                .saturating_add(frames.try_into().unwrap_or(usize::MAX))
+        };
+        self.seek_to_index(index);
+    }
+
+    /// Seek by a specified amount of seconds. Negative values seek backwards.
+    /// The resulting index is clamped to the length of the sound.
+    #[inline]
+    pub fn seek_by(&mut self, seconds: f64) {
+        let cur_position = self.index.value as f64 / self.sample_rate as f64;
+        self.seek_to(cur_position + seconds);
+    }
+
+    /// Seek to a specified position in seconds. The resulting index is
+    /// clamped to the length of the sound.
+    #[inline]
+    pub fn seek_to(&mut self, seconds: f64) {
+        self.seek_to_index(secs_to_index(seconds, self.sample_rate));
+    }
+
+    /// Reverse the playback rate so the sound plays backwards.
+    #[inline]
+    pub fn reverse(&mut self) {
+        self.playback_rate
+            .start_tween(self.playback_rate.value.reverse())
+    }
+
+    /// Set whether the sound plays backwards, keeping the speed of the
+    /// playback rate. The position is kept; use [`Sound::play_reversed`]
+    /// to play backwards from the end.
+    #[inline]
+    pub fn set_reversed(&mut self, reversed: bool) {
+        let factor = self.playback_rate.value.as_factor().abs();
+        self.playback_rate
+            .start_tween(PlaybackRate::Factor(if reversed {
+                -factor
+            } else {
+                factor
+            }));
+    }
+
+    /// Play the sound backwards, starting from the last frame. The next
+    /// rendered frame is the last frame of the sound, and the sound
+    /// finishes when the playhead reaches the first frame, without
+    /// underflowing the position.
+    pub fn play_reversed(&mut self) {
+        self.set_reversed(true);
+        // the resampler outputs the frame that was pushed 3 frames ago
+        self.index.start_tween(self.frames.len().saturating_sub(4));
+        self.fractional_position = 0.0;
+        self.prime_resampler();
+    }
+
+    /// Add a command to the sound. See [`Command`] for more details.
+    ///
+    /// The command is not validated, commands with a negative or NaN
+    /// duration are applied instantly. Use [`Sound::try_add_command`] for
+    /// commands from untrusted data.
+    #[inline]
+    pub fn add_command(&mut self, command: Command) {
+        self.commands.push(command);
+    }
+
+    /// Add a command to the sound if it is valid, see [`Command::validate`].
+    /// Commands that start later than the maximum command delay are
+    /// rejected, see [`Sound::set_max_command_delay`].
+    #[inline]
+    pub fn try_add_command(&mut self, command: Command) -> Result<(), KaError> {
+        command.validate(self.max_command_delay)?;
+        self.add_command(command);
+        Ok(())
+    }
+
+    /// Set how far ahead commands added with [`Sound::try_add_command`] can
+    /// be scheduled. If [`None`] (default), there is no limit.
+    #[inline]
+    pub fn set_max_command_delay(&mut self, delay: Option<Duration>) {
+        self.max_command_delay = delay.map(|delay| delay.as_secs_f64());
+    }
+
+    /// Return how far ahead commands can be scheduled. See
+    /// [`Sound::set_max_command_delay`].
+    #[inline]
+    pub fn max_command_delay(&self) -> Option<Duration> {
+        self.max_command_delay.map(Duration::from_secs_f64)
+    }
+
+    fn update_commands(&mut self, dt: f64) {
+        self.command_time += dt;
+        // take the commands out, so finished commands can stop their tweens
+        let mut commands = std::mem::take(&mut self.commands);
+        commands.retain_mut(|command| {
+            let is_nonzero_duration = command.duration > 0.0;
+            if !is_nonzero_duration {
+                // this is on the top of this loop because the command.start_after <= check
+                // must be triggered before the stop tween check so commands with
+                // 0 duration are triggered
+                command.start_after -= dt;
+            }
+
+            if command.start_after <= 0.0 {
+                // compute value with easing
+                // start_after will be negative, and it counts the amount of time the sound has been running for
+                // t is a value between 0 and 1
+                let t = if is_nonzero_duration {
+                    // if we divide by 0 here we'll get a NaN
+                    command.value((-command.start_after / command.duration) as f32)
+                } else {
+                    // commands with 0 or less duration end immediately
+                    1.0
+                };
+                // overshooting seeks could move the playhead past the end of
+                // the sound and finish it, so those are always clamped
+                let t = if command.clamped
+                    || matches!(command.change, Change::Index(_) | Change::Position(_))
+                {
+                    t.clamp(0.0, 1.0)
+                } else {
+                    t
+                };
+
+                // apply change
+                match &command.change {
+                    Change::Volume(vol) => self.volume.update(*vol, t),
+                    Change::Index(index) => {
+                        self.index.update(*index, t);
+                        // TODO: push frame to resampler
+                    }
+                    Change::Position(position) => {
+                        let index = secs_to_index(*position, self.sample_rate);
+                        self.index.update(index, t);
+                        // TODO: push frame to resampler
+                    }
+                    Change::Pause(pause) => {
+                        if t >= 0.5 {
+                            self.paused = *pause;
+                        }
+                    }
+                    Change::PlaybackRate(rate) => self.playback_rate.update(*rate, t),
+                    Change::LoopSeconds(range) => self.loop_points.update(
+                        LoopPoints::from_range_secs(range.clone(), self.sample_rate),
+                        t,
+                    ),
+                    Change::LoopIndex(range) => self
+                        .loop_points
+                        .update(LoopPoints::from_range(range.clone()), t),
+                    Change::Panning(panning) => self.panning.update(*panning, t),
+                    Change::BassDb(db) => self.bass_db.update(*db, t),
+                    Change::TrebleDb(db) => self.treble_db.update(*db, t),
+                    Change::Stop => {
+                        if t >= 0.5 {
+                            self.stopped = true;
+                        }
+                    }
+                }
+            }
+
+            if is_nonzero_duration {
+                // if start_after is negative, it measures the elapsed time the command
+                // has been running
+                command.start_after -= dt;
+            }
+
+            // if the command has finished, stop the tween
+            let is_running = -command.start_after < command.duration;
+            if !is_running {
+                self.stop_tween(&command.change);
+                self.push_event(RenderEventKind::CommandFinished(command.change.clone()));
+            }
+            is_running // only keep commands that are running
+        });
+        if commands.is_empty() {
+            self.command_time = 0.0;
+        }
+        self.commands = commands;
+    }
+
+    /// Stop the tween of the parameter that is changed by `change`, keeping
+    /// its current value.
+    #[inline]
+    fn stop_tween(&mut self, change: &Change) {
+        match change {
+            Change::Volume(_) => self.volume.stop_tween(),
+            Change::Index(_) => self.index.stop_tween(),
+            Change::Position(_) => self.index.stop_tween(),
+            Change::Pause(_) => (),
+            Change::PlaybackRate(_) => self.playback_rate.stop_tween(),
+            Change::LoopSeconds(_) | Change::LoopIndex(_) => self.loop_points.stop_tween(),
+            Change::Panning(_) => self.panning.stop_tween(),
+            Change::BassDb(_) => self.bass_db.stop_tween(),
+            Change::TrebleDb(_) => self.treble_db.stop_tween(),
+            Change::Stop => (),
+        }
+    }
+
+    /// Stop the tween of the parameter that is changed by `change` between
+    /// frames, sharing the kept value with linked sounds.
+    fn stop_shared_tween(&mut self, change: &Change) {
+        self.stop_tween(change);
+        match change {
+            Change::Volume(_) => self.write_link(LinkedParameter::Volume),
+            Change::PlaybackRate(_) => self.write_link(LinkedParameter::PlaybackRate),
+            Change::Panning(_) => self.write_link(LinkedParameter::Panning),
+            _ => (),
+        }
+    }
+
+    /// Set the parameter that is changed by `change` back to the value it
+    /// had before its tween started.
+    fn rewind_tween(&mut self, change: &Change) {
+        match change {
+            Change::Volume(_) => {
+                self.volume.value = self.volume.base_value;
+                self.write_link(LinkedParameter::Volume);
+            }
+            Change::PlaybackRate(_) => {
+                self.playback_rate.value = self.playback_rate.base_value;
+                self.write_link(LinkedParameter::PlaybackRate);
+            }
+            Change::LoopSeconds(_) | Change::LoopIndex(_) => {
+                self.loop_points.value = self.loop_points.base_value
+            }
+            Change::Panning(_) => {
+                self.panning.value = self.panning.base_value;
+                self.write_link(LinkedParameter::Panning);
+            }
+            Change::BassDb(_) => self.bass_db.value = self.bass_db.base_value,
+            Change::TrebleDb(_) => self.treble_db.value = self.treble_db.base_value,
+            // the position was reset by the restart
+            Change::Index(_) | Change::Position(_) | Change::Pause(_) | Change::Stop => (),
+        }
+    }
+
+    /// Remove all commands in an automation lane. See [`Command::lane`].
+    /// Parameters that were being tweened by the removed commands keep
+    /// their current value. Commands in other lanes keep running.
+    pub fn cancel_lane(&mut self, lane: u32) {
+        let mut i = 0;
+        while i < self.commands.len() {
+            if self.commands[i].lane != Some(lane) {
+                i += 1;
+                continue;
+            }
+            let command = self.commands.remove(i);
+            if command.start_after <= 0.0 {
+                self.stop_shared_tween(&command.change);
+            }
+        }
+    }
+
+    /// Replace all commands in an automation lane with `commands`. The new
+    /// commands are put into the lane. See [`Sound::cancel_lane`].
+    pub fn replace_lane(&mut self, lane: u32, commands: Vec<Command>) {
+        self.cancel_lane(lane);
+        self.commands
+            .extend(commands.into_iter().map(|command| command.with_lane(lane)));
+    }
+
+    /// Return whether an automation lane has any unfinished commands.
+    #[inline]
+    pub fn has_lane(&self, lane: u32) -> bool {
+        self.commands
+            .iter()
+            .any(|command| command.lane == Some(lane))
+    }
+
+    /// Enable or disable the DC blocker, a highpass filter that removes DC
+    /// offset from the sound, so it doesn't waste headroom or thump when the
+    /// sound starts or stops. Disabled by default.
+    #[inline]
+    pub fn set_dc_block(&mut self, enabled: bool) {
+        if enabled != self.dc_blocker.is_some() {
+            self.dc_blocker = enabled.then(DcBlocker::default);
+        }
+    }
+
+    /// Return whether the DC blocker is enabled. See [`Sound::set_dc_block`].
+    #[inline]
+    pub fn dc_block(&self) -> bool {
+        self.dc_blocker.is_some()
+    }
+
+    /// Set a marker at a position (in seconds) in the sound. Markers can be
+    /// used as sync points, see [`crate::TransitionSync::AtMarker`]. If a
+    /// marker with the same id exists, it is moved.
+    pub fn set_marker(&mut self, id: u32, seconds: f64) {
+        let index = secs_to_index(seconds, self.sample_rate);
+        match self.markers.iter_mut().find(|(marker, _)| *marker == id) {
+            Some((_, marker_index)) => *marker_index = index,
+            None => self.markers.push((id, index)),
+        }
+    }
+
+    /// Return the position (in seconds) of a marker, or [`None`] if the
+    /// marker doesn't exist. See [`Sound::set_marker`].
+    #[inline]
+    pub fn marker(&self, id: u32) -> Option<f64> {
+        self.markers
+            .iter()
+            .find(|(marker, _)| *marker == id)
+            .map(|(_, index)| *index as f64 / self.sample_rate as f64)
+    }
+
+    /// Remove a marker. See [`Sound::set_marker`].
+    #[inline]
+    pub fn remove_marker(&mut self, id: u32) {
+        self.markers.retain(|(marker, _)| *marker != id);
+    }
+
+    /// Return the current value of an automatable parameter.
+    fn automated_value(&self, target: AutomationTarget) -> f64 {
+        match target {
+            AutomationTarget::Volume => self.volume.value as f64,
+            AutomationTarget::Panning => self.panning.value as f64,
+            AutomationTarget::PlaybackRate => self.playback_rate.value.as_factor(),
+            AutomationTarget::Pitch => self.playback_rate.value.as_semitones(),
+        }
+    }
+
+    /// Set the value of an automatable parameter.
+    fn set_automated_value(&mut self, target: AutomationTarget, value: f64) {
+        match target {
+            AutomationTarget::Volume => {
+                self.volume.start_tween(value as f32);
+                self.write_link(LinkedParameter::Volume);
+            }
+            AutomationTarget::Panning => {
+                self.panning.start_tween(value as f32);
+                self.write_link(LinkedParameter::Panning);
+            }
+            AutomationTarget::PlaybackRate => {
+                self.playback_rate.start_tween(PlaybackRate::Factor(value));
+                self.write_link(LinkedParameter::PlaybackRate);
+            }
+            AutomationTarget::Pitch => {
+                self.playback_rate
+                    .start_tween(PlaybackRate::Semitones(value));
+                self.write_link(LinkedParameter::PlaybackRate);
+            }
+        }
+    }
+
+    /// Read a parameter from the link if it is linked, so the value is up to
+    /// date even if this sound wasn't rendered since it changed.
+    #[inline]
+    fn shared<T: LinkValue>(&self, parameter: LinkedParameter, own: Parameter<T>) -> Parameter<T> {
+        match self.links.iter().find(|slot| slot.parameter == parameter) {
+            Some(slot) => T::decode(slot.link.next.read().0),
+            None => own,
+        }
+    }
+
+    /// Write a parameter that was set on this sound to its link, so the
+    /// linked sounds use it from the next frame on.
+    #[inline]
+    fn write_link(&mut self, parameter: LinkedParameter) {
+        let words = parameter.encode(self);
+        if let Some(slot) = self
+            .links
+            .iter_mut()
+            .find(|slot| slot.parameter == parameter)
+        {
+            slot.version = slot.link.next.write(words);
+            slot.words = words;
+        }
+    }
+
+    /// Read the shared parameters at the start of a frame. The first linked
+    /// sound that renders a frame commits the values written in the
+    /// previous frame: a sound that reads the same generation twice knows a
+    /// new frame started.
+    fn read_links(&mut self) {
+        let mut links = std::mem::take(&mut self.links);
+        for slot in &mut links {
+            let link = &slot.link;
+            let generation = link.generation.load(Ordering::Acquire);
+            if slot.seen == generation
+                && link
+                    .generation
+                    .compare_exchange(
+                        generation,
+                        generation + 1,
+                        Ordering::AcqRel,
+                        Ordering::Acquire,
+                    )
+                    .is_ok()
+            {
+                link.current.write(link.next.read().0);
+            }
+            slot.seen = link.generation.load(Ordering::Acquire);
+            slot.words = link.current.read().0;
+            slot.version = link.next.read().1;
+            slot.parameter.decode(slot.words, self);
+        }
+        self.links = links;
+    }
+
+    /// Write the shared parameters that commands or automation changed in
+    /// this frame. If a linked sound wrote the value since this sound read
+    /// it, that value wins. The change is used from the next frame, so this
+    /// sound keeps the value the other linked sounds use in this frame.
+    fn write_links(&mut self) {
+        let mut links = std::mem::take(&mut self.links);
+        for slot in &mut links {
+            let words = slot.parameter.encode(self);
+            if words == slot.words {
+                continue;
+            }
+            if let Some(version) = slot.link.next.write_if(slot.version, words) {
+                slot.version = version;
+            }
+            slot.parameter.decode(slot.words, self);
+        }
+        self.links = links;
+    }
+
+    /// Return the link of a parameter, making a new one from the current
+    /// value of the parameter if it isn't linked yet.
+    fn link_of(&mut self, parameter: LinkedParameter) -> Arc<ParameterLink> {
+        if let Some(slot) = self.links.iter().find(|slot| slot.parameter == parameter) {
+            return slot.link.clone();
+        }
+        let words = parameter.encode(self);
+        let link = Arc::new(ParameterLink::new(words));
+        self.links.push(LinkSlot {
+            parameter,
+            link: link.clone(),
+            seen: 0,
+            words,
+            version: link.next.read().1,
+        });
+        link
+    }
+
+    /// Share a parameter through `link`, replacing any previous link of the
+    /// parameter.
+    fn join_link(&mut self, parameter: LinkedParameter, link: Arc<ParameterLink>) {
+        self.unlink(parameter);
+        let (words, version) = link.next.read();
+        parameter.decode(words, self);
+        self.links.push(LinkSlot {
+            parameter,
+            link,
+            seen: 0,
+            words,
+            version,
+        });
+    }
+
+    /// Stop sharing a parameter with other sounds, see
+    /// [`SoundHandle::link`]. The parameter keeps the current shared value.
+    /// Commands that tween it keep running on the sound they were added
+    /// to, so they only change that sound from now on.
+    pub fn unlink(&mut self, parameter: LinkedParameter) {
+        let Some(index) = self
+            .links
+            .iter()
+            .position(|slot| slot.parameter == parameter)
+        else {
+            return;
+        };
+        let slot = self.links.remove(index);
+        let words = slot.link.next.read().0;
+        match parameter {
+            LinkedParameter::Volume => self.volume = Parameter::new(f32::decode(words).value),
+            LinkedParameter::Panning => self.panning = Parameter::new(f32::decode(words).value),
+            LinkedParameter::PlaybackRate => {
+                self.playback_rate = Parameter::new(PlaybackRate::decode(words).value)
+            }
+        }
+    }
+
+    /// Return whether a parameter is shared with other sounds, see
+    /// [`SoundHandle::link`].
+    #[inline]
+    pub fn is_linked(&self, parameter: LinkedParameter) -> bool {
+        self.links.iter().any(|slot| slot.parameter == parameter)
+    }
+
+    /// Apply all automation curves.
+    fn update_automations(&mut self, dt: f64) {
+        let position = self.position_seconds();
+        for i in 0..self.automations.len() {
+            let automation = &mut self.automations[i];
+            automation.elapsed += dt;
+            let time = match automation.time {
+                AutomationTime::SoundPosition => position,
+                AutomationTime::RenderTime => automation.elapsed,
+            };
+            let Some(value) = automation.curve.value(time) else {
+                continue;
+            };
+
+            // fade in from the value the parameter had before the curve
+            let fade = (automation.elapsed / AUTOMATION_FADE_SECS).min(1.0);
+            let value = lerp_f64(automation.fade_from, value, fade);
+            let target = automation.target;
+            self.set_automated_value(target, value);
+        }
+    }
+
+    /// Control a parameter with an [`AutomationCurve`]. The curve is
+    /// evaluated every frame, at the position of the sound or at the time
+    /// since the curve was set, depending on `time`.
+    ///
+    /// The curve replaces any curve that controls the same parameter, and
+    /// fades in from the current value of the parameter over a few
+    /// milliseconds, so the parameter doesn't jump. Commands are applied on
+    /// top of the automated value.
+    ///
+    /// Note: [`AutomationTarget::PlaybackRate`] and [`AutomationTarget::Pitch`]
+    /// both control the playback rate. If both are set, the one that was set
+    /// last wins.
+    pub fn set_automation(
+        &mut self,
+        target: AutomationTarget,
+        curve: AutomationCurve,
+        time: AutomationTime,
+    ) {
+        let fade_from = self.automated_value(target);
+        self.automations
+            .retain(|automation| automation.target != target);
+        self.automations.push(Automation {
+            target,
+            curve,
+            time,
+            elapsed: 0.0,
+            fade_from,
+        });
+    }
+
+    /// Advance the LFOs by `dt` seconds and update the modulation they apply.
+    fn update_lfos(&mut self, dt: f64) {
+        self.lfo_gain = 1.0;
+        self.lfo_pan = 0.0;
+        for active in &mut self.lfos {
+            let value = active.lfo.value(active.elapsed) as f32;
+            let depth = active.lfo.depth.clamp(0.0, 1.0);
+            active.elapsed += dt;
+            match active.target {
+                // 1.0 at the top of the cycle, 1.0 - depth at the bottom
+                LfoTarget::Volume => self.lfo_gain = 1.0 - depth * (1.0 - value) * 0.5,
+                LfoTarget::Panning => self.lfo_pan = depth * value * 0.5,
+            }
+        }
+    }
+
+    /// Modulate the volume (tremolo) or the panning (auto-pan) of the sound
+    /// with an [`Lfo`]. The LFO replaces any LFO that modulates the same
+    /// parameter, and starts at its phase on the next rendered frame.
+    ///
+    /// The modulation is applied on top of the value set by commands and
+    /// automation, without changing it. See [`LfoTarget`] for how the depth
+    /// is applied.
+    pub fn set_lfo(&mut self, target: LfoTarget, lfo: Lfo) {
+        let active = ActiveLfo {
+            target,
+            lfo,
+            elapsed: 0.0,
+        };
+        match self.lfos.iter_mut().find(|active| active.target == target) {
+            Some(existing) => *existing = active,
+            None => self.lfos.push(active),
+        }
+    }
+
+    /// Stop modulating a parameter with an [`Lfo`].
+    #[inline]
+    pub fn remove_lfo(&mut self, target: LfoTarget) {
+        self.lfos.retain(|active| active.target != target);
+        match target {
+            LfoTarget::Volume => self.lfo_gain = 1.0,
+            LfoTarget::Panning => self.lfo_pan = 0.0,
+        }
+    }
+
+    /// Return the [`Lfo`] that modulates a parameter, if there is one.
+    #[inline]
+    pub fn lfo(&self, target: LfoTarget) -> Option<Lfo> {
+        self.lfos
+            .iter()
+            .find(|active| active.target == target)
+            .map(|active| active.lfo)
+    }
+
+    /// Stop automating a parameter. The parameter keeps its current value.
+    #[inline]
+    pub fn remove_automation(&mut self, target: AutomationTarget) {
+        self.automations
+            .retain(|automation| automation.target != target);
+    }
+
+    /// Multiply the playback rate by a factor per output frame: the first
+    /// rendered frame uses `factors[0]`, the next one `factors[1]`, and so
+    /// on. The last factor is held until [`Sound::clear_rate_automation`] is
+    /// called. Negative factors are treated as 0.
+    ///
+    /// Unlike commands, which are timed in seconds, this is sample exact, so
+    /// it can be used for precomputed glissandi and sweeps. The factors apply
+    /// on top of the playback rate, its commands and its automation. An empty
+    /// slice clears the rate automation.
+    pub fn set_rate_automation(&mut self, factors: &[f64]) {
+        self.rate_automation = (!factors.is_empty()).then(|| RateAutomation {
+            factors: factors.into(),
+            position: 0,
+        });
+    }
+
+    /// Stop multiplying the playback rate by the factors set with
+    /// [`Sound::set_rate_automation`].
+    #[inline]
+    pub fn clear_rate_automation(&mut self) {
+        self.rate_automation = None;
+    }
+
+    /// Return the number of rate automation factors that were not applied
+    /// yet, or [`None`] if there is no rate automation.
+    #[inline]
+    pub fn rate_automation_remaining(&self) -> Option<usize> {
+        self.rate_automation
+            .as_ref()
+            .map(|automation| automation.factors.len() - automation.position)
+    }
+
+    /// Return whether a parameter is controlled by an [`AutomationCurve`].
+    #[inline]
+    pub fn has_automation(&self, target: AutomationTarget) -> bool {
+        self.automations
+            .iter()
+            .any(|automation| automation.target == target)
+    }
+
+    /// Return a copy of the sound with its volume and panning commands baked
+    /// into the frame data, so they don't have to be evaluated while playing.
+    /// The other commands are kept.
+    ///
+    /// `sample_rate` is the sample rate the sound will be played at. The
+    /// commands are evaluated once per output frame, like in
+    /// [`Sound::next_frame`], assuming the sound plays without pauses and
+    /// without changing its playback rate. Frames behind the playhead get the
+    /// current volume and panning. The returned sound has a volume of 1.0
+    /// and center panning, as those are now part of the frame data.
+    pub fn bake_commands(&self, sample_rate: u32) -> Sound {
+        let is_bakeable = |c: &Command| matches!(c.change, Change::Volume(_) | Change::Panning(_));
+
+        // only simulate the commands that are baked
+        let mut sim = self.clone();
+        sim.commands.retain(is_bakeable);
+        let bake = |frame: &mut Frame, sim: &Sound| {
+            *frame = frame.panned_with(sim.panning.value.max(0.0), sim.pan_law) * sim.volume.value;
+        };
+
+        let mut frames = self.frames.to_vec();
+        let index = self.index.value.min(frames.len());
+        let backwards = self.is_playing_backwards();
+
+        // frames behind the playhead
+        let behind = if backwards {
+            &mut frames[(index + 1).min(self.frames.len())..]
+        } else {
+            &mut frames[..index]
+        };
+        behind.iter_mut().for_each(|frame| bake(frame, &sim));
+
+        // frames in front of the playhead, in the order they will be played
+        let ahead: Vec<usize> = if backwards {
+            (0..=index.min(frames.len().saturating_sub(1)))
+                .rev()
+                .collect()
+        } else {
+            (index..frames.len()).collect()
+        };
+        let step = (self.sample_rate as f64 / sample_rate as f64)
+            * self.playback_rate.value.as_factor().abs();
+        let mut position = 0.0;
+        let mut baked = 0;
+        while baked < ahead.len() {
+            if sim.commands.is_empty() || step <= 0.0 || !step.is_finite() {
+                // values won't change anymore
+                for &i in &ahead[baked..] {
+                    bake(&mut frames[i], &sim);
+                }
+                break;
+            }
+
+            sim.update_commands(1.0 / sample_rate as f64);
+            position += step;
+            while baked < ahead.len() && (baked as f64) < position {
+                bake(&mut frames[ahead[baked]], &sim);
+                baked += 1;
+            }
+        }
+
+        let mut sound = self.clone();
+        sound.frames = frames.into();
+        sound.commands.retain(|c| !is_bakeable(c));
+        sound.volume = Parameter::new(1.0);
+        sound.panning = Parameter::new(0.5);
+        sound
+    }
+
+    /// Return a copy of the sound with its pitch shifted by `semitones`,
+    /// while keeping its duration. Useful for making variations of sound
+    /// effects ahead of time, instead of changing the playback rate while
+    /// playing.
+    ///
+    /// The sound is resampled to the new pitch, then stretched back to its
+    /// length with overlapping grains, so large shifts can sound grainy. The
+    /// sample rate and the loop points stay the same. This is an offline
+    /// operation and is too slow for the audio thread.
+    pub fn pitch_shifted(&self, semitones: f64) -> Sound {
+        if semitones == 0.0 || !semitones.is_finite() || self.frames.is_empty() {
+            return self.clone();
+        }
+
+        // playing the resampled frames at the same sample rate raises the
+        // pitch by `factor`
+        const RATE_SCALE: f64 = 65536.0;
+        let factor = 2f64.powf(semitones / 12.0);
+        let from_rate = (factor * RATE_SCALE).round().clamp(1.0, u32::MAX as f64) as u32;
+        let resampled = resample_frames(
+            &self.frames.to_vec(),
+            from_rate,
+            RATE_SCALE as u32,
+            ResampleQuality::Hermite,
+        );
+
+        let grain = (STRETCH_GRAIN_SECS * self.sample_rate as f64) as usize;
+        let frames = time_stretch(&resampled, self.frames.len(), grain);
+
+        let mut sound = self.clone();
+        sound.frames = frames.into();
+        if self.frames.is_mono() {
+            sound = sound.compacted();
+        }
+        sound
+    }
+
+    /// Return a copy of the sound where only the frames in `range` are
+    /// reversed, and the frames before and after it stay in place. Useful
+    /// for glitch and stutter effects. The range is clamped to the length of
+    /// the sound, and an empty range returns the sound unchanged.
+    ///
+    /// Unlike [`Sound::reverse`], this edits the audio data instead of the
+    /// playback direction.
+    pub fn reverse_region(&self, range: RangeInclusive<usize>) -> Sound {
+        let (start, end) = (*range.start(), *range.end());
+        let end = end.min(self.frames.len().saturating_sub(1));
+        if start >= end || self.frames.is_empty() {
+            return self.clone();
+        }
+
+        let mut sound = self.clone();
+        sound.frames = match &self.frames {
+            FrameData::Stereo(frames) => {
+                let mut frames = frames.to_vec();
+                frames[start..=end].reverse();
+                frames.into()
+            }
+            FrameData::Mono(samples) => {
+                let mut samples = samples.to_vec();
+                samples[start..=end].reverse();
+                FrameData::Mono(samples.into())
+            }
+        };
+        sound
+    }
+
+    /// Return `true` if the left and right channels of every frame differ by
+    /// at most `tolerance`.
+    pub fn is_effectively_mono(&self, tolerance: f32) -> bool {
+        match &self.frames {
+            FrameData::Mono(_) => true,
+            FrameData::Stereo(frames) => frames
+                .iter()
+                .all(|frame| (frame.left - frame.right).abs() <= tolerance),
+        }
+    }
+
+    /// Analyze how well the sound survives being summed to mono, for example
+    /// on broadcast targets or mono speakers. Returns the correlation between
+    /// the channels and how much quieter the mono sum is. The volume and
+    /// panning of the sound are ignored.
+    ///
+    /// Sounds with channels that are out of phase have a negative
+    /// correlation and a large level drop. See [`crate::Mixer::correlation`]
+    /// to measure the mixed output while it plays.
+    pub fn mono_compatibility(&self) -> MonoReport {
+        MonoReport::analyze(self.frames.iter())
+    }
+
+    /// Return a copy of the sound that stores its frames as mono if both
+    /// channels are identical, which halves the memory it uses. The sound
+    /// plays back exactly the same. Sounds with different channels are
+    /// returned unchanged.
+    ///
+    /// The copy no longer shares its audio data with the other clones of
+    /// this sound.
+    pub fn compacted(&self) -> Sound {
+        let mut sound = self.clone();
+        if let FrameData::Stereo(frames) = &self.frames {
+            if self.is_effectively_mono(0.0) {
+                sound.frames = FrameData::Mono(frames.iter().map(|frame| frame.left).collect());
+            }
+        }
+        sound
+    }
+
+    /// Serialize the audio data and sample rate of the sound into a compact
+    /// binary layout, which can be loaded with [`Sound::from_cached_bytes`]
+    /// without decoding it again. Playback settings (volume, loops,
+    /// commands and so on) are not included.
+    ///
+    /// The layout starts with a magic and a version header, followed by the
+    /// raw little-endian samples. Sounds stored as mono (see
+    /// [`Sound::compacted`]) are written as mono, which halves the size.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let stored_channels: u8 = if self.frames.is_mono() { 1 } else { 2 };
+        let mut bytes = Vec::with_capacity(
+            CACHED_SOUND_HEADER_LEN + self.frames.len() * stored_channels as usize * 4,
+        );
+        bytes.extend_from_slice(&CACHED_SOUND_MAGIC);
+        bytes.extend_from_slice(&CACHED_SOUND_VERSION.to_le_bytes());
+        bytes.push(stored_channels);
+        bytes.extend_from_slice(&self.source_channels.to_le_bytes());
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u64).to_le_bytes());
+        match &self.frames {
+            FrameData::Stereo(frames) => {
+                for frame in frames.iter() {
+                    bytes.extend_from_slice(&frame.left.to_le_bytes());
+                    bytes.extend_from_slice(&frame.right.to_le_bytes());
+                }
+            }
+            FrameData::Mono(samples) => {
+                for sample in samples.iter() {
+                    bytes.extend_from_slice(&sample.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Set the loop points as a frame index. Loop ends past the end of the
+    /// sound are clamped to the end. If the start is not before the
+    /// (clamped) end, looping is disabled.
+    #[inline]
+    pub fn set_loop_index(&mut self, loop_region: RangeInclusive<usize>) {
+        self.loop_points
+            .start_tween(LoopPoints::from_range(loop_region));
+        self.validate_loop();
+    }
+
+    /// Set the current loop state (enabled/disabled). Return the previous loop state.
+    ///
+    /// Looping can't be enabled if the loop region is empty or inverted.
+    #[inline]
+    pub fn set_loop_enabled(&mut self, enabled: bool) -> bool {
+        let prev_enabled = self.loop_enabled;
+        self.loop_enabled = enabled;
+        self.validate_loop();
+        prev_enabled
+    }
+
+    /// Set the loop points as a position in seconds. See
+    /// [`Sound::set_loop_index`].
+    #[inline]
+    pub fn set_loop(&mut self, loop_region: RangeInclusive<f64>) {
+        self.loop_points =
+            Parameter::new(LoopPoints::from_range_secs(loop_region, self.sample_rate));
+        self.validate_loop();
+    }
+
+    /// Set up the common "intro + loop" structure of game music: the sound
+    /// restarts from the beginning, plays the intro once, then loops the
+    /// body from `intro_end` to the end of the sound without a gap.
+    ///
+    /// This is a shortcut for [`Sound::reset`], [`Sound::set_loop_index`]
+    /// and [`Sound::set_loop_enabled`]. If `intro_end` is not before the end
+    /// of the sound, looping is disabled.
+    pub fn set_intro_loop(&mut self, intro_end: Duration) {
+        self.reset();
+        // start on the first frame right away, without the frames the
+        // resampler held before
+        self.cue(0.0);
+        let start = secs_to_index(intro_end.as_secs_f64(), self.sample_rate);
+        self.set_loop_index(start..=self.frames.len());
+        self.set_loop_enabled(true);
+    }
+
+    /// Return the sound with an intro that plays once, followed by a body
+    /// that loops. See [`Sound::set_intro_loop`].
+    #[inline]
+    pub fn with_intro_loop(mut self, intro_end: Duration) -> Self {
+        self.set_intro_loop(intro_end);
+        self
+    }
+
+    /// Return the starting point of the loop as a frame index.
+    #[inline]
+    pub fn loop_start(&self) -> usize {
+        self.loop_points.value.start
+    }
+
+    /// Return the ending point of the loop as a frame index.
+    #[inline]
+    pub fn loop_end(&self) -> usize {
+        self.loop_points.value.end
+    }
+
+    /// Return the starting point of the loop as seconds.
+    #[inline]
+    pub fn loop_start_secs(&self) -> f64 {
+        self.loop_points.value.start_secs(self.sample_rate)
+    }
+
+    /// Return the ending point of the loop as seconds.
+    #[inline]
+    pub fn loop_end_secs(&self) -> f64 {
+        self.loop_points.value.end_secs(self.sample_rate)
+    }
+
+    /// Return the current index in the source sound data. Can be modified with commands.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index.value
+    }
+
+    /// Return the current index in the source sound data. Cannot be modified with commands.
+    #[inline]
+    pub fn base_index(&self) -> usize {
+        self.index.base_value
+    }
+
+    /// Return the playback position in seconds. Unlike [`Sound::index`], this
+    /// is the position of the frame that the resampler is currently
+    /// outputting, including the fractional position between frames.
+    ///
+    /// A paused sound reports the position it will resume from, which is
+    /// updated right away when it is seeked.
+    pub fn position_seconds(&self) -> f64 {
+        if self.sample_rate == 0 {
+            return 0.0;
+        }
+        let index = if self.skipping_silence {
+            // the resampler isn't updated while silence is skipped. it is
+            // primed with the frames before the playhead once the sound is
+            // audible again, see `Sound::prime_resampler`
+            if self.moving_backwards() {
+                self.index.value + 3
+            } else {
+                self.index.value.saturating_sub(3)
+            }
+        } else {
+            self.resampler.current_frame_index()
+        } as f64;
+        let position = if self.moving_backwards() {
+            index - self.fractional_position
+        } else {
+            index + self.fractional_position
+        };
+        position / self.sample_rate as f64
+    }
+
+    /// Return whether the sound is currently outputting silence: its output
+    /// stayed at or below the silence threshold for at least the silence hold
+    /// time. By default, only exact zeros count as silence and there is no
+    /// hold time. See [`Sound::set_silence_threshold`].
+    ///
+    /// Silent sounds with a volume of 0 skip rendering, see
+    /// [`Sound::next_frame`].
+    #[inline]
+    pub fn outputting_silence(&self) -> bool {
+        self.resampler
+            .outputting_silence_below(self.silence_threshold)
+            && self.quiet_time >= self.silence_hold
+    }
+
+    /// Return how loud the sound currently is for ranking voices: its volume
+    /// (including mute and the tremolo LFO) multiplied by the volume drop of
+    /// its occlusion. See [`crate::VoicePolicy`].
+    pub fn audibility(&self) -> f32 {
+        let (_, occlusion_gain) = self.occlusion_model.map(self.occlusion.target);
+        self.gain() * occlusion_gain
+    }
+
+    /// Return whether the sound is rendered, or virtual because the renderer
+    /// gave its voice to louder sounds. See [`crate::VoicePolicy`].
+    #[inline]
+    pub fn voice_state(&self) -> VoiceState {
+        if self.virtual_voice {
+            VoiceState::Virtual
+        } else {
+            VoiceState::Real
+        }
+    }
+
+    /// Set whether events are recorded for the event log of the renderer.
+    /// Recorded events are kept until they are taken with
+    /// [`Sound::take_events`].
+    #[inline]
+    pub(crate) fn set_record_events(&mut self, record: bool) {
+        self.record_events = record;
+    }
+
+    /// Take the events recorded since the last call.
+    #[inline]
+    pub(crate) fn take_events(&mut self) -> std::vec::Drain<'_, RenderEventKind> {
+        self.events.drain(..)
+    }
+
+    /// Record an event if the renderer keeps an event log.
+    #[inline]
+    fn push_event(&mut self, kind: RenderEventKind) {
+        if self.record_events {
+            self.events.push(kind);
+        }
+    }
+
+    /// Set whether the sound is virtual. Used by the renderer when it limits
+    /// the amount of voices.
+    #[inline]
+    pub(crate) fn set_virtual(&mut self, virtual_voice: bool) {
+        self.virtual_voice = virtual_voice;
+    }
+
+    /// Return whether all frames held by the resampler are no louder than
+    /// `threshold` (an amplitude) on both channels. Unlike
+    /// [`Sound::outputting_silence`], this ignores the silence threshold and
+    /// hold time of the sound, so it can be used for one-off checks such as
+    /// whether a sound has drained.
+    #[inline]
+    pub fn is_quiet(&self, threshold: f32) -> bool {
+        self.resampler.outputting_silence_below(threshold.max(0.0))
+    }
+
+    /// Set the amplitude at or below which the sound counts as silent, for
+    /// example `0.00003` for -90 dB. Defaults to 0.0, which only counts exact
+    /// zeros. Negative values are treated as 0.0.
+    ///
+    /// See [`Sound::outputting_silence`] and [`Sound::set_silence_hold`].
+    #[inline]
+    pub fn set_silence_threshold(&mut self, threshold: f32) {
+        self.silence_threshold = threshold.max(0.0);
+    }
+
+    /// Return the amplitude at or below which the sound counts as silent.
+    #[inline]
+    pub fn silence_threshold(&self) -> f32 {
+        self.silence_threshold
+    }
+
+    /// Set how long the output has to stay at or below the silence threshold
+    /// before the sound counts as silent. Getting louder than the threshold
+    /// counts as audible right away. This keeps
+    /// [`Sound::outputting_silence`] from flickering when the level hovers
+    /// around the threshold. Defaults to zero.
+    #[inline]
+    pub fn set_silence_hold(&mut self, hold: Duration) {
+        self.silence_hold = hold.as_secs_f64();
+    }
+
+    /// Return how long the output has to stay at or below the silence
+    /// threshold before the sound counts as silent.
+    #[inline]
+    pub fn silence_hold(&self) -> Duration {
+        Duration::from_secs_f64(self.silence_hold)
+    }
+
+    /// Pause the sound if it is playing. This won't cut off the audio signal, but smoothly
+    /// interpolate the last audio value to 0 for 4 frames.
+    ///
+    /// Commands keep running while the sound is paused, so their timers are
+    /// measured in render time. Use [`crate::Mixer::set_paused`] to freeze
+    /// the commands of all sounds as well.
+    #[inline]
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Return whether the sound is paused. This does not take the playback speed for account.
+    #[inline]
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Resume the sound if paused. This won't immediately start the audio signal, but smoothly
+    /// interpolate the last audio value for 4 frames.
+    #[inline]
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    /// Return whether the playback rate is exactly zero and no command is
+    /// changing it.
+    #[inline]
+    fn at_standstill(&self) -> bool {
+        !self.paused
+            && self.playback_rate.value.as_factor() == 0.0
+            && !self
+                .commands
+                .iter()
+                .any(|command| matches!(command.change, Change::PlaybackRate(_)))
+    }
+
+    /// Set what the sound does when its playback rate is exactly zero and no
+    /// command is changing it. By default, the sound is kept in the mixer,
+    /// where it stays until the rate changes. See [`ZeroRatePolicy`].
+    #[inline]
+    pub fn set_zero_rate_policy(&mut self, policy: ZeroRatePolicy) {
+        self.zero_rate_policy = policy;
+    }
+
+    /// Return what the sound does when its playback rate is zero. See
+    /// [`Sound::set_zero_rate_policy`].
+    #[inline]
+    pub fn zero_rate_policy(&self) -> ZeroRatePolicy {
+        self.zero_rate_policy
+    }
+
+    /// Slow the sound down to a standstill over `duration`, like a tape
+    /// machine or a turntable that is switched off, then pause, stop or
+    /// hold the sound according to `then`.
+    ///
+    /// The pitch glides down linearly in semitones, which sounds smoother
+    /// than gliding the speed factor. Reversed sounds glide their speed
+    /// factor instead.
+    pub fn tape_stop(&mut self, duration: Duration, then: ZeroRatePolicy) {
+        let seconds = duration.as_secs_f64();
+        let rate = self.playback_rate.value;
+        let target = if rate.as_factor() > 0.0 {
+            let semitones = rate.as_semitones();
+            self.set_playback_rate(PlaybackRate::Semitones(semitones));
+            PlaybackRate::Semitones(semitones - TAPE_GLIDE_SEMITONES)
+        } else {
+            PlaybackRate::Factor(0.0)
+        };
+        self.add_command(Command::new(
+            Change::PlaybackRate(target),
+            Easing::Linear,
+            0.0,
+            seconds,
+        ));
+        self.add_command(Command::new(
+            Change::PlaybackRate(PlaybackRate::Factor(0.0)),
+            Easing::Linear,
+            seconds,
+            0.0,
+        ));
+        let end = match then {
+            ZeroRatePolicy::Hold => return,
+            ZeroRatePolicy::Pause => Change::Pause(true),
+            ZeroRatePolicy::Stop => Change::Stop,
+        };
+        self.add_command(Command::new(end, Easing::Linear, seconds, 0.0));
+    }
+
+    /// Resume the sound and speed it up from a standstill to `target` over
+    /// `duration`. This is the inverse of [`Sound::tape_stop`]. A target
+    /// rate that is zero or negative is set immediately.
+    pub fn tape_start(&mut self, target: PlaybackRate, duration: Duration) {
+        self.resume();
+        if target.as_factor() <= 0.0 || duration.is_zero() {
+            self.set_playback_rate(target);
+            return;
+        }
+
+        let seconds = duration.as_secs_f64();
+        let semitones = target.as_semitones();
+        self.set_playback_rate(PlaybackRate::Semitones(semitones - TAPE_GLIDE_SEMITONES));
+        self.add_command(Command::new(
+            Change::PlaybackRate(PlaybackRate::Semitones(semitones)),
+            Easing::Linear,
+            0.0,
+            seconds,
+        ));
+        // land exactly on the target rate
+        self.add_command(Command::new(
+            Change::PlaybackRate(target),
+            Easing::Linear,
+            seconds,
+            0.0,
+        ));
+    }
+
+    /// Stop the sound immediately. The sound will be finished and removed
+    /// from the renderer on the next frame.
+    ///
+    /// Note: this cuts off the audio signal, which can click. Use
+    /// [`Sound::stop_with_fade`] to fade the sound out before stopping it.
+    #[inline]
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Fade the volume to zero over `duration` with the given easing, then
+    /// stop the sound. A zero `duration` stops the sound immediately, like
+    /// [`Sound::stop`].
+    pub fn stop_with_fade(&mut self, duration: Duration, easing: Easing) {
+        if duration.is_zero() {
+            self.stop();
+            return;
+        }
+
+        let duration = duration.as_secs_f64();
+        self.add_command(Command::new(Change::Volume(0.0), easing, 0.0, duration));
+        self.add_command(Command::new(Change::Stop, Easing::Linear, duration, 0.0));
+    }
+
+    /// Stop the sound after `seconds` of source material were played. This is
+    /// measured in source frames, so it doesn't depend on the playback rate.
+    /// If [`None`], the sound plays until the end.
+    #[inline]
+    pub fn set_play_for(&mut self, seconds: Option<f64>) {
+        self.frames_left = seconds.map(|seconds| secs_to_index(seconds, self.sample_rate));
+    }
+
+    /// Return how many seconds of source material are left before the sound
+    /// stops because of [`Sound::set_play_for`].
+    #[inline]
+    pub fn play_for_left(&self) -> Option<f64> {
+        self.frames_left
+            .map(|frames| frames as f64 / self.sample_rate as f64)
+    }
+
+    /// Stop the sound after it was rendered for `duration`, even if it loops.
+    /// Unlike [`Sound::set_play_for`], this is measured in output time, and
+    /// only counts the time the sound wasn't paused. The sound fades out
+    /// over the last few milliseconds, so it doesn't click.
+    #[inline]
+    pub fn set_max_play_time(&mut self, duration: Duration) {
+        self.max_play_time = Some(duration.as_secs_f64());
+    }
+
+    /// Remove the limit set with [`Sound::set_max_play_time`].
+    #[inline]
+    pub fn clear_max_play_time(&mut self) {
+        self.max_play_time = None;
+    }
+
+    /// Return the limit set with [`Sound::set_max_play_time`].
+    #[inline]
+    pub fn max_play_time(&self) -> Option<Duration> {
+        self.max_play_time.map(Duration::from_secs_f64)
+    }
+
+    /// Return how long the sound was rendered for while not paused.
+    #[inline]
+    pub fn play_time(&self) -> Duration {
+        Duration::from_secs_f64(self.play_time)
+    }
+
+    /// Set the maximum age of the sound, used by the renderer to stop sounds
+    /// that played for too long. See [`crate::Mixer::set_max_sound_age`].
+    #[inline]
+    pub(crate) fn set_max_age(&mut self, max_age: Option<Duration>) {
+        self.max_age = max_age.map(|age| age.as_secs_f64());
+    }
+
+    /// Stop the sound because its voice was stolen. The sound fades out over
+    /// a few milliseconds like with [`Sound::set_max_play_time`], so it
+    /// doesn't click. Sounds that are paused or didn't start yet are silent,
+    /// so they are stopped immediately.
+    pub(crate) fn steal(&mut self) {
+        if self.paused || self.start_delay > 0.0 {
+            self.stop();
+        } else if self.stolen_at.is_none() {
+            self.stolen_at = Some(self.play_time + MAX_PLAY_TIME_FADE_SECS);
+        }
+    }
+
+    /// Return whether the voice of the sound was stolen, so it is fading out
+    /// or stopped. See [`Sound::steal`].
+    #[inline]
+    pub(crate) fn is_stolen(&self) -> bool {
+        self.stolen_at.is_some() || self.stopped
+    }
+
+    /// Return whether the sound was stopped with [`Sound::stop`] or
+    /// [`Sound::stop_with_fade`].
+    #[inline]
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Enter scrubbing mode. While scrubbing, the playback rate is ignored and
+    /// the playhead smoothly chases the position set by [`Sound::scrub_to`],
+    /// so the audio under the playhead is heard pitch-shifted by the drag
+    /// speed.
+    ///
+    /// Note: a paused sound stays silent while scrubbing.
+    #[inline]
+    pub fn begin_scrub(&mut self) {
+        if self.scrub.is_none() {
+            self.scrub = Some(Scrub {
+                target: self.index.value as f64,
+                backwards: false,
+            });
+            self.fractional_position = 0.0;
+        }
+    }
+
+    /// Set the position (in seconds) that the playhead should move towards.
+    /// The playhead will reach it over the next few milliseconds. Enters
+    /// scrubbing mode if the sound is not scrubbing already.
+    pub fn scrub_to(&mut self, seconds: f64) {
+        self.begin_scrub();
+        let last_index = self.frames.len().saturating_sub(1) as f64;
+        let target = (seconds * self.sample_rate as f64).clamp(0.0, last_index);
+        if let Some(scrub) = &mut self.scrub {
+            scrub.target = target;
+        }
+    }
+
+    /// Leave scrubbing mode. The sound continues playing from the current
+    /// position with its playback rate.
+    #[inline]
+    pub fn end_scrub(&mut self) {
+        self.scrub = None;
+    }
+
+    /// Return whether the sound is in scrubbing mode.
+    #[inline]
+    pub fn scrubbing(&self) -> bool {
+        self.scrub.is_some()
+    }
+
+    /// Set the audio panning.
+    ///
+    /// * Panning of 0.0 means hard left panning
+    /// * Panning of 0.5 means center panning (default)
+    /// * Panning of 1.0 means hard right panning
+    ///
+    /// # Returns
+    ///
+    /// Returns the previous panning value
+    #[inline]
+    pub fn set_panning(&mut self, panning: f32) -> f32 {
+        let prev_panning = self.panning.value;
+        self.panning.start_tween(panning);
+        self.write_link(LinkedParameter::Panning);
+        prev_panning
+    }
+
+    /// Get the audio panning.
+    ///
+    /// * Panning of 0.0 means hard left panning
+    /// * Panning of 0.5 means center panning (default)
+    /// * Panning of 1.0 means hard right panning
+    ///
+    /// # Returns
+    ///
+    /// Returns the panning value
+    #[inline]
+    pub fn panning(&self) -> f32 {
+        self.shared(LinkedParameter::Panning, self.panning).value
+    }
+
+    /// Return the current base panning value. Can't be modified with
+    /// commands.
+    #[inline]
+    pub fn base_panning(&self) -> f32 {
+        self.shared(LinkedParameter::Panning, self.panning)
+            .base_value
+    }
+
+    /// Move the panning to `target` over `duration` with the given easing.
+    /// A zero `duration` sets the panning immediately, like
+    /// [`Sound::set_panning`].
+    pub fn pan_to(&mut self, target: f32, duration: Duration, easing: Easing) {
+        if duration.is_zero() {
+            self.set_panning(target);
+            return;
+        }
+        self.add_command(Command::new(
+            Change::Panning(target),
+            easing,
+            0.0,
+            duration.as_secs_f64(),
+        ));
+    }
+
+    /// Set the tone controls: the gain of a bass shelf (below 250 Hz) and
+    /// a treble shelf (above 4 kHz) in decibels. The gains are clamped to
+    /// ±12 dB; 0 dB (the default) disables the filters. Cutting the treble
+    /// muffles a sound, for example behind a wall, cutting both sounds like
+    /// a radio. The gains can be tweened with [`Change::BassDb`] and
+    /// [`Change::TrebleDb`].
+    #[inline]
+    pub fn set_tone(&mut self, bass_db: f32, treble_db: f32) {
+        self.bass_db
+            .start_tween(bass_db.clamp(-TONE_MAX_DB, TONE_MAX_DB));
+        self.treble_db
+            .start_tween(treble_db.clamp(-TONE_MAX_DB, TONE_MAX_DB));
+    }
+
+    /// Return the gain of the bass shelf in decibels. Can be modified with
+    /// commands. See [`Sound::set_tone`].
+    #[inline]
+    pub fn bass_db(&self) -> f32 {
+        self.bass_db.value
+    }
+
+    /// Return the gain of the treble shelf in decibels. Can be modified with
+    /// commands. See [`Sound::set_tone`].
+    #[inline]
+    pub fn treble_db(&self) -> f32 {
+        self.treble_db.value
+    }
+
+    /// Set how occluded the sound is, from 0.0 (clear, the default) to 1.0
+    /// (fully occluded, for example behind a wall). Occlusion muffles the
+    /// sound with a lowpass filter and lowers its volume, as described by
+    /// the [`OcclusionModel`]. Changes are smoothed, so this can be called
+    /// every frame of a game.
+    #[inline]
+    pub fn set_occlusion(&mut self, amount: f32) {
+        self.occlusion.target = amount.clamp(0.0, 1.0);
+    }
+
+    /// Return the occlusion amount set with [`Sound::set_occlusion`].
+    #[inline]
+    pub fn occlusion(&self) -> f32 {
+        self.occlusion.target
+    }
+
+    /// Set how the occlusion amount maps to the lowpass filter and the
+    /// volume. See [`OcclusionModel`].
+    #[inline]
+    pub fn set_occlusion_model(&mut self, model: OcclusionModel) {
+        self.occlusion_model = model;
+    }
+
+    /// Return the occlusion model of the sound.
+    #[inline]
+    pub fn occlusion_model(&self) -> OcclusionModel {
+        self.occlusion_model
+    }
+
+    /// Set the output channels the sound plays on. Only has an effect on
+    /// streams with more than two channels, see [`OutputRoute`].
+    #[inline]
+    pub fn set_output_route(&mut self, route: OutputRoute) {
+        self.output_route = route;
+    }
+
+    /// Return the output channels the sound plays on.
+    #[inline]
+    pub fn output_route(&self) -> OutputRoute {
+        self.output_route
+    }
+
+    /// Set the pan law used to apply the panning. See [`PanLaw`].
+    #[inline]
+    pub fn set_pan_law(&mut self, law: PanLaw) {
+        self.pan_law = law;
+    }
+
+    /// Return the pan law of the sound.
+    #[inline]
+    pub fn pan_law(&self) -> PanLaw {
+        self.pan_law
+    }
+
+    /// Mute or unmute the sound. A muted sound keeps playing, but outputs
+    /// silence. Muting doesn't change the volume, so unmuting restores the
+    /// previous level.
+    #[inline]
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    /// Return whether the sound is muted.
+    #[inline]
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Solo or unsolo the sound. While any sound in a renderer is soloed,
+    /// only soloed sounds are audible. Muting takes priority over soloing.
+    #[inline]
+    pub fn set_solo(&mut self, solo: bool) {
+        self.solo = solo;
+    }
+
+    /// Return whether the sound is soloed.
+    #[inline]
+    pub fn solo(&self) -> bool {
+        self.solo
+    }
+
+    /// Only render the sound into recordings (a [`crate::RecordMixer`]), so it
+    /// is recorded but not heard, for example a click track. The sound still
+    /// advances in live renderers. Clears [`Sound::set_monitor_only`].
+    #[inline]
+    pub fn set_record_only(&mut self, record_only: bool) {
+        self.record_only = record_only;
+        if record_only {
+            self.monitor_only = false;
+        }
+    }
+
+    /// Return whether the sound is only rendered into recordings. See
+    /// [`Sound::set_record_only`].
+    #[inline]
+    pub fn record_only(&self) -> bool {
+        self.record_only
+    }
+
+    /// Only render the sound in live renderers (a [`crate::Mixer`]), so it
+    /// is heard but not recorded, for example a monitor cue. The sound still
+    /// advances in recording renderers. Clears [`Sound::set_record_only`].
+    #[inline]
+    pub fn set_monitor_only(&mut self, monitor_only: bool) {
+        self.monitor_only = monitor_only;
+        if monitor_only {
+            self.record_only = false;
+        }
+    }
+
+    /// Return whether the sound is only rendered in live renderers. See
+    /// [`Sound::set_monitor_only`].
+    #[inline]
+    pub fn monitor_only(&self) -> bool {
+        self.monitor_only
+    }
+
+    /// Return whether the sound is audible on the given render path.
+    #[inline]
+    pub fn plays_on(&self, path: RenderPath) -> bool {
+        match path {
+            RenderPath::Live => !self.record_only,
+            RenderPath::Record => !self.monitor_only,
+        }
+    }
+
+    /// Attach user data to the sound, for example the game object the sound
+    /// belongs to, or the subtitle line it plays. The data is shared between
+    /// clones of the sound and stays attached while the sound plays.
+    ///
+    /// The user data is only storage: it is ignored when comparing sounds,
+    /// and it is not serialized.
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: Arc<dyn Any + Send + Sync>) {
+        self.user_data = UserData(Some(user_data));
+    }
+
+    /// Remove the user data. See [`Sound::set_user_data`].
+    #[inline]
+    pub fn clear_user_data(&mut self) {
+        self.user_data = UserData(None);
+    }
+
+    /// Return the user data set with [`Sound::set_user_data`]. Returns
+    /// [`None`] if there is no user data, or if it isn't a `T`.
+    #[inline]
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.user_data.0.clone()?.downcast().ok()
+    }
+}
+
+/// Bit in [`ParameterMailbox::pending`] for a new volume.
+const MAILBOX_VOLUME: u32 = 1 << 0;
+/// Bit in [`ParameterMailbox::pending`] for a new panning.
+const MAILBOX_PANNING: u32 = 1 << 1;
+/// Bit in [`ParameterMailbox::pending`] for a new playback rate.
+const MAILBOX_PLAYBACK_RATE: u32 = 1 << 2;
+/// Bit in [`ParameterMailbox::pending`] for a new paused state.
+const MAILBOX_PAUSED: u32 = 1 << 3;
+/// Bit in [`ParameterMailbox::pending`] that is set if the last playback
+/// rate was written in semitones. Always changed together with
+/// [`MAILBOX_PLAYBACK_RATE`].
+const MAILBOX_SEMITONES: u32 = 1 << 4;
+
+/// Frequently changed parameters, written by a [`SoundHandle`] without
+/// locking the [`Sound`]. They are applied the next time the sound is
+/// locked, which happens every frame while it is playing.
+///
+/// If a parameter is written multiple times before it is applied, the last
+/// value wins.
+#[derive(Debug, Default)]
+struct ParameterMailbox {
+    /// Bitmask of the parameters that were written but not applied yet.
+    pending: AtomicU32,
+    /// Bits of the [`f32`] volume.
+    volume: AtomicU32,
+    /// Bits of the [`f32`] panning.
+    panning: AtomicU32,
+    /// Bits of the last [`PlaybackRate::Factor`].
+    playback_factor: AtomicU64,
+    /// Bits of the last [`PlaybackRate::Semitones`].
+    playback_semitones: AtomicU64,
+    paused: AtomicBool,
+}
+
+impl ParameterMailbox {
+    /// Mark a parameter as written. Must be called after storing the value.
+    #[inline]
+    fn post(&self, bit: u32) {
+        self.pending.fetch_or(bit, Ordering::Release);
+    }
+
+    /// Write a new playback rate. Each variant has its own slot, and the
+    /// variant that was written last is marked in the same atomic update
+    /// that marks the playback rate as written, so a value is never read as
+    /// the wrong variant.
+    #[inline]
+    fn post_playback_rate(&self, playback_rate: PlaybackRate) {
+        let semitones = match playback_rate {
+            PlaybackRate::Factor(factor) => {
+                self.playback_factor
+                    .store(factor.to_bits(), Ordering::Relaxed);
+                0
+            }
+            PlaybackRate::Semitones(semitones) => {
+                self.playback_semitones
+                    .store(semitones.to_bits(), Ordering::Relaxed);
+                MAILBOX_SEMITONES
+            }
+        };
+        let _ = self
+            .pending
+            .fetch_update(Ordering::Release, Ordering::Relaxed, |pending| {
+                Some(pending & !MAILBOX_SEMITONES | MAILBOX_PLAYBACK_RATE | semitones)
+            });
+    }
+
+    /// Apply all written parameters to the sound.
+    #[inline]
+    fn apply(&self, sound: &mut Sound) {
+        // avoid writing to the atomic if nothing changed
+        if self.pending.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        let pending = self.pending.swap(0, Ordering::Acquire);
+
+        if pending & MAILBOX_VOLUME != 0 {
+            sound.set_volume(f32::from_bits(self.volume.load(Ordering::Relaxed)));
+        }
+        if pending & MAILBOX_PANNING != 0 {
+            sound.set_panning(f32::from_bits(self.panning.load(Ordering::Relaxed)));
+        }
+        if pending & MAILBOX_PLAYBACK_RATE != 0 {
+            let playback_rate = if pending & MAILBOX_SEMITONES != 0 {
+                PlaybackRate::Semitones(f64::from_bits(
+                    self.playback_semitones.load(Ordering::Relaxed),
+                ))
+            } else {
+                PlaybackRate::Factor(f64::from_bits(self.playback_factor.load(Ordering::Relaxed)))
+            };
+            sound.set_playback_rate(playback_rate);
+        }
+        if pending & MAILBOX_PAUSED != 0 {
+            sound.paused = self.paused.load(Ordering::Relaxed);
+        }
+    }
+}
+
+/// Data shared between all clones of a [`SoundHandle`].
+#[derive(Debug)]
+struct SharedSound {
+    sound: Mutex<Sound>,
+    mailbox: ParameterMailbox,
+}
+
+/// Wraps a [`Sound`] so it can be returned to the user after `play`.
+///
+/// This type can be cheaply cloned, and it will share the same data.
+///
+/// Most methods lock the sound, which can make the audio thread wait. The
+/// `send_*` methods (for example [`SoundHandle::send_volume`]) change the
+/// most frequently used parameters without locking, use them if you update
+/// parameters very often (for example every frame of a game).
+#[derive(Debug, Clone)]
+pub struct SoundHandle(Arc<SharedSound>);
+
+impl From<Sound> for SoundHandle {
+    fn from(sound: Sound) -> Self {
+        Self::new(sound)
+    }
+}
+
+impl SoundHandle {
+    /// Make a new [`SoundHandle`] from a [`Sound`].
+    #[inline]
+    pub fn new(sound: Sound) -> Self {
+        Self(Arc::new(SharedSound {
+            sound: Mutex::new(sound),
+            mailbox: ParameterMailbox::default(),
+        }))
+    }
+
+    /// Lock the [`Sound`] for modification. Returns a [`MutexGuard`].
+    ///
+    /// Parameters changed with the `send_*` methods are applied before the
+    /// guard is returned.
+    #[inline]
+    pub fn guard(&self) -> MutexGuard<'_, Sound> {
+        let mut sound = self.0.sound.lock();
+        self.0.mailbox.apply(&mut sound);
+        sound
+    }
+
+    /// Return whether both handles point to the same sound.
+    #[inline]
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Set the volume without locking the sound. The volume is applied on
+    /// the next rendered frame, see [`Sound::set_volume`].
+    #[inline]
+    pub fn send_volume(&self, volume: f32) {
+        let mailbox = &self.0.mailbox;
+        mailbox.volume.store(volume.to_bits(), Ordering::Relaxed);
+        mailbox.post(MAILBOX_VOLUME);
+    }
+
+    /// Set the panning without locking the sound. The panning is applied on
+    /// the next rendered frame, see [`Sound::set_panning`].
+    #[inline]
+    pub fn send_panning(&self, panning: f32) {
+        let mailbox = &self.0.mailbox;
+        mailbox.panning.store(panning.to_bits(), Ordering::Relaxed);
+        mailbox.post(MAILBOX_PANNING);
+    }
+
+    /// Set the playback rate without locking the sound. The playback rate is
+    /// applied on the next rendered frame, see [`Sound::set_playback_rate`].
+    #[inline]
+    pub fn send_playback_rate(&self, playback_rate: PlaybackRate) {
+        self.0.mailbox.post_playback_rate(playback_rate);
+    }
+
+    /// Pause or resume the sound without locking it. Applied on the next
+    /// rendered frame, see [`Sound::pause`] and [`Sound::resume`].
+    #[inline]
+    pub fn send_paused(&self, paused: bool) {
+        let mailbox = &self.0.mailbox;
+        mailbox.paused.store(paused, Ordering::Relaxed);
+        mailbox.post(MAILBOX_PAUSED);
+    }
+
+    /// Share a parameter between this sound and `other`, so setting it or
+    /// tweening it with a command on either sound changes it on both. Both
+    /// sounds always render with the same value, even in the same frame:
+    /// changes are used by all linked sounds from the frame after they were
+    /// made. Commands run on the sound they were added to, so they only
+    /// advance while that sound is rendered.
+    ///
+    /// The shared value is stored in atomics, so rendering linked sounds
+    /// doesn't lock anything.
+    ///
+    /// If this sound already shares the parameter, `other` joins the sounds
+    /// it shares it with. Otherwise, `other` takes the value of this sound.
+    /// Clones of a linked [`Sound`] are linked as well. Use
+    /// [`Sound::unlink`] to make the parameter independent again.
+    pub fn link(&self, other: &SoundHandle, parameter: LinkedParameter) {
+        if self.ptr_eq(other) {
+            return;
+        }
+        // don't hold both locks at once, so linking in both directions at
+        // the same time can't deadlock
+        let link = self.guard().link_of(parameter);
+        other.guard().join_link(parameter, link);
+    }
+
+    /// Share the volume with `other`, see [`SoundHandle::link`].
+    #[inline]
+    pub fn link_volume(&self, other: &SoundHandle) {
+        self.link(other, LinkedParameter::Volume);
+    }
+
+    /// Share the panning with `other`, see [`SoundHandle::link`].
+    #[inline]
+    pub fn link_panning(&self, other: &SoundHandle) {
+        self.link(other, LinkedParameter::Panning);
+    }
+
+    /// Share the playback rate with `other`, see [`SoundHandle::link`].
+    #[inline]
+    pub fn link_playback_rate(&self, other: &SoundHandle) {
+        self.link(other, LinkedParameter::PlaybackRate);
+    }
+
+    /// Delegate to the underlying [`Sound`].
+    #[inline]
+    pub fn loop_enabled(&self) -> bool {
+        self.guard().loop_enabled
+    }
+
+    /// Delegate to the underlying [`Sound`].
+    #[inline]
+    pub fn user_data<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.guard().user_data()
+    }
+}
+
+macro_rules! delegate {
+    ($($name:ident($($arg:ident: $type:ty),*) $(-> $ret:ty)?),* $(,)?) => {
+        $(
+            /// Delegate to the underlying [`Sound`]'s method.
+            #[inline]
+            pub fn $name(&self, $($arg: $type),*) $(-> $ret)? {
+                self.guard().$name($($arg),*)
+            }
+        )*
+    };
+}
+
+// best solution for now?
+impl SoundHandle {
+    delegate! {
+        sample_rate() -> u32,
+        frames() -> Arc<[Frame]>,
+        frame_count() -> usize,
+        set_sample_rate_override(sample_rate: u32),
+        clear_sample_rate_override(),
+        sample_rate_override() -> Option<u32>,
+        duration() -> Duration,
+        duration_seconds() -> f64,
+        remaining_seconds() -> f64,
+        remaining() -> Duration,
+        playback_rate_for_duration(target: Duration) -> PlaybackRate,
+        set_duration(target: Duration) -> PlaybackRate,
+        output_frame_count(sample_rate: u32) -> Option<usize>,
+        push_frame_to_resampler(),
         is_playing_backwards() -> bool,
         update_position(),
         finished() -> bool,
         next_frame(sample_rate: u32) -> Option<Frame>,
+        skip(duration: Duration),
         reset(),
+        restart(commands: RestartCommands),
+        set_zero_rate_policy(policy: ZeroRatePolicy),
+        zero_rate_policy() -> ZeroRatePolicy,
+        tape_stop(duration: Duration, then: ZeroRatePolicy),
+        tape_start(target: PlaybackRate, duration: Duration),
         set_playback_rate(playback_rate: PlaybackRate) -> PlaybackRate,
         playback_rate() -> PlaybackRate,
         base_playback_rate() -> PlaybackRate,
@@ -961,12 +4957,32 @@ impl SoundHandle {
         base_volume() -> f32,
         seek_to_index(index: usize),
         seek_to_end(),
+        seek_by_frames(frames: i64),
         seek_by(seconds: f64),
         seek_to(seconds: f64),
         reverse(),
+        set_reversed(reversed: bool),
+        play_reversed(),
         add_command(command: Command),
+        try_add_command(command: Command) -> Result<(), KaError>,
+        set_max_command_delay(delay: Option<Duration>),
+        max_command_delay() -> Option<Duration>,
+        cancel_lane(lane: u32),
+        replace_lane(lane: u32, commands: Vec<Command>),
+        has_lane(lane: u32) -> bool,
+        bake_commands(sample_rate: u32) -> Sound,
+        pitch_shifted(semitones: f64) -> Sound,
+        reverse_region(range: RangeInclusive<usize>) -> Sound,
+        is_effectively_mono(tolerance: f32) -> bool,
+        set_rate_automation(factors: &[f64]),
+        clear_rate_automation(),
+        rate_automation_remaining() -> Option<usize>,
+        mono_compatibility() -> MonoReport,
+        compacted() -> Sound,
+        to_bytes() -> Vec<u8>,
         set_loop_index(loop_region: RangeInclusive<usize>),
         set_loop_enabled(enabled: bool) -> bool,
+        set_intro_loop(intro_end: Duration),
         set_loop(loop_region: RangeInclusive<f64>),
         loop_start() -> usize,
         loop_end() -> usize,
@@ -974,11 +4990,1405 @@ impl SoundHandle {
         loop_end_secs() -> f64,
         index() -> usize,
         base_index() -> usize,
+        position_seconds() -> f64,
         outputting_silence() -> bool,
+        is_quiet(threshold: f32) -> bool,
+        channels_in_source() -> u32,
+        audibility() -> f32,
+        voice_state() -> VoiceState,
+        unlink(parameter: LinkedParameter),
+        playback_state() -> PlaybackState,
+        restore_playback_state(state: &PlaybackState) -> Result<(), KaError>,
+        is_linked(parameter: LinkedParameter) -> bool,
+        set_lfo(target: LfoTarget, lfo: Lfo),
+        remove_lfo(target: LfoTarget),
+        lfo(target: LfoTarget) -> Option<Lfo>,
+        set_silence_threshold(threshold: f32),
+        silence_threshold() -> f32,
+        set_silence_hold(hold: Duration),
+        silence_hold() -> Duration,
         pause(),
         paused() -> bool,
         resume(),
+        stop(),
+        stop_with_fade(duration: Duration, easing: Easing),
+        stopped() -> bool,
+        set_play_for(seconds: Option<f64>),
+        play_for_left() -> Option<f64>,
+        set_max_play_time(duration: Duration),
+        set_latency_offset(samples: i64),
+        latency_offset() -> i64,
+        clear_max_play_time(),
+        max_play_time() -> Option<Duration>,
+        play_time() -> Duration,
+        begin_scrub(),
+        scrub_to(seconds: f64),
+        end_scrub(),
+        scrubbing() -> bool,
         set_panning(panning: f32) -> f32,
         panning() -> f32,
+        base_panning() -> f32,
+        pan_to(target: f32, duration: Duration, easing: Easing),
+        set_panning_smoothing(time: Duration),
+        panning_smoothing() -> Duration,
+        set_output_route(route: OutputRoute),
+        output_route() -> OutputRoute,
+        set_pan_law(law: PanLaw),
+        pan_law() -> PanLaw,
+        set_muted(muted: bool),
+        muted() -> bool,
+        set_solo(solo: bool),
+        solo() -> bool,
+        set_record_only(record_only: bool),
+        record_only() -> bool,
+        set_monitor_only(monitor_only: bool),
+        monitor_only() -> bool,
+        plays_on(path: RenderPath) -> bool,
+        set_user_data(user_data: Arc<dyn Any + Send + Sync>),
+        clear_user_data(),
+        set_automation(target: AutomationTarget, curve: AutomationCurve, time: AutomationTime),
+        remove_automation(target: AutomationTarget),
+        has_automation(target: AutomationTarget) -> bool,
+        set_marker(id: u32, seconds: f64),
+        marker(id: u32) -> Option<f64>,
+        remove_marker(id: u32),
+        set_dc_block(enabled: bool),
+        set_tone(bass_db: f32, treble_db: f32),
+        set_volume_smoothing(time: Duration),
+        set_occlusion(amount: f32),
+        occlusion() -> f32,
+        set_occlusion_model(model: OcclusionModel),
+        occlusion_model() -> OcclusionModel,
+        volume_smoothing() -> Duration,
+        bass_db() -> f32,
+        treble_db() -> f32,
+        preview_loop_seam(lead: f64, tail: f64),
+        stop_preview(),
+        previewing_seam() -> bool,
+        dc_block() -> bool,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DefaultRenderer, LfoRate, Renderer};
+    use std::f32::consts::{FRAC_1_SQRT_2, TAU};
+
+    const SAMPLE_RATE: u32 = 1000;
+
+    /// Return a stereo sound with different, non-trivial channels.
+    fn stereo_ramp(len: usize) -> Sound {
+        let frames: Vec<Frame> = (0..len)
+            .map(|i| Frame::new(i as f32 / len as f32, -(i as f32) / len as f32 * 0.5))
+            .collect();
+        Sound::from_frames(SAMPLE_RATE, &frames)
+    }
+
+    /// Render `frames` frames of `sound` at `sample_rate`, with finished
+    /// frames rendered as silence.
+    fn render(sound: &mut Sound, sample_rate: u32, frames: usize) -> Vec<Frame> {
+        (0..frames)
+            .map(|_| sound.next_frame(sample_rate).unwrap_or(Frame::ZERO))
+            .collect()
+    }
+
+    #[test]
+    fn latency_offset_shifts_by_exact_samples() {
+        let sound = stereo_ramp(1000);
+        for rate in [1.0, 0.5] {
+            // the delay is a duration, while the skipped frames are played at
+            // the playback rate: at half speed, every frame is rendered twice
+            let skip = (100.0 / rate) as usize;
+            let prepare = |offset: i64| {
+                let mut sound = sound.clone();
+                sound.set_playback_rate(PlaybackRate::Factor(rate));
+                sound.set_latency_offset(offset);
+                render(&mut sound, SAMPLE_RATE, 1200)
+            };
+            let (plain, delayed, advanced) = (prepare(0), prepare(100), prepare(-100));
+
+            assert!(delayed[..100].iter().all(|&frame| frame == Frame::ZERO));
+            assert_eq!(delayed[100..], plain[..1100]);
+            assert_eq!(advanced[..1200 - skip], plain[skip..]);
+        }
+    }
+
+    #[test]
+    fn linked_sounds_follow_one_tween() {
+        let constant = || Sound::from_mono_samples(SAMPLE_RATE, &[1.0; 1000]);
+        let (a, b) = (SoundHandle::new(constant()), SoundHandle::new(constant()));
+        a.link_volume(&b);
+        a.add_command(Command::new(Change::Volume(0.0), Easing::Linear, 0.0, 0.2));
+
+        // the order the sounds are rendered in doesn't matter
+        let mut levels = vec![];
+        for i in 0..400 {
+            let (first, second) = if i % 3 == 0 { (&b, &a) } else { (&a, &b) };
+            let first = first.guard().next_frame(SAMPLE_RATE).unwrap().left;
+            let second = second.guard().next_frame(SAMPLE_RATE).unwrap().left;
+            assert_eq!(first, second, "frame {i}");
+            levels.push(first);
+
+            // a set on either sound changes both
+            if i == 300 {
+                b.set_volume(0.5);
+            }
+        }
+        assert!(levels[50] < levels[20] && levels[100] < levels[50]);
+        assert!(levels[250] < 0.01);
+        assert_eq!(levels[399], 0.5);
+        assert_eq!((a.volume(), b.volume()), (0.5, 0.5));
+    }
+
+    #[test]
+    fn unlinking_keeps_the_current_value() {
+        let constant = || Sound::from_mono_samples(SAMPLE_RATE, &[1.0; 1000]);
+        let (a, b) = (SoundHandle::new(constant()), SoundHandle::new(constant()));
+        a.link(&b, LinkedParameter::Panning);
+        a.add_command(Command::new(Change::Panning(1.0), Easing::Linear, 0.0, 0.2));
+        for _ in 0..100 {
+            a.guard().next_frame(SAMPLE_RATE);
+            b.guard().next_frame(SAMPLE_RATE);
+        }
+
+        let panning = b.panning();
+        assert!(panning > 0.7 && panning < 0.8, "{panning}");
+        b.unlink(LinkedParameter::Panning);
+        assert!(!b.is_linked(LinkedParameter::Panning));
+        assert_eq!(b.panning(), panning);
+
+        // the tween continues on the sound it was added to only
+        for _ in 0..200 {
+            a.guard().next_frame(SAMPLE_RATE);
+            b.guard().next_frame(SAMPLE_RATE);
+        }
+        assert!(a.panning() > 0.99);
+        assert_eq!(b.panning(), panning);
+        assert_eq!(b.base_panning(), panning);
+    }
+
+    #[test]
+    fn mailbox_keeps_the_last_value() {
+        let handle = SoundHandle::new(stereo_ramp(100));
+        handle.send_volume(0.3);
+        handle.send_volume(0.7);
+        // odd mantissas must not be rounded
+        let factor = f64::from_bits(1.5f64.to_bits() | 1);
+        handle.send_playback_rate(PlaybackRate::Semitones(-3.0));
+        handle.send_playback_rate(PlaybackRate::Factor(factor));
+        assert_eq!(handle.volume(), 0.7);
+        assert_eq!(handle.playback_rate(), PlaybackRate::Factor(factor));
+
+        let semitones = f64::from_bits(7.0f64.to_bits() | 1);
+        handle.send_playback_rate(PlaybackRate::Factor(2.0));
+        handle.send_playback_rate(PlaybackRate::Semitones(semitones));
+        assert_eq!(handle.playback_rate(), PlaybackRate::Semitones(semitones));
+    }
+
+    #[test]
+    fn mailbox_concurrent_writes() {
+        let handle = SoundHandle::new(stereo_ramp(100));
+        // factors are always positive and semitones always negative, so a
+        // value applied as the wrong variant is detected
+        let writers: Vec<_> = (0..4)
+            .map(|thread| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    for i in 0..2000 {
+                        let value = 1.0 + (thread * 2000 + i) as f64 / 10000.0;
+                        if i % 2 == 0 {
+                            handle.send_playback_rate(PlaybackRate::Factor(value));
+                        } else {
+                            handle.send_playback_rate(PlaybackRate::Semitones(-value));
+                        }
+                    }
+                })
+            })
+            .collect();
+        let reader = {
+            let handle = handle.clone();
+            std::thread::spawn(move || {
+                for _ in 0..2000 {
+                    match handle.playback_rate() {
+                        PlaybackRate::Factor(factor) => assert!(factor > 0.0),
+                        PlaybackRate::Semitones(semitones) => assert!(semitones <= 0.0),
+                    }
+                }
+            })
+        };
+        for thread in writers {
+            thread.join().unwrap();
+        }
+        reader.join().unwrap();
+
+        // a write that happens after the others wins
+        handle.send_playback_rate(PlaybackRate::Factor(0.5));
+        let last = handle.clone();
+        std::thread::spawn(move || last.send_playback_rate(PlaybackRate::Semitones(-5.0)))
+            .join()
+            .unwrap();
+        assert_eq!(handle.playback_rate(), PlaybackRate::Semitones(-5.0));
+    }
+
+    #[test]
+    fn compacted_sound_plays_the_same() {
+        let frames: Vec<Frame> = (0..500)
+            .map(|i| Frame::from_mono((i as f32 * 0.1).sin()))
+            .collect();
+        let sound = Sound::from_frames(44100, &frames);
+        let compacted = sound.compacted();
+        assert!(compacted.frames.is_mono());
+        assert_eq!(compacted.frames(), sound.frames());
+        assert_eq!(compacted.frame_count(), 500);
+
+        let (mut sound, mut compacted) = (sound, compacted);
+        sound.set_playback_rate(PlaybackRate::Factor(0.7));
+        compacted.set_playback_rate(PlaybackRate::Factor(0.7));
+        for _ in 0..800 {
+            assert_eq!(sound.next_frame(48000), compacted.next_frame(48000));
+        }
+
+        // different channels can't be compacted
+        let stereo = stereo_ramp(100);
+        assert!(!stereo.compacted().frames.is_mono());
+    }
+
+    #[test]
+    fn baked_commands_play_the_same() {
+        let mut sound = stereo_ramp(1000);
+        sound.set_volume(0.8);
+        sound.add_command(Command::new(Change::Volume(0.2), Easing::Linear, 0.0, 0.3));
+        sound.add_command(Command::new(Change::Panning(0.2), Easing::CircIn, 0.1, 0.2));
+        sound.add_command(Command::new(Change::Pause(true), Easing::Linear, 0.9, 0.0));
+        let mut baked = sound.bake_commands(SAMPLE_RATE);
+
+        // only the pause is left to evaluate
+        assert_eq!(baked.commands.len(), 1);
+        assert_eq!((baked.volume(), baked.panning()), (1.0, 0.5));
+
+        let (expected, actual) = (
+            render(&mut sound, SAMPLE_RATE, 1000),
+            render(&mut baked, SAMPLE_RATE, 1000),
+        );
+        for (i, (expected, actual)) in expected.iter().zip(&actual).enumerate() {
+            assert!(
+                (expected.left - actual.left).abs() < 1e-5
+                    && (expected.right - actual.right).abs() < 1e-5,
+                "frame {i}: {expected:?} != {actual:?}"
+            );
+        }
+        assert!(baked.paused());
+    }
+
+    #[test]
+    fn restart_rearms_or_clears_commands() {
+        let with_commands = || {
+            let mut sound = stereo_ramp(1000);
+            sound.add_command(Command::new(Change::Volume(0.0), Easing::Linear, 0.05, 0.2));
+            sound.add_command(Command::new(Change::Panning(1.0), Easing::Linear, 0.5, 0.1));
+            sound
+        };
+        let volumes = |sound: &mut Sound| -> Vec<f32> {
+            (0..150)
+                .map(|_| {
+                    sound.next_frame(SAMPLE_RATE);
+                    sound.volume()
+                })
+                .collect()
+        };
+
+        // rearmed commands replay exactly like they did the first time
+        let mut sound = with_commands();
+        let first = volumes(&mut sound);
+        assert!(first[149] < 0.6);
+        sound.restart(RestartCommands::Rearm);
+        assert_eq!(sound.volume(), 1.0);
+        assert_eq!(sound.commands.len(), 2);
+        assert_eq!(volumes(&mut sound), first);
+        assert!(sound.index() < 160);
+
+        // cleared commands keep the current value and never run again
+        let mut sound = with_commands();
+        volumes(&mut sound);
+        let volume = sound.volume();
+        sound.restart(RestartCommands::Clear);
+        assert!(sound.commands.is_empty());
+        assert_eq!(sound.volume(), volume);
+        render(&mut sound, SAMPLE_RATE, 700);
+        assert_eq!(sound.volume(), volume);
+        assert_eq!(sound.panning(), 0.5);
+    }
+
+    #[test]
+    fn cached_bytes_round_trip() {
+        let sound = stereo_ramp(500);
+        let cached = Sound::from_cached_bytes(&sound.to_bytes()).unwrap();
+        assert_eq!(cached.frames, sound.frames);
+        assert_eq!(cached.sample_rate(), sound.sample_rate());
+        assert_eq!(cached.channels_in_source(), 2);
+
+        let mono = Sound::from_mono_samples(22050, &[0.1, -0.2, 0.3]).compacted();
+        let cached = Sound::from_cached_bytes(&mono.to_bytes()).unwrap();
+        assert!(cached.frames.is_mono());
+        assert_eq!(cached.frames, mono.frames);
+        assert_eq!(cached.channels_in_source(), 1);
+    }
+
+    #[test]
+    fn cached_bytes_reject_invalid_data() {
+        let bytes = stereo_ramp(10).to_bytes();
+        let invalid = |bytes: &[u8]| {
+            matches!(
+                Sound::from_cached_bytes(bytes),
+                Err(KaError::InvalidCachedSound(_))
+            )
+        };
+        assert!(invalid(&bytes[..bytes.len() - 1]));
+        assert!(invalid(&bytes[..4]));
+
+        let mut version = bytes.clone();
+        version[4] = 0xff;
+        assert!(invalid(&version));
+
+        let mut sample_rate = bytes.clone();
+        sample_rate[11..15].copy_from_slice(&0u32.to_le_bytes());
+        assert!(invalid(&sample_rate));
+    }
+
+    /// Return a 16-bit WAV file with interleaved `samples`.
+    #[cfg(feature = "symphonia")]
+    fn wav_bytes(channels: u16, samples: &[i16]) -> Vec<u8> {
+        let data_len = samples.len() as u32 * 2;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&channels.to_le_bytes());
+        wav.extend_from_slice(&44100u32.to_le_bytes());
+        wav.extend_from_slice(&(44100 * 2 * channels as u32).to_le_bytes());
+        wav.extend_from_slice(&(2 * channels).to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[cfg(feature = "symphonia")]
+    #[test]
+    fn decoding_an_empty_stream_fails() {
+        assert!(matches!(
+            Sound::from_bytes(wav_bytes(2, &[])),
+            Err(KaError::EmptyAudio)
+        ));
+    }
+
+    #[cfg(feature = "symphonia")]
+    #[test]
+    fn decoded_mono_wav_has_one_source_channel() {
+        let samples: Vec<i16> = (0..100).map(|i| i * 100).collect();
+        let sound = Sound::from_bytes(wav_bytes(1, &samples)).unwrap();
+        assert_eq!(sound.channels_in_source(), 1);
+        assert_eq!(sound.sample_rate(), 44100);
+        assert_eq!(sound.frame_count(), 100);
+
+        // the channel is played on both sides
+        let frame = sound.frames()[50];
+        assert_eq!(frame.left, frame.right);
+        assert!((frame.left - 5000.0 / 32768.0).abs() < 1e-6);
+
+        let stereo = Sound::from_bytes(wav_bytes(2, &samples)).unwrap();
+        assert_eq!(stereo.channels_in_source(), 2);
+    }
+
+    /// Return a silent 44.1 kHz mono CBR MP3 with `frames` MPEG frames, and an
+    /// Info tag that stores the encoder delay and padding in samples, like
+    /// the ones written by ffmpeg.
+    #[cfg(feature = "symphonia")]
+    fn mp3_bytes(frames: usize, delay: u32, padding: u32) -> Vec<u8> {
+        // MPEG-1 layer III, 128 kbps, 44100 Hz, no padding, mono
+        const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0xC0];
+        const FRAME_LEN: usize = 417;
+        const SIDE_INFO_LEN: usize = 17;
+
+        // the tag lives in a silent frame in front of the audio
+        let mut info = HEADER.to_vec();
+        info.resize(HEADER.len() + SIDE_INFO_LEN, 0);
+        info.extend_from_slice(b"Info");
+        info.extend_from_slice(&1u32.to_be_bytes()); // only the frame count
+        info.extend_from_slice(&(frames as u32).to_be_bytes());
+        info.extend_from_slice(b"Lavf58.76");
+        info.resize(info.len() + 12, 0); // revision, lowpass, replay gain, flags
+                                         // the decoder delay of 529 samples is included by the decoder
+        let trim = (delay - 529) << 12 | (padding + 529);
+        info.extend_from_slice(&trim.to_be_bytes()[1..]);
+        info.resize(FRAME_LEN, 0);
+
+        // zeroed side info decodes to a frame of silence
+        let mut mp3 = info;
+        for _ in 0..frames {
+            mp3.extend_from_slice(&HEADER);
+            mp3.resize(mp3.len() + FRAME_LEN - HEADER.len(), 0);
+        }
+        mp3
+    }
+
+    #[cfg(feature = "symphonia")]
+    #[test]
+    fn gapless_mp3_has_no_delay_or_padding() {
+        let decode = |gapless: bool| {
+            let settings = DecodeSettings {
+                extension: Some("mp3".to_string()),
+                gapless,
+                ..Default::default()
+            };
+            let cursor = std::io::Cursor::new(mp3_bytes(10, 1105, 471));
+            Sound::from_media_source_ex(cursor, &settings).unwrap()
+        };
+
+        // without trimming, the delay and padding would play as a gap every
+        // time the sound loops
+        assert_eq!(decode(false).frame_count(), 10 * 1152);
+        let sound = decode(true);
+        assert_eq!(sound.frame_count(), 10 * 1152 - 1105 - 471);
+        assert_eq!(sound.sample_rate(), 44100);
+        assert_eq!(sound.channels_in_source(), 1);
+    }
+
+    #[test]
+    fn skip_matches_rendering() {
+        let prepare = || {
+            let mut sound = stereo_ramp(1000);
+            sound.set_loop_index(200..=700);
+            sound.set_loop_enabled(true);
+            sound.set_latency_offset(50);
+            sound.set_max_play_time(Duration::from_secs(3));
+            sound.set_rate_automation(&[1.0, 1.25, 1.5, 0.75]);
+            sound.add_command(Command::new(Change::Volume(0.3), Easing::Linear, 0.5, 1.0));
+            sound.set_lfo(LfoTarget::Volume, Lfo::new(LfoRate::Hz(2.0), 0.5));
+            sound
+        };
+        let mut skipped = prepare();
+        skipped.skip(Duration::from_millis(1234));
+        let mut rendered = prepare();
+        render(&mut rendered, SAMPLE_RATE, 1234);
+
+        assert_eq!(
+            render(&mut skipped, SAMPLE_RATE, 2000),
+            render(&mut rendered, SAMPLE_RATE, 2000)
+        );
+        assert!(skipped.finished() && rendered.finished());
+
+        // skipping past the maximum play time finishes the sound
+        let mut sound = prepare();
+        sound.skip(Duration::from_secs(4));
+        assert!(sound.finished());
+    }
+
+    #[test]
+    fn rate_automation_ramp_reaches_its_target() {
+        // ramp from 1.0 down to 0.5 over 100 frames
+        let factors: Vec<f64> = (0..100).map(|i| 1.0 - i as f64 / 198.0).collect();
+        let mut sound = stereo_ramp(1000);
+        sound.set_rate_automation(&factors);
+
+        let position = |sound: &Sound| sound.index() as f64 + sound.fractional_position;
+        let start = position(&sound);
+        let mut expected = 0.0;
+        for i in 0..150 {
+            sound.next_frame(SAMPLE_RATE);
+            expected += factors[i.min(99)];
+            assert!(
+                (position(&sound) - start - expected).abs() < 1e-9,
+                "frame {i}: {} != {expected}",
+                position(&sound) - start,
+            );
+            if i == 98 {
+                assert_eq!(sound.rate_automation_remaining(), Some(1));
+            }
+        }
+
+        // the target is reached on the last scheduled frame and then held
+        assert_eq!(factors[99], 0.5);
+        assert_eq!(sound.rate_automation_remaining(), Some(0));
+        let before = position(&sound);
+        sound.next_frame(SAMPLE_RATE);
+        assert!((position(&sound) - before - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lanes_are_cancelled_and_replaced_alone() {
+        let mut sound = stereo_ramp(1000);
+        sound.add_command(Command::new(Change::Volume(0.0), Easing::Linear, 0.0, 0.2).with_lane(1));
+        sound
+            .add_command(Command::new(Change::Panning(1.0), Easing::Linear, 0.0, 0.4).with_lane(2));
+        render(&mut sound, SAMPLE_RATE, 100);
+
+        // the volume stops where it is, the panning keeps moving
+        sound.cancel_lane(1);
+        let (volume, panning) = (sound.volume(), sound.panning());
+        assert!(volume > 0.4 && volume < 0.6, "{volume}");
+        render(&mut sound, SAMPLE_RATE, 100);
+        assert_eq!(sound.volume(), volume);
+        assert!(sound.panning() > panning);
+
+        // the replacement starts from the current value instead of jumping
+        let panning = sound.panning();
+        sound.replace_lane(
+            2,
+            vec![Command::new(Change::Panning(0.0), Easing::Linear, 0.0, 0.1)],
+        );
+        assert_eq!(sound.panning(), panning);
+        render(&mut sound, SAMPLE_RATE, 1);
+        assert!((sound.panning() - panning).abs() < 0.02);
+        render(&mut sound, SAMPLE_RATE, 50);
+        let halfway = sound.panning();
+        assert!((halfway - panning / 2.0).abs() < 0.02, "{halfway}");
+        render(&mut sound, SAMPLE_RATE, 100);
+        assert!(sound.panning() < 0.01);
+        assert_eq!(sound.volume(), volume);
+        assert!(sound.commands.is_empty());
+    }
+
+    #[test]
+    fn intro_plays_once_then_the_body_loops() {
+        let mut sound = stereo_ramp(1000);
+        render(&mut sound, SAMPLE_RATE, 300);
+        sound.set_intro_loop(Duration::from_millis(200));
+
+        let indices: Vec<usize> = render(&mut sound, SAMPLE_RATE, 2800)
+            .iter()
+            .map(|frame| (frame.left * 1000.0).round() as usize)
+            .collect();
+
+        // the intro restarts from the beginning and is never heard again
+        assert_eq!(indices[..200], (0..200).collect::<Vec<_>>());
+        assert!(indices[200..].iter().all(|&index| index >= 200));
+
+        // the body repeats every 800 frames
+        let wraps: Vec<usize> = (1..indices.len())
+            .filter(|&i| indices[i] < indices[i - 1])
+            .collect();
+        assert_eq!(wraps, [999, 1799, 2599]);
+        assert_eq!(indices[1000..1800], indices[1800..2600]);
+    }
+
+    #[test]
+    fn loop_seam_preview_jumps_to_the_loop_start() {
+        let mut sound = stereo_ramp(1000);
+        sound.set_loop_index(300..=700);
+        render(&mut sound, SAMPLE_RATE, 100);
+        sound.preview_loop_seam(0.05, 0.03);
+
+        let indices: Vec<usize> = render(&mut sound, SAMPLE_RATE, 400)
+            .iter()
+            .map(|frame| (frame.left * 1000.0).round() as usize)
+            .collect();
+
+        // every seam goes from the end of the loop to its start, and the
+        // preview repeats every 50 + 30 frames
+        let seams: Vec<usize> = (1..indices.len())
+            .filter(|&i| indices[i] == 300 && indices[i - 1] != 300)
+            .collect();
+        assert_eq!(seams.len(), 5);
+        for (n, &seam) in seams.iter().enumerate() {
+            assert_eq!(indices[seam - 1], 699);
+            assert!(indices[seam - 10..seam]
+                .iter()
+                .all(|&i| (680..700).contains(&i)));
+            assert!(indices[seam..seam + 25]
+                .iter()
+                .all(|&i| (300..330).contains(&i)));
+            if n > 0 {
+                assert_eq!(seam - seams[n - 1], 80);
+            }
+        }
+    }
+
+    #[test]
+    fn custom_ease_is_sampled_every_frame() {
+        /// A curve in 4 steps that records where it was sampled.
+        struct Steps(Arc<Mutex<Vec<f32>>>);
+        impl crate::Ease for Steps {
+            fn ease(&self, t: f32) -> f32 {
+                self.0.lock().push(t);
+                (t * 4.0).floor() / 4.0
+            }
+        }
+
+        let samples = Arc::new(Mutex::new(vec![]));
+        let mut sound = stereo_ramp(1000);
+        sound.add_command(
+            Command::new(Change::Volume(0.0), Easing::Linear, 0.0, 0.1)
+                .with_ease(Steps(samples.clone())),
+        );
+        let volumes: Vec<f32> = (0..150)
+            .map(|_| {
+                sound.next_frame(SAMPLE_RATE);
+                sound.volume()
+            })
+            .collect();
+
+        // once per frame, at the elapsed fraction of the duration
+        let samples = samples.lock();
+        assert_eq!(samples.len(), 100);
+        for (i, t) in samples.iter().enumerate() {
+            assert!((t - i as f32 / 100.0).abs() < 1e-4, "sample {i}: {t}");
+        }
+        for (i, volume) in volumes[..100].iter().enumerate() {
+            let expected = 1.0 - (i / 25) as f32 / 4.0;
+            assert!((volume - expected).abs() < 1e-6, "frame {i}");
+        }
+        // the last sampled value is kept once the command finished
+        assert_eq!(volumes[149], volumes[99]);
+    }
+
+    #[test]
+    fn volume_smoothing_ramps_a_step_change() {
+        let mut sound = Sound::from_mono_samples(48000, &[1.0; 48000]);
+        sound.set_volume_smoothing(Duration::from_millis(5));
+        let before = render(&mut sound, 48000, 100);
+        sound.set_volume(0.0);
+        let after = render(&mut sound, 48000, 4800);
+
+        // the level moves toward the target on every frame without jumping
+        let mut last = before[99].left;
+        for (i, frame) in after.iter().enumerate() {
+            assert!(frame.left <= last, "frame {i} went up");
+            assert!(last - frame.left < 0.01, "frame {i} jumped");
+            last = frame.left;
+        }
+        assert!(after[240].left > 0.2, "{}", after[240].left);
+        assert!(after[4799].left < 1e-3);
+    }
+
+    #[test]
+    fn occlusion_muffles_high_frequencies() {
+        // level of the sound once the filter settled
+        let level = |sound: &Sound, amount: f32| {
+            let mut sound = sound.clone();
+            sound.set_occlusion_model(OcclusionModel {
+                attenuation_db: 0.0,
+                smoothing: Duration::ZERO,
+                ..Default::default()
+            });
+            sound.set_occlusion(amount);
+            let tail = &render(&mut sound, 44100, 20000)[10000..];
+            (tail.iter().map(|f| f.left * f.left).sum::<f32>() / tail.len() as f32).sqrt()
+        };
+
+        // well above the 600 Hz cutoff of full occlusion
+        let high = sine(8000.0);
+        assert!(level(&high, 1.0) < level(&high, 0.0) * 0.05);
+
+        // DC isn't touched by the lowpass
+        let dc = Sound::from_mono_samples(44100, &[0.5; 44100]);
+        let (occluded, clear) = (level(&dc, 1.0), level(&dc, 0.0));
+        assert!((occluded - clear).abs() < 1e-3, "{occluded} != {clear}");
+    }
+
+    #[test]
+    fn silence_below_the_threshold_is_skipped() {
+        let quiet = |threshold: f32| {
+            let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &[0.005; 1000]);
+            sound.set_silence_threshold(threshold);
+            render(&mut sound, SAMPLE_RATE, 100);
+            sound
+        };
+        let mut twin = quiet(0.0);
+        let mut sound = quiet(0.01);
+
+        // quiet, but audible sounds are still rendered
+        assert!(sound.outputting_silence());
+        assert!(!sound.skipping_silence);
+
+        // the held frames are already below the threshold, so the sound
+        // skips right away, while exact silence needs the frames to drain
+        let mut exact = quiet(0.0);
+        sound.set_volume(0.0);
+        exact.set_volume(0.0);
+        render(&mut sound, SAMPLE_RATE, 1);
+        render(&mut exact, SAMPLE_RATE, 1);
+        render(&mut twin, SAMPLE_RATE, 1);
+        assert!(sound.skipping_silence);
+        assert!(!exact.skipping_silence);
+
+        // skipped sounds keep their position
+        let frames = render(&mut sound, SAMPLE_RATE, 300);
+        render(&mut twin, SAMPLE_RATE, 300);
+        assert!(frames.iter().all(|frame| *frame == Frame::ZERO));
+        assert!(sound.skipping_silence);
+        assert_eq!(sound.index(), twin.index());
+        assert_eq!(sound.position_seconds(), twin.position_seconds());
+
+        sound.set_volume(1.0);
+        assert_eq!(
+            render(&mut sound, SAMPLE_RATE, 100)[10..],
+            render(&mut twin, SAMPLE_RATE, 100)[10..]
+        );
+    }
+
+    #[test]
+    fn stop_with_fade_attenuates_then_removes() {
+        let mut renderer = DefaultRenderer::default();
+        let sound = SoundHandle::new(Sound::from_mono_samples(SAMPLE_RATE, &[1.0; 1000]));
+        renderer.add_sound(sound.clone());
+        assert_eq!(renderer.next_frame(SAMPLE_RATE).left, 1.0);
+
+        sound.stop_with_fade(Duration::from_millis(100), Easing::Linear);
+        let faded: Vec<f32> = (0..100)
+            .map(|_| renderer.next_frame(SAMPLE_RATE).left)
+            .collect();
+        assert!((faded[50] - 0.5).abs() < 0.05, "{}", faded[50]);
+        assert!(faded.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(faded[99] < 0.05);
+
+        for _ in 0..5 {
+            renderer.next_frame(SAMPLE_RATE);
+        }
+        assert!(sound.finished());
+        assert_eq!(renderer.sound_count(), 0);
+
+        // a zero duration stops the sound immediately
+        let mut sound = stereo_ramp(1000);
+        sound.stop_with_fade(Duration::ZERO, Easing::Linear);
+        assert!(sound.next_frame(SAMPLE_RATE).is_none());
+    }
+
+    #[test]
+    fn seeks_clamp_out_of_range_positions() {
+        let mut sound = stereo_ramp(1000);
+        let mut seek = |seek: &dyn Fn(&mut Sound)| {
+            sound.seek_to_index(500);
+            seek(&mut sound);
+            sound.index()
+        };
+        assert_eq!(seek(&|sound| sound.seek_to(-5.0)), 0);
+        assert_eq!(seek(&|sound| sound.seek_to(1e300)), 1000);
+        assert_eq!(seek(&|sound| sound.seek_to(f64::NAN)), 0);
+        assert_eq!(seek(&|sound| sound.seek_to(f64::INFINITY)), 1000);
+        assert_eq!(seek(&|sound| sound.seek_to(f64::NEG_INFINITY)), 0);
+        assert_eq!(seek(&|sound| sound.seek_by(-1e12)), 0);
+        assert_eq!(seek(&|sound| sound.seek_by(1e300)), 1000);
+        assert_eq!(seek(&|sound| sound.seek_by_frames(i64::MIN)), 0);
+        assert_eq!(seek(&|sound| sound.seek_by_frames(i64::MAX)), 1000);
+        assert_eq!(seek(&|sound| sound.seek_to_index(usize::MAX)), 1000);
+
+        let points = LoopPoints::from_range_secs(-1.0..=1e300, SAMPLE_RATE);
+        assert_eq!((points.start, points.end), (0, usize::MAX));
+    }
+
+    #[test]
+    fn output_frame_count_matches_render() {
+        let mut sound = Sound::from_mono_samples(44100, &vec![0.5; 44100]);
+        sound.set_playback_rate(PlaybackRate::Factor(2.0));
+        // a new sound has its first 3 frames in the resampler, so 44097
+        // source frames are left, at 1.8375 source frames per output frame
+        let counted = sound.output_frame_count(48000);
+
+        let mut rendered = 0;
+        while sound.next_frame(48000).is_some() {
+            rendered += 1;
+        }
+        assert_eq!(counted, Some(23999));
+        assert_eq!(rendered, 23999);
+
+        sound.set_loop_enabled(true);
+        sound.seek_to(0.0);
+        assert_eq!(sound.output_frame_count(48000), None);
+    }
+
+    #[test]
+    fn playback_rate_for_duration_stretches_to_target() {
+        let mut sound = stereo_ramp(2000);
+        assert_eq!(
+            sound.playback_rate_for_duration(Duration::from_secs(1)),
+            PlaybackRate::Factor(2.0)
+        );
+
+        // the direction is kept
+        sound.set_playback_rate(PlaybackRate::Factor(-1.0));
+        sound.set_duration(Duration::from_secs(4));
+        assert_eq!(sound.playback_rate(), PlaybackRate::Factor(-0.5));
+    }
+
+    #[test]
+    fn passthrough_is_bit_exact() {
+        let frames: Vec<Frame> = (0..1000)
+            .map(|i| Frame::new((i as f32 * 0.37).sin(), (i as f32 * 0.11).cos() * 0.3))
+            .collect();
+        let mut sound = Sound::from_frames(SAMPLE_RATE, &frames);
+        // the sound finishes with its last 3 frames still in the resampler
+        let rendered = render(&mut sound, SAMPLE_RATE, 997);
+        assert_eq!(rendered, frames[..997]);
+        assert!(sound.next_frame(SAMPLE_RATE).is_none());
+    }
+
+    #[test]
+    fn from_samples_constructors() {
+        let mono = Sound::from_mono_samples(SAMPLE_RATE, &[0.1, -0.2, 0.3]);
+        assert_eq!(mono.frame_count(), 3);
+        assert_eq!(mono.channels_in_source(), 1);
+        assert_eq!(mono.frames()[1], Frame::new(-0.2, -0.2));
+
+        let stereo = Sound::from_stereo_samples(SAMPLE_RATE, &[[0.1, 0.2], [-0.3, 0.4]]);
+        assert_eq!(stereo.frame_count(), 2);
+        assert_eq!(stereo.channels_in_source(), 2);
+        assert_eq!(
+            &*stereo.frames(),
+            [Frame::new(0.1, 0.2), Frame::new(-0.3, 0.4)]
+        );
+    }
+
+    #[test]
+    fn dc_blocker_removes_offset() {
+        const RATE: u32 = 48000;
+        let sine = |offset: f32| {
+            let samples: Vec<f32> = (0..RATE)
+                .map(|i| offset + 0.5 * (i as f32 / RATE as f32 * 1000.0 * TAU).sin())
+                .collect();
+            let mut sound = Sound::from_mono_samples(RATE, &samples);
+            sound.set_dc_block(true);
+            // skip the first half second while the filter settles
+            render(&mut sound, RATE, RATE as usize / 2);
+            render(&mut sound, RATE, 24000)
+        };
+        let mean =
+            |frames: &[Frame]| frames.iter().map(|f| f.left).sum::<f32>() / frames.len() as f32;
+        let rms = |frames: &[Frame]| {
+            (frames.iter().map(|f| f.left * f.left).sum::<f32>() / frames.len() as f32).sqrt()
+        };
+
+        let offset = sine(0.4);
+        assert!(mean(&offset).abs() < 1e-3, "{}", mean(&offset));
+        // the tone itself passes unchanged
+        let clean = sine(0.0);
+        let expected = 0.5 / 2.0f32.sqrt();
+        assert!((rms(&clean) - expected).abs() < 1e-3, "{}", rms(&clean));
+        assert!((rms(&offset) - expected).abs() < 1e-3, "{}", rms(&offset));
+    }
+
+    #[test]
+    fn mix_sums_overlapping_frames() {
+        let tone = |hz: f32, len: usize| {
+            let samples: Vec<f32> = (0..len)
+                .map(|i| 0.25 * (i as f32 / SAMPLE_RATE as f32 * hz * TAU).sin())
+                .collect();
+            Sound::from_mono_samples(SAMPLE_RATE, &samples)
+        };
+        let (low, high) = (tone(50.0, 1000), tone(120.0, 600));
+        let mixed = Sound::mix(&[&low, &high]).unwrap();
+        assert_eq!(mixed.frame_count(), 1000);
+
+        let (low, high, mixed) = (low.frames(), high.frames(), mixed.frames());
+        for i in 0..600 {
+            assert_eq!(mixed[i], low[i] + high[i]);
+        }
+        // the shorter sound is padded with silence
+        assert_eq!(mixed[600..], low[600..]);
+
+        let gained = Sound::mix_with_gain(&[(&tone(50.0, 10), 0.5)]).unwrap();
+        assert_eq!(gained.frames()[3], tone(50.0, 10).frames()[3] * 0.5);
+
+        let other_rate = Sound::from_mono_samples(SAMPLE_RATE * 2, &[0.0; 10]);
+        assert!(matches!(
+            Sound::mix(&[&tone(50.0, 10), &other_rate]),
+            Err(KaError::SampleRateMismatch(..))
+        ));
+    }
+
+    #[test]
+    fn play_reversed_plays_from_the_last_frame() {
+        let source = stereo_ramp(1000);
+        let frames = source.frames();
+        let mut sound = source.clone();
+        sound.play_reversed();
+
+        let mut rendered = Vec::new();
+        while let Some(frame) = sound.next_frame(SAMPLE_RATE) {
+            rendered.push(frame);
+            assert!(rendered.len() <= 1000, "the sound didn't finish");
+        }
+        // like when playing forwards, the sound finishes with the 3 frames
+        // at the end (here the first frames) still in the resampler
+        let expected: Vec<Frame> = frames[3..].iter().rev().copied().collect();
+        assert_eq!(rendered, expected);
+        assert!(sound.finished());
+        assert!(sound.next_frame(SAMPLE_RATE).is_none());
+    }
+
+    #[test]
+    fn loop_end_past_the_sound_is_clamped() {
+        let mut sound = stereo_ramp(1000);
+        sound.set_loop_index(900..=5000);
+        sound.set_loop_enabled(true);
+        assert!(sound.loop_enabled);
+        sound.seek_to_index(950);
+
+        // the sound wraps at its last frame instead of finishing. the first
+        // frames are the ones from before the seek that are in the resampler
+        let rendered = render(&mut sound, SAMPLE_RATE, 300);
+        assert!(!sound.finished());
+        assert!(rendered[3..].iter().all(|frame| frame.left >= 0.9));
+        assert!(rendered[100..].iter().any(|frame| frame.left < 0.95));
+    }
+
+    #[test]
+    fn inverted_loop_disables_looping() {
+        let mut sound = stereo_ramp(1000);
+        let (start, end) = (700, 300);
+        sound.set_loop_index(start..=end);
+        sound.set_loop_enabled(true);
+        assert!(!sound.loop_enabled);
+
+        sound.seek_to_index(900);
+        render(&mut sound, SAMPLE_RATE, 200);
+        assert!(sound.finished());
+    }
+
+    #[test]
+    fn seek_while_paused_resumes_from_the_new_position() {
+        for rate in [1.0, 0.75] {
+            let mut sound = stereo_ramp(1000);
+            sound.set_playback_rate(PlaybackRate::Factor(rate));
+            sound.seek_to_index(800);
+            render(&mut sound, SAMPLE_RATE, 20);
+            sound.pause();
+            render(&mut sound, SAMPLE_RATE, 20);
+            sound.seek_to_index(200);
+            sound.resume();
+
+            // nothing from before the seek (around 0.8) is heard
+            let rendered = render(&mut sound, SAMPLE_RATE, 50);
+            assert!(
+                rendered.iter().all(|frame| frame.left <= 0.26),
+                "{rendered:?}"
+            );
+            assert!(rendered[49].left > 0.2);
+        }
+    }
+
+    #[test]
+    fn try_add_command_rejects_invalid_timing() {
+        let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &[1.0; 1000]);
+        sound.set_max_command_delay(Some(Duration::from_secs(60)));
+        let volume = |start_after, duration| {
+            Command::new(Change::Volume(0.5), Easing::Linear, start_after, duration)
+        };
+        let rejected = |sound: &mut Sound, command| {
+            matches!(
+                sound.try_add_command(command),
+                Err(KaError::InvalidCommand(_))
+            )
+        };
+        assert!(rejected(&mut sound, volume(0.0, -1.0)));
+        assert!(rejected(&mut sound, volume(0.0, f64::NAN)));
+        assert!(rejected(&mut sound, volume(f64::INFINITY, 1.0)));
+        assert!(rejected(&mut sound, volume(120.0, 1.0)));
+        let nan = Command::new(Change::Volume(f32::NAN), Easing::Linear, 0.0, 1.0);
+        assert!(rejected(&mut sound, nan));
+        assert_eq!(sound.commands.len(), 0);
+
+        // a zero duration applies the change instantly
+        sound.try_add_command(volume(0.0, 0.0)).unwrap();
+        sound.next_frame(SAMPLE_RATE);
+        assert_eq!(sound.volume(), 0.5);
+    }
+
+    #[test]
+    fn loop_tween_keeps_the_playhead_continuous() {
+        // tween the loop region over 100 frames and return the playhead
+        // position after each of the next 150 frames
+        let sweep = |playhead: usize, reversed: bool, to: RangeInclusive<usize>| {
+            let mut sound = stereo_ramp(1000);
+            sound.set_loop_index(100..=900);
+            sound.set_loop_enabled(true);
+            sound.set_reversed(reversed);
+            sound.seek_to_index(playhead);
+            sound.add_command(Command::new(
+                Change::LoopIndex(to),
+                Easing::Linear,
+                0.0,
+                0.1,
+            ));
+            (0..150)
+                .map(|_| {
+                    sound.next_frame(SAMPLE_RATE);
+                    sound.index()
+                })
+                .collect::<Vec<_>>()
+        };
+        let continuous = |indices: &[usize], step: isize| {
+            indices
+                .windows(2)
+                .all(|pair| pair[1] as isize - pair[0] as isize == step)
+        };
+
+        // the loop start moves past the playhead
+        let indices = sweep(300, false, 600..=950);
+        assert!(continuous(&indices, 1), "{indices:?}");
+
+        // the loop end moves back past the playhead (without landing on the
+        // same frame, which would be a regular wrap). the playhead continues
+        // while the tween runs, and wraps into the loop once it completes
+        let indices = sweep(503, false, 100..=300);
+        assert!(continuous(&indices[..99], 1), "{indices:?}");
+        assert!((100..300).contains(&indices[149]), "{indices:?}");
+
+        // the same while playing backwards
+        let indices = sweep(503, true, 700..=900);
+        assert!(continuous(&indices[..99], -1), "{indices:?}");
+        assert!((700..900).contains(&indices[149]), "{indices:?}");
+    }
+
+    #[test]
+    fn remaining_halves_at_double_rate() {
+        let mut sound = stereo_ramp(2000);
+        sound.seek_to_index(1000);
+        assert_eq!(sound.remaining_seconds(), 1.0);
+        sound.set_playback_rate(PlaybackRate::Factor(2.0));
+        assert_eq!(sound.remaining_seconds(), 0.5);
+        assert_eq!(sound.remaining(), Duration::from_millis(500));
+
+        // playing backwards counts the frames before the playhead
+        sound.set_playback_rate(PlaybackRate::Factor(-4.0));
+        assert_eq!(sound.remaining_seconds(), 0.25);
+        sound.set_playback_rate(PlaybackRate::Factor(0.0));
+        assert_eq!(sound.remaining(), Duration::MAX);
+    }
+
+    #[test]
+    fn overshooting_position_command_keeps_playing() {
+        let mut sound = stereo_ramp(1000);
+        sound.seek_to(0.5);
+        // elastic easing overshoots past the end of the sound
+        let change = Change::Position(0.998);
+        sound.add_command(Command::new(change, Easing::ElasticOut, 0.0, 0.3));
+        for _ in 0..300 {
+            assert!(sound.next_frame(SAMPLE_RATE).is_some());
+            assert!(sound.index() < 1000);
+        }
+
+        // overshoot of other parameters can be clamped per command
+        sound.seek_to(0.0);
+        let change = Change::Volume(0.5);
+        let command = Command::new(change, Easing::ElasticOut, 0.0, 0.3).clamped(true);
+        sound.add_command(command);
+        for _ in 0..300 {
+            sound.next_frame(SAMPLE_RATE);
+            assert!((0.5..=1.0).contains(&sound.volume()));
+        }
+    }
+
+    #[test]
+    fn loop_period_does_not_drift() {
+        const SOURCE_RATE: u32 = 44100;
+        const OUTPUT_RATE: u32 = 48000;
+        let mut sound = Sound::from_mono_samples(SOURCE_RATE, &vec![0.5; 3000]);
+        sound.set_loop_index(1000..=2000);
+        sound.set_loop_enabled(true);
+        sound.seek_to_index(1000);
+
+        // output frames at which the playhead wrapped back to the loop start
+        let mut wraps = Vec::new();
+        let mut index = sound.index();
+        let mut frame = 0;
+        while wraps.len() < 3000 {
+            sound.next_frame(OUTPUT_RATE);
+            frame += 1;
+            if sound.index() < index {
+                wraps.push(frame);
+            }
+            index = sound.index();
+        }
+
+        let period = 1000.0 * OUTPUT_RATE as f64 / SOURCE_RATE as f64;
+        for (i, &wrap) in wraps.iter().enumerate() {
+            let expected = wraps[0] as f64 + i as f64 * period;
+            assert!(
+                (wrap as f64 - expected).abs() <= 1.0,
+                "wrap {i}: {wrap} != {expected}"
+            );
+        }
+    }
+
+    /// Return one second of a sine at `hz`, sampled at 44100 Hz.
+    fn sine(hz: f32) -> Sound {
+        let samples: Vec<f32> = (0..44100)
+            .map(|i| 0.5 * (i as f32 / 44100.0 * hz * TAU).sin())
+            .collect();
+        Sound::from_mono_samples(44100, &samples)
+    }
+
+    #[test]
+    fn estimate_pitch_of_a_sine() {
+        let pitch = sine(440.0).estimate_pitch().unwrap();
+        assert!((pitch - 440.0).abs() < 2.0, "{pitch}");
+
+        let silence = Sound::from_mono_samples(44100, &[0.0; 44100]);
+        assert_eq!(silence.estimate_pitch(), None);
+    }
+
+    #[test]
+    fn pitch_shifted_keeps_duration() {
+        // frequency of the sound from its rising zero crossings, skipping
+        // the first and last 100 ms
+        let frequency = |sound: &Sound| {
+            let frames = sound.frames();
+            let window = &frames[4410..frames.len() - 4410];
+            let crossings = window
+                .windows(2)
+                .filter(|pair| pair[0].left < 0.0 && pair[1].left >= 0.0)
+                .count();
+            crossings as f32 / (window.len() as f32 / 44100.0)
+        };
+
+        let source = sine(440.0);
+        for (semitones, expected) in [(12.0, 880.0), (-12.0, 220.0), (7.0, 659.26)] {
+            let shifted = source.pitch_shifted(semitones);
+            assert!(shifted.frame_count().abs_diff(source.frame_count()) <= 1);
+            assert_eq!(shifted.sample_rate(), 44100);
+            let frequency = frequency(&shifted);
+            assert!(
+                (frequency / expected - 1.0).abs() < 0.02,
+                "{semitones}: {frequency}"
+            );
+        }
+    }
+
+    #[test]
+    fn tremolo_oscillates_at_its_rate() {
+        let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &[1.0; 3000]);
+        sound.set_lfo(LfoTarget::Volume, Lfo::new(LfoRate::Hz(5.0), 1.0));
+        let levels: Vec<f32> = render(&mut sound, SAMPLE_RATE, 2000)
+            .iter()
+            .map(|frame| frame.left)
+            .collect();
+
+        // the volume dips to 0 and is back at 1 once per cycle
+        let (min, max) = levels.iter().fold((1.0f32, 0.0f32), |(min, max), &level| {
+            (min.min(level), max.max(level))
+        });
+        assert!(min < 0.01 && max > 0.99, "{min} {max}");
+        // 5 Hz is one cycle every 200 frames
+        let crossings: Vec<usize> = (1..levels.len())
+            .filter(|&i| levels[i - 1] < 0.5 && levels[i] >= 0.5)
+            .collect();
+        assert!(crossings.len() >= 9);
+        for pair in crossings.windows(2) {
+            assert_eq!(pair[1] - pair[0], 200, "{crossings:?}");
+        }
+
+        // tempo synced: one cycle per beat at 150 bpm is 2.5 Hz
+        let rate = LfoRate::Beats {
+            bpm: 150.0,
+            beats: 1.0,
+        };
+        assert_eq!(rate.hz(), 2.5);
+    }
+
+    #[test]
+    fn playback_state_round_trip() {
+        let mut sound = stereo_ramp(2000);
+        sound.set_volume(0.8);
+        sound.set_playback_rate(PlaybackRate::Factor(0.75));
+        sound.set_loop_index(100..=900);
+        sound.set_loop_enabled(true);
+        sound.add_command(Command::new(Change::Volume(0.2), Easing::Linear, 0.5, 0.3));
+        sound.add_command(Command::new(Change::Panning(0.9), Easing::Linear, 0.1, 0.6));
+        let fresh = sound.clone();
+
+        render(&mut sound, SAMPLE_RATE, 300);
+        let state = sound.playback_state();
+        let expected = render(&mut sound, SAMPLE_RATE, 2000);
+
+        let mut restored = fresh;
+        restored.restore_playback_state(&state).unwrap();
+        let continued = render(&mut restored, SAMPLE_RATE, 2000);
+        // the resampler is primed with the gain at the time of the save, while
+        // the panning tween is running, so the first frames differ slightly
+        for (i, (a, b)) in expected.iter().zip(&continued).enumerate() {
+            let tolerance = if i < 4 { 1e-3 } else { 1e-6 };
+            assert!((a.left - b.left).abs() < tolerance, "frame {i}");
+            assert!((a.right - b.right).abs() < tolerance, "frame {i}");
+        }
+
+        // a sound with different audio data, with or without the same length
+        let mut shorter = stereo_ramp(1999);
+        let mut other = Sound::from_frames(SAMPLE_RATE, &[Frame::new(0.5, 0.5); 2000]);
+        for sound in [&mut shorter, &mut other] {
+            let before = sound.playback_state();
+            assert!(matches!(
+                sound.restore_playback_state(&state),
+                Err(KaError::PlaybackStateMismatch)
+            ));
+            assert_eq!(sound.playback_state().index, before.index);
+        }
+    }
+
+    #[test]
+    fn paused_seek_reports_the_new_position() {
+        let mut sound = stereo_ramp(1000);
+        render(&mut sound, SAMPLE_RATE, 100);
+        sound.pause();
+
+        sound.seek_to(0.5);
+        assert!((sound.position_seconds() - 0.5).abs() < 1e-9);
+        sound.seek_by(-0.2);
+        assert!((sound.position_seconds() - 0.3).abs() < 1e-9);
+
+        // rendering while paused doesn't move the position
+        render(&mut sound, SAMPLE_RATE, 50);
+        assert!((sound.position_seconds() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reverse_region_flips_only_the_region() {
+        let mono: Vec<f32> = (0..100).map(|i| i as f32 / 100.0).collect();
+        for sound in [
+            stereo_ramp(100),
+            Sound::from_mono_samples(SAMPLE_RATE, &mono),
+        ] {
+            let original = sound.frames();
+            let reversed = sound.reverse_region(30..=59).frames();
+            assert_eq!(reversed[..30], original[..30]);
+            assert_eq!(reversed[60..], original[60..]);
+            assert!(reversed[30..60].iter().eq(original[30..60].iter().rev()));
+
+            // the end is clamped to the last frame
+            let clamped = sound.reverse_region(90..=500).frames();
+            assert_eq!(clamped[..90], original[..90]);
+            assert!(clamped[90..].iter().eq(original[90..].iter().rev()));
+
+            let (start, end) = (70, 40);
+            assert_eq!(sound.reverse_region(start..=end).frames(), original);
+            assert_eq!(sound.reverse_region(200..=300).frames(), original);
+        }
+    }
+
+    #[test]
+    fn mono_compatibility_of_an_out_of_phase_sound() {
+        let samples: Vec<[f32; 2]> = sine(440.0)
+            .frames()
+            .iter()
+            .map(|frame| [frame.left, -frame.left])
+            .collect();
+        let inverted = Sound::from_stereo_samples(44100, &samples);
+        let report = inverted.mono_compatibility();
+        assert!(report.correlation < -0.99, "{report:?}");
+        assert_eq!(report.level_drop_db, f32::INFINITY);
+
+        let report = sine(440.0).mono_compatibility();
+        assert!(report.correlation > 0.99, "{report:?}");
+        assert!(report.level_drop_db.abs() < 0.01, "{report:?}");
+
+        // the live meter of the renderer agrees
+        let mixer = crate::RecordMixer::new();
+        mixer.play(inverted);
+        mixer.render_region(44100, 0.0, 0.5);
+        assert!(mixer.correlation() < -0.99, "{}", mixer.correlation());
+    }
+
+    #[test]
+    fn user_data_follows_the_sound() {
+        #[derive(Debug, PartialEq)]
+        struct Owner(u32);
+
+        let plain = stereo_ramp(100);
+        let mut sound = plain.clone();
+        sound.set_user_data(Arc::new(Owner(7)));
+        assert_eq!(sound.user_data::<Owner>().as_deref(), Some(&Owner(7)));
+        // a different type isn't returned, and comparing ignores the data
+        assert!(sound.user_data::<u32>().is_none());
+        assert_eq!(sound, plain);
+
+        // the mixer can find the sounds of an owner while they play
+        let mut mixer = crate::Mixer::new();
+        mixer.play(plain);
+        let handle = mixer.play(sound.clone());
+        mixer.next_frame(SAMPLE_RATE);
+        let mut owned = Vec::new();
+        mixer.for_each_sound(|playing| {
+            if playing
+                .user_data::<Owner>()
+                .is_some_and(|owner| owner.0 == 7)
+            {
+                owned.push(playing.clone());
+            }
+        });
+        assert_eq!(owned.len(), 1);
+        assert!(owned[0].ptr_eq(&handle));
+
+        handle.clear_user_data();
+        assert!(handle.user_data::<Owner>().is_none());
+        // clones made before keep their data
+        assert!(sound.user_data::<Owner>().is_some());
+    }
+
+    #[test]
+    fn pan_laws_keep_the_power_constant() {
+        let db = |gain: f32| 20.0 * gain.log10();
+        for (law, center_db, power) in [
+            (PanLaw::CenterUnity, 0.0, 2.0),
+            (PanLaw::EqualPower, -3.0, 1.0),
+        ] {
+            // the opposite channel is silent at the extremes
+            let left = Frame::from_mono(1.0).panned_with(0.0, law);
+            let right = Frame::from_mono(1.0).panned_with(1.0, law);
+            assert_eq!((left.right, right.left), (0.0, 0.0), "{law:?}");
+            assert!(
+                (db(left.left) - center_db - 3.0).abs() < 0.02,
+                "{law:?}: {left:?}"
+            );
+
+            let center = Frame::from_mono(1.0).panned_with(0.5, law);
+            assert_eq!(center.left, center.right);
+            assert!(
+                (db(center.left) - center_db).abs() < 0.02,
+                "{law:?}: {center:?}"
+            );
+
+            for i in 0..=20 {
+                let frame = Frame::from_mono(1.0).panned_with(i as f32 / 20.0, law);
+                let sum = frame.left * frame.left + frame.right * frame.right;
+                assert!((sum - power).abs() < 1e-5, "{law:?} {i}: {frame:?}");
+            }
+        }
+
+        // the sound applies its pan law
+        let mut sound = Sound::from_mono_samples(SAMPLE_RATE, &[0.5; 100]);
+        sound.set_pan_law(PanLaw::EqualPower);
+        let frame = render(&mut sound, SAMPLE_RATE, 10)[5];
+        assert!((frame.left - 0.5 * FRAC_1_SQRT_2).abs() < 1e-6, "{frame:?}");
+        assert_eq!(frame.left, frame.right);
+    }
+
+    #[test]
+    fn skipped_silence_continues_seamlessly() {
+        let prepare = |volume: f32| {
+            let mut sound = sine(440.0);
+            sound.set_playback_rate(PlaybackRate::Factor(1.3));
+            sound.set_volume(volume);
+            sound
+        };
+        // the silent sound skips rendering, the reference renders normally
+        let mut silent = prepare(0.0);
+        let mut reference = prepare(1.0);
+        render(&mut silent, 48000, 1000);
+        render(&mut reference, 48000, 1000);
+        assert!(silent.skipping_silence);
+        assert_eq!(silent.index(), reference.index());
+
+        // once audible, the primed resampler outputs the same frames
+        silent.set_volume(1.0);
+        assert_eq!(
+            render(&mut silent, 48000, 1000),
+            render(&mut reference, 48000, 1000)
+        );
     }
 }
@@ -0,0 +1,394 @@
+use crate::{Frame, Renderer, RendererHandle};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Return the bits of both channels of a frame, so it can be stored in an
+/// atomic.
+#[inline]
+fn pack_frame(frame: Frame) -> u64 {
+    frame.left.to_bits() as u64 | (frame.right.to_bits() as u64) << 32
+}
+
+/// Return the frame stored by [`pack_frame`].
+#[inline]
+fn unpack_frame(bits: u64) -> Frame {
+    Frame::new(
+        f32::from_bits(bits as u32),
+        f32::from_bits((bits >> 32) as u32),
+    )
+}
+
+/// Bounded single-producer, single-consumer ring of frames shared between a
+/// [`StreamingSource`] and its producers. The consumer never locks.
+#[derive(Debug)]
+struct RingBuffer {
+    /// Frames in the ring, see [`pack_frame`].
+    slots: Box<[AtomicU64]>,
+    /// Amount of frames read since the ring was created. Only written by
+    /// the consumer.
+    read: AtomicUsize,
+    /// Amount of frames written since the ring was created. Only written
+    /// while `push_lock` is held.
+    written: AtomicUsize,
+    /// Lets producers on different threads take turns. Only producers lock
+    /// it.
+    push_lock: Mutex<()>,
+    /// Amount of times the source ran out of frames.
+    underruns: AtomicU64,
+    /// Whether the source is currently out of frames.
+    starved: AtomicBool,
+}
+
+impl RingBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity).map(|_| AtomicU64::new(0)).collect(),
+            read: AtomicUsize::new(0),
+            written: AtomicUsize::new(0),
+            push_lock: Mutex::new(()),
+            underruns: AtomicU64::new(0),
+            // don't count an underrun before the first frames are pushed
+            starved: AtomicBool::new(true),
+        }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        let read = self.read.load(Ordering::Acquire);
+        let written = self.written.load(Ordering::Acquire);
+        written.wrapping_sub(read).min(self.slots.len())
+    }
+
+    /// Write up to `count` frames returned by `next_frame`, stopping when
+    /// the ring is full. Returns the amount of frames that were written.
+    fn push_with(&self, count: usize, mut next_frame: impl FnMut() -> Frame) -> usize {
+        let _guard = self.push_lock.lock();
+        let written = self.written.load(Ordering::Relaxed);
+        let free = self.slots.len() - written.wrapping_sub(self.read.load(Ordering::Acquire));
+        let count = count.min(free);
+        for i in 0..count {
+            let slot = &self.slots[written.wrapping_add(i) % self.slots.len()];
+            slot.store(pack_frame(next_frame()), Ordering::Relaxed);
+        }
+        // publish the frames to the consumer
+        self.written
+            .store(written.wrapping_add(count), Ordering::Release);
+        count
+    }
+
+    /// Take the oldest frame out of the ring.
+    fn pop(&self) -> PopResult {
+        let read = self.read.load(Ordering::Relaxed);
+        if read == self.written.load(Ordering::Acquire) {
+            if !self.starved.swap(true, Ordering::Relaxed) {
+                self.underruns.fetch_add(1, Ordering::Relaxed);
+            }
+            return PopResult::Underrun;
+        }
+        let frame = unpack_frame(self.slots[read % self.slots.len()].load(Ordering::Relaxed));
+        // hand the slot back to the producers
+        self.read.store(read.wrapping_add(1), Ordering::Release);
+        self.starved.store(false, Ordering::Relaxed);
+        PopResult::Frame(frame)
+    }
+}
+
+/// Result of pushing a frame with [`StreamingProducer::push_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushResult {
+    /// The frame was added to the buffer.
+    Pushed,
+    /// The buffer is full, so the frame was dropped.
+    Full,
+}
+
+/// Result of taking a frame with [`StreamingSource::pop_frame`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PopResult {
+    /// The oldest buffered frame.
+    Frame(Frame),
+    /// The buffer is empty. The source plays silence and counts an
+    /// underrun.
+    Underrun,
+}
+
+/// A [`Renderer`] that plays frames pushed from another thread with a
+/// [`StreamingProducer`]. Useful for feeding audio from an external
+/// synthesizer or decoder into a [`crate::Backend`].
+///
+/// The frames are expected to be at the sample rate of the stream. If the
+/// source runs out of frames, it outputs silence and counts an underrun.
+///
+/// Frames are passed through a bounded ring that the audio thread reads
+/// without locking. Only one clone of the source should be rendered.
+///
+/// # Example
+///
+/// ```ignore
+/// let source = StreamingSource::new(4096);
+/// let producer = source.producer();
+/// std::thread::spawn(move || loop {
+///     producer.push(&generate_frames());
+/// });
+/// backend.start_audio_thread(Device::Default, StreamSettings::default(), source.into())?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct StreamingSource(Arc<RingBuffer>);
+
+/// Pushes frames into a [`StreamingSource`]. This type can be cheaply
+/// cloned and sent to other threads.
+#[derive(Debug, Clone)]
+pub struct StreamingProducer(Arc<RingBuffer>);
+
+impl StreamingSource {
+    /// Create a new [`StreamingSource`] that can buffer up to `capacity`
+    /// frames.
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(RingBuffer::new(capacity)))
+    }
+
+    /// Return a producer that pushes frames into this source.
+    #[inline]
+    pub fn producer(&self) -> StreamingProducer {
+        StreamingProducer(self.0.clone())
+    }
+
+    /// Take the oldest buffered frame. Rendering the source does this for
+    /// every frame.
+    #[inline]
+    pub fn pop_frame(&mut self) -> PopResult {
+        self.0.pop()
+    }
+
+    /// Return how many times the source ran out of frames. Consecutive
+    /// silent frames count as one underrun.
+    #[inline]
+    pub fn underruns(&self) -> u64 {
+        self.0.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Return the amount of buffered frames.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Return whether no frames are buffered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the maximum amount of frames the source can buffer.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.0.slots.len()
+    }
+}
+
+impl StreamingProducer {
+    /// Push frames to the source. If the buffer is full, the remaining frames
+    /// are dropped. Returns the amount of frames that were pushed.
+    pub fn push(&self, frames: &[Frame]) -> usize {
+        let mut frames = frames.iter();
+        self.0.push_with(frames.len(), || *frames.next().unwrap())
+    }
+
+    /// Push a single frame to the source.
+    #[inline]
+    pub fn push_frame(&self, frame: Frame) -> PushResult {
+        if self.push(&[frame]) == 1 {
+            PushResult::Pushed
+        } else {
+            PushResult::Full
+        }
+    }
+
+    /// Return how many frames can be pushed before the buffer is full.
+    #[inline]
+    pub fn free_space(&self) -> usize {
+        self.0.slots.len() - self.0.len()
+    }
+}
+
+impl Renderer for StreamingSource {
+    fn next_frame(&mut self, _sample_rate: u32) -> Frame {
+        match self.pop_frame() {
+            PopResult::Frame(frame) => frame,
+            PopResult::Underrun => Frame::ZERO,
+        }
+    }
+}
+
+/// A [`Renderer`] that plays frames rendered ahead of time by another
+/// renderer. Instead of the backend pulling frames from the renderer in real
+/// time, the application renders them with [`BufferedRenderer::render_ahead`],
+/// for example once per frame of its main loop, so audio stays in sync with
+/// an emulated machine or a fixed timestep.
+///
+/// Rendered frames wait in a bounded buffer, like the frames of a
+/// [`StreamingSource`]. If the buffer runs empty, the backend plays silence
+/// and an underrun is counted. Clones share the same buffer and renderer.
+///
+/// # Example
+///
+/// ```ignore
+/// let mixer = Mixer::new();
+/// let buffered = BufferedRenderer::new(mixer.renderer.clone(), 48000, 4096);
+/// backend.start_audio_thread(Device::Default, StreamSettings::default(), buffered.clone().into())?;
+///
+/// loop {
+///     emulate_frame();
+///     buffered.render_ahead(800);
+/// }
+/// ```
+#[derive(Clone)]
+pub struct BufferedRenderer<R: Renderer> {
+    /// The renderer that renders the frames.
+    renderer: RendererHandle<R>,
+    /// Buffer of frames that were rendered, but not played yet.
+    source: StreamingSource,
+    /// Sample rate the frames are rendered at.
+    sample_rate: Arc<AtomicU32>,
+}
+
+impl<R: Renderer> BufferedRenderer<R> {
+    /// Create a new [`BufferedRenderer`] that renders frames with `renderer`
+    /// and can buffer up to `capacity` frames.
+    ///
+    /// Frames are rendered at `sample_rate` until the backend requests the
+    /// first frame, then at the sample rate of the stream.
+    pub fn new(renderer: RendererHandle<R>, sample_rate: u32, capacity: usize) -> Self {
+        Self {
+            renderer,
+            source: StreamingSource::new(capacity),
+            sample_rate: Arc::new(AtomicU32::new(sample_rate)),
+        }
+    }
+
+    /// Render up to `frames` frames into the buffer. If the buffer doesn't
+    /// have enough space, only the frames that fit are rendered. Returns the
+    /// amount of frames that were rendered.
+    pub fn render_ahead(&self, frames: usize) -> usize {
+        // frames are rendered straight into the buffer. the audio thread
+        // doesn't lock the renderer, so it can be held while pushing
+        let sample_rate = self.sample_rate();
+        let mut renderer = self.renderer.guard();
+        self.source
+            .0
+            .push_with(frames, || renderer.next_frame(sample_rate))
+    }
+
+    /// Return the handle of the renderer that renders the frames.
+    #[inline]
+    pub fn renderer(&self) -> &RendererHandle<R> {
+        &self.renderer
+    }
+
+    /// Return the sample rate the frames are rendered at.
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Return how many times the buffer ran empty. Consecutive silent frames
+    /// count as one underrun.
+    #[inline]
+    pub fn underruns(&self) -> u64 {
+        self.source.underruns()
+    }
+
+    /// Return the amount of buffered frames.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.source.len()
+    }
+
+    /// Return whether no frames are buffered.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+
+    /// Return how many frames can be rendered before the buffer is full.
+    #[inline]
+    pub fn free_space(&self) -> usize {
+        self.source.capacity() - self.source.len()
+    }
+}
+
+impl<R: Renderer> Renderer for BufferedRenderer<R> {
+    fn next_frame(&mut self, sample_rate: u32) -> Frame {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+        self.source.next_frame(sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(i: usize) -> Frame {
+        Frame::new(i as f32, -(i as f32))
+    }
+
+    #[test]
+    fn frames_are_played_in_order() {
+        let mut source = StreamingSource::new(8);
+        let producer = source.producer();
+
+        // wrap around the ring a few times
+        let mut next = 0;
+        for round in 0..5 {
+            let frames: Vec<Frame> = (next..next + 6).map(frame).collect();
+            assert_eq!(producer.push(&frames), 6, "round {round}");
+            assert_eq!(source.len(), 6);
+            for _ in 0..6 {
+                assert_eq!(source.next_frame(48000), frame(next));
+                next += 1;
+            }
+        }
+        assert!(source.is_empty());
+        assert_eq!(source.underruns(), 0);
+    }
+
+    #[test]
+    fn underrun_plays_silence() {
+        let mut source = StreamingSource::new(4);
+        let producer = source.producer();
+
+        // nothing was pushed yet, so this isn't an underrun
+        assert_eq!(source.pop_frame(), PopResult::Underrun);
+        assert_eq!(source.underruns(), 0);
+
+        producer.push(&[frame(1)]);
+        assert_eq!(source.next_frame(48000), frame(1));
+        for _ in 0..3 {
+            assert_eq!(source.next_frame(48000), Frame::ZERO);
+        }
+        assert_eq!(source.underruns(), 1);
+
+        producer.push(&[frame(2)]);
+        assert_eq!(source.pop_frame(), PopResult::Frame(frame(2)));
+        assert_eq!(source.pop_frame(), PopResult::Underrun);
+        assert_eq!(source.underruns(), 2);
+    }
+
+    #[test]
+    fn push_is_refused_when_full() {
+        let mut source = StreamingSource::new(4);
+        let producer = source.producer();
+
+        let frames: Vec<Frame> = (0..6).map(frame).collect();
+        assert_eq!(producer.push(&frames), 4);
+        assert_eq!(producer.free_space(), 0);
+        assert_eq!(producer.push_frame(frame(9)), PushResult::Full);
+
+        // the frames that didn't fit were dropped, not the buffered ones
+        assert_eq!(source.next_frame(48000), frame(0));
+        assert_eq!(producer.push_frame(frame(9)), PushResult::Pushed);
+        let played: Vec<Frame> = (0..4).map(|_| source.next_frame(48000)).collect();
+        assert_eq!(played, [frame(1), frame(2), frame(3), frame(9)]);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{Change, Command, Easing, Sound, SoundHandle};
+use std::time::Duration;
+
+/// When a music transition switches to the new sound. The beat grid is
+/// defined by [`TransitionRule::bpm`] and [`TransitionRule::first_beat`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransitionSync {
+    /// Switch on the next rendered frame (default).
+    #[default]
+    Immediate,
+    /// Switch on the next beat of the old sound.
+    NextBeat,
+    /// Switch on the first beat of the next bar of the old sound.
+    NextBar {
+        /// Amount of beats in a bar.
+        beats_per_bar: u32,
+    },
+    /// Switch when the old sound reaches a marker. If the marker doesn't
+    /// exist or the sound is already past it, switch immediately. See
+    /// [`Sound::set_marker`].
+    AtMarker(u32),
+}
+
+/// Where the new sound of a music transition starts playing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransitionEntry {
+    /// Start at a position (in seconds).
+    Offset(f64),
+    /// Start at the position the old sound is at when switching, for tracks
+    /// that share the same structure.
+    MatchPosition,
+}
+
+impl Default for TransitionEntry {
+    fn default() -> Self {
+        Self::Offset(0.0)
+    }
+}
+
+/// Describes a music transition, see [`crate::Mixer::transition`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TransitionRule {
+    /// When to switch to the new sound.
+    pub sync: TransitionSync,
+    /// Tempo of the old sound in beats per minute.
+    pub bpm: f64,
+    /// Position (in seconds) of the first beat in the old sound.
+    pub first_beat: f64,
+    /// Where the new sound starts playing.
+    pub entry: TransitionEntry,
+    /// How long the old sound fades out and the new sound fades in. If zero,
+    /// the sounds are switched without a crossfade.
+    pub crossfade: Duration,
+    /// Easing of the crossfade.
+    pub easing: Easing,
+}
+
+impl Default for TransitionRule {
+    fn default() -> Self {
+        Self {
+            sync: TransitionSync::Immediate,
+            bpm: 120.0,
+            first_beat: 0.0,
+            entry: TransitionEntry::default(),
+            crossfade: Duration::ZERO,
+            easing: Easing::Linear,
+        }
+    }
+}
+
+impl TransitionRule {
+    /// Return the position (in seconds) of the old sound where the switch
+    /// should happen, given its current position.
+    fn switch_position(&self, sound: &Sound) -> f64 {
+        let position = sound.position_seconds();
+        let beat = 60.0 / self.bpm;
+        let next_on_grid = |length: f64| {
+            if length <= 0.0 || !length.is_finite() {
+                return position;
+            }
+            let start = self.first_beat.min(position);
+            start + ((position - start) / length).ceil() * length
+        };
+
+        match self.sync {
+            TransitionSync::Immediate => position,
+            TransitionSync::NextBeat => next_on_grid(beat),
+            TransitionSync::NextBar { beats_per_bar } => next_on_grid(beat * beats_per_bar as f64),
+            TransitionSync::AtMarker(id) => sound
+                .marker(id)
+                .filter(|&marker| marker >= position)
+                .unwrap_or(position),
+        }
+    }
+}
+
+/// A music transition waiting for its sync point. Executed by the
+/// [`crate::DefaultRenderer`].
+#[derive(Debug, Clone)]
+pub(crate) struct PendingTransition {
+    from: SoundHandle,
+    to: SoundHandle,
+    rule: TransitionRule,
+    /// Position (in seconds) of the old sound where the switch happens.
+    switch_at: f64,
+    /// Position of the old sound on the last frame, used to detect loops.
+    last_position: f64,
+}
+
+impl PendingTransition {
+    /// Prepare a transition from the current position of `from`.
+    pub(crate) fn new(from: SoundHandle, to: SoundHandle, rule: TransitionRule) -> Self {
+        let (switch_at, last_position) = {
+            let from = from.guard();
+            (rule.switch_position(&from), from.position_seconds())
+        };
+        Self {
+            from,
+            to,
+            rule,
+            switch_at,
+            last_position,
+        }
+    }
+
+    /// Check whether the old sound reached the sync point. If it did (or if
+    /// it looped around or finished before reaching it), fade out the old
+    /// sound and return the new sound, which should start playing on this
+    /// frame.
+    pub(crate) fn update(&mut self) -> Option<SoundHandle> {
+        let (position, finished) = {
+            let from = self.from.guard();
+            (from.position_seconds(), from.finished())
+        };
+        let looped = position < self.last_position;
+        self.last_position = position;
+        if !finished && !looped && position < self.switch_at {
+            return None;
+        }
+
+        let crossfade = self.rule.crossfade;
+        self.from.stop_with_fade(crossfade, self.rule.easing);
+
+        let mut to = self.to.guard();
+        if !crossfade.is_zero() {
+            let volume = to.set_volume(0.0);
+            to.add_command(Command::new(
+                Change::Volume(volume),
+                self.rule.easing,
+                0.0,
+                crossfade.as_secs_f64(),
+            ));
+        }
+        match self.rule.entry {
+            TransitionEntry::Offset(offset) => to.cue(offset),
+            TransitionEntry::MatchPosition => to.cue(position),
+        }
+        drop(to);
+        Some(self.to.clone())
+    }
+}